@@ -0,0 +1,257 @@
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::future::Future;
+use std::path::Path;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use tokio::sync::Mutex;
+
+use crate::error::Error;
+use crate::error::Result;
+
+/// tokens are refreshed the time before they expire.
+const REFRESH_BEFORE_EXPIRY: Duration = Duration::from_secs(30);
+
+/// Bearer token like a JWT, see [`crate::Client::with_token_refresh`].
+#[derive(Clone, PartialEq, Eq)]
+pub struct Token {
+	pub value: String,
+	/// the token is refreshed before it expires, `None` if it's refreshed only after server rejects
+	/// it.
+	pub expires_at: Option<SystemTime>,
+}
+
+impl Token {
+	pub fn new(value: impl Into<String>) -> Token {
+		Token { value: value.into(), expires_at: None }
+	}
+
+	pub fn with_expires_at(mut self, expires_at: SystemTime) -> Self {
+		self.expires_at = Some(expires_at);
+		self
+	}
+
+	pub fn with_expires_in(self, expires_in: Duration) -> Self {
+		self.with_expires_at(SystemTime::now() + expires_in)
+	}
+
+	fn expires_soon(&self) -> bool {
+		self.expires_at.is_some_and(|at| at <= SystemTime::now() + REFRESH_BEFORE_EXPIRY)
+	}
+}
+
+impl Debug for Token {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("Token").field("expires_at", &self.expires_at).finish_non_exhaustive()
+	}
+}
+
+type RefreshFuture = Pin<Box<dyn Future<Output = Result<Token>> + Send>>;
+type RefreshFn = Arc<dyn Fn() -> RefreshFuture + Send + Sync>;
+
+/// Authentication of requests, user and password are kept by client.
+#[derive(Debug, Clone, Default)]
+pub(crate) enum Auth {
+	/// `X-ClickHouse-User` and `X-ClickHouse-Key` headers.
+	#[default]
+	Password,
+	/// user authenticated by the certificate of client set by [`crate::TlsOptions`].
+	SslCertificate,
+	/// `Authorization: Bearer` header.
+	Bearer(Arc<BearerToken>),
+}
+
+impl Auth {
+	/// headers of requests, the token is refreshed if it's about to expire.
+	pub(crate) async fn headers(
+		&self,
+		user: Option<&str>,
+		password: Option<&str>,
+	) -> Result<Vec<(&'static str, String)>> {
+		let mut headers = Vec::with_capacity(2);
+		if let (Some(user), false) = (user, matches!(self, Auth::Bearer(_))) {
+			headers.push(("X-ClickHouse-User", user.to_owned()));
+		}
+		match self {
+			Auth::Password => headers.extend(password.map(|password| ("X-ClickHouse-Key", password.to_owned()))),
+			Auth::SslCertificate => headers.push(("X-ClickHouse-SSL-Certificate-Auth", "on".to_owned())),
+			Auth::Bearer(token) => headers.push(("Authorization", format!("Bearer {}", token.get().await?))),
+		}
+		Ok(headers)
+	}
+
+	/// drop the token rejected by server, return true if a new token can be fetched.
+	pub(crate) async fn invalidate(&self, headers: &[(&'static str, String)]) -> bool {
+		let Auth::Bearer(token) = self else {
+			return false;
+		};
+		let rejected = headers.iter().find(|(name, _)| *name == "Authorization");
+		token.invalidate(rejected.and_then(|(_, value)| value.strip_prefix("Bearer ")).unwrap_or_default()).await
+	}
+}
+
+/// Bearer token shared by clones of client.
+pub(crate) struct BearerToken {
+	refresh: Option<RefreshFn>,
+	token: Mutex<Option<Token>>,
+}
+
+impl Debug for BearerToken {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("BearerToken").field("refresh", &self.refresh.is_some()).finish_non_exhaustive()
+	}
+}
+
+impl BearerToken {
+	pub(crate) fn fixed(token: Token) -> BearerToken {
+		BearerToken { refresh: None, token: Mutex::new(Some(token)) }
+	}
+
+	pub(crate) fn refreshed<F, Fut>(refresh: F) -> BearerToken
+	where
+		F: Fn() -> Fut + Send + Sync + 'static,
+		Fut: Future<Output = Result<Token>> + Send + 'static,
+	{
+		let refresh: RefreshFn = Arc::new(move || Box::pin(refresh()));
+		BearerToken { refresh: Some(refresh), token: Mutex::new(None) }
+	}
+
+	/// token read from the file each time it's refreshed, e.g. a token mounted by kubernetes.
+	pub(crate) fn from_file(path: PathBuf) -> BearerToken {
+		BearerToken::refreshed(move || {
+			let path = path.clone();
+			async move { Ok(Token::new(read_secret(&path)?)) }
+		})
+	}
+
+	async fn get(&self) -> Result<String> {
+		let mut token = self.token.lock().await;
+		let expired = match token.as_ref() {
+			Some(token) => token.expires_soon(),
+			None => true,
+		};
+		if let (true, Some(refresh)) = (expired, &self.refresh) {
+			*token = Some(refresh().await?);
+		}
+		token.as_ref().map(|token| token.value.clone()).ok_or_else(|| Error::AuthError("no token".to_owned()))
+	}
+
+	/// the token may be refreshed already by a concurrent request.
+	async fn invalidate(&self, rejected: &str) -> bool {
+		let mut token = self.token.lock().await;
+		if self.refresh.is_some() && token.as_ref().is_some_and(|token| token.value == rejected) {
+			*token = None;
+		}
+		self.refresh.is_some()
+	}
+}
+
+/// read password or token from the file, trailing newline is trimmed.
+pub(crate) fn read_secret(path: &Path) -> Result<String> {
+	let secret = std::fs::read_to_string(path).map_err(|e| Error::AuthError(format!("{}: {}", path.display(), e)))?;
+	Ok(secret.trim_end_matches(['\r', '\n']).to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+	use std::sync::atomic::AtomicU32;
+	use std::sync::atomic::Ordering;
+	use std::sync::Arc;
+	use std::time::Duration;
+
+	use hyper::StatusCode;
+
+	use super::Token;
+	use crate::client::tests::MockServer;
+	use crate::client::tests::Recorded;
+	use crate::client::tests::Reply;
+
+	fn header<'a>(request: &'a Recorded, name: &str) -> Option<&'a str> {
+		request.headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+	}
+
+	/// server accepting only tokens `t2` and later.
+	async fn server() -> MockServer {
+		MockServer::start_with(|request| match header(request, "authorization") {
+			Some("Bearer t1") | Some("Bearer old") => Reply {
+				status: StatusCode::FORBIDDEN,
+				..Reply::ok("Code: 516. DB::Exception: Authentication failed. (AUTHENTICATION_FAILED)")
+			},
+			_ => Reply::ok(""),
+		})
+		.await
+	}
+
+	#[tokio::test]
+	async fn test_auth() {
+		let server = server().await;
+		let client = server.client().with_user("reader").with_password("secret");
+		client.query("SELECT 1").execute().await.unwrap();
+		client.clone().with_ssl_certificate_auth().query("SELECT 1").execute().await.unwrap();
+		client.with_bearer_token("jwt").query("SELECT 1").execute().await.unwrap();
+		let requests = server.requests();
+		let headers = |idx: usize| {
+			["x-clickhouse-user", "x-clickhouse-key", "x-clickhouse-ssl-certificate-auth", "authorization"]
+				.map(|name| header(&requests[idx], name))
+		};
+		assert_eq!([Some("reader"), Some("secret"), None, None], headers(0));
+		assert_eq!([Some("reader"), None, Some("on"), None], headers(1));
+		assert_eq!([None, None, None, Some("Bearer jwt")], headers(2));
+
+		let dir = tempfile::tempdir().unwrap();
+		std::fs::write(dir.path().join("password"), "from file\n").unwrap();
+		let vars = HashMap::from([
+			("CLICKHOUSE_USER", "env_user".to_owned()),
+			("CLICKHOUSE_PASSWORD_FILE", dir.path().join("password").display().to_string()),
+		]);
+		let client = server.client().with_credentials_from(|name| vars.get(name).cloned()).unwrap();
+		client.query("SELECT 1").execute().await.unwrap();
+		let request = server.requests().pop().unwrap();
+		assert_eq!(
+			(Some("env_user"), Some("from file")),
+			(header(&request, "x-clickhouse-user"), header(&request, "x-clickhouse-key"))
+		);
+		let conflict = HashMap::from([("CLICKHOUSE_TOKEN", String::new()), ("CLICKHOUSE_TOKEN_FILE", String::new())]);
+		assert!(server.client().with_credentials_from(|name| conflict.get(name).cloned()).is_err());
+		assert!(server.client().with_password_file(dir.path().join("missing")).is_err());
+	}
+
+	#[tokio::test]
+	async fn test_token_refresh() {
+		let server = server().await;
+		let refreshes = Arc::new(AtomicU32::new(0));
+		let counter = refreshes.clone();
+		let client = server.client().with_user("ignored").with_token_refresh(move || {
+			let n = counter.fetch_add(1, Ordering::SeqCst) + 1;
+			// tokens after t2 expire soon, so they're refreshed before each query.
+			async move {
+				Ok(Token::new(format!("t{}", n)).with_expires_in(Duration::from_secs(if n == 2 { 3600 } else { 10 })))
+			}
+		});
+
+		// t1 is rejected and the query is sent again with t2.
+		client.query("INSERT INTO t VALUES (1)").execute().await.unwrap();
+		client.query("SELECT 1").execute().await.unwrap();
+		assert_eq!(2, refreshes.load(Ordering::SeqCst));
+		let tokens =
+			server.requests().iter().map(|r| header(r, "authorization").unwrap().to_owned()).collect::<Vec<_>>();
+		assert_eq!(vec!["Bearer t1", "Bearer t2", "Bearer t2"], tokens);
+		assert!(server.requests().iter().all(|r| header(r, "x-clickhouse-user").is_none()));
+
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("token");
+		std::fs::write(&path, "old\n").unwrap();
+		let client = server.client().with_token_file(&path);
+		let error = client.query("SELECT 1").execute().await.unwrap_err();
+		assert_eq!(Some("AUTHENTICATION_FAILED"), error.to_string().split(['(', ')']).nth(1));
+		std::fs::write(&path, "new\n").unwrap();
+		client.query("SELECT 1").execute().await.unwrap();
+		assert_eq!(Some("Bearer new"), server.requests().last().and_then(|r| header(r, "authorization")));
+		assert!(server.client().with_token_file(dir.path().join("missing")).query("SELECT 1").execute().await.is_err());
+	}
+}
@@ -0,0 +1,87 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use tokio::sync::Notify;
+
+use crate::client::Client;
+use crate::error::Result;
+
+/// Handle to cancel a running query from another task, see [`crate::Query::cancel_handle`].
+#[derive(Debug, Clone)]
+pub struct CancelHandle {
+	client: Client,
+	state: Arc<CancelState>,
+}
+
+#[derive(Debug)]
+struct CancelState {
+	query_id: String,
+	cancelled: AtomicBool,
+	notify: Notify,
+}
+
+impl CancelHandle {
+	pub(crate) fn new(client: Client, query_id: String) -> CancelHandle {
+		let state = CancelState { query_id, cancelled: AtomicBool::new(false), notify: Notify::new() };
+		CancelHandle { client, state: Arc::new(state) }
+	}
+
+	pub fn query_id(&self) -> &str {
+		&self.state.query_id
+	}
+
+	pub fn is_cancelled(&self) -> bool {
+		self.state.cancelled.load(Ordering::Acquire)
+	}
+
+	/// abort the query, its cursor or execution fails with [`crate::Error::Cancelled`] and `KILL
+	/// QUERY` is sent to server on a separate connection. It's a no-op if the query is already
+	/// cancelled.
+	pub async fn cancel(&self) -> Result<()> {
+		match self.mark_cancelled() {
+			true => self.kill().await,
+			false => Ok(()),
+		}
+	}
+
+	/// cancel in background, used when the cursor is dropped before all rows are read.
+	pub(crate) fn cancel_in_background(&self) {
+		let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+			return;
+		};
+		if self.mark_cancelled() {
+			let handle = self.clone();
+			runtime.spawn(async move {
+				let _ = handle.kill().await;
+			});
+		}
+	}
+
+	/// wait until the query is cancelled.
+	pub(crate) async fn cancelled(&self) {
+		loop {
+			let notified = self.state.notify.notified();
+			if self.is_cancelled() {
+				return;
+			}
+			notified.await;
+		}
+	}
+
+	/// return false if it's already cancelled.
+	fn mark_cancelled(&self) -> bool {
+		let first = !self.state.cancelled.swap(true, Ordering::AcqRel);
+		self.state.notify.notify_waiters();
+		first
+	}
+
+	async fn kill(&self) -> Result<()> {
+		self.client.kill_query(self.query_id()).await
+	}
+}
+
+/// random query id like `0b5a3c2e-7f6d-4e1a-9c8b-2d4f6a8e0c1b`.
+pub(crate) fn generate_query_id() -> String {
+	uuid::Uuid::new_v4().to_string()
+}
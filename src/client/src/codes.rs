@@ -0,0 +1,158 @@
+/// names of well known server exception codes, sorted by code.
+const KNOWN_CODES: &[(i32, &str)] = &[
+	(1, "UNSUPPORTED_METHOD"),
+	(2, "UNSUPPORTED_PARAMETER"),
+	(3, "UNEXPECTED_END_OF_FILE"),
+	(4, "EXPECTED_END_OF_FILE"),
+	(6, "CANNOT_PARSE_TEXT"),
+	(7, "INCORRECT_NUMBER_OF_COLUMNS"),
+	(8, "THERE_IS_NO_COLUMN"),
+	(9, "SIZES_OF_COLUMNS_DOESNT_MATCH"),
+	(10, "NOT_FOUND_COLUMN_IN_BLOCK"),
+	(11, "POSITION_OUT_OF_BOUND"),
+	(12, "PARAMETER_OUT_OF_BOUND"),
+	(13, "SIZES_OF_COLUMNS_IN_TUPLE_DOESNT_MATCH"),
+	(15, "DUPLICATE_COLUMN"),
+	(16, "NO_SUCH_COLUMN_IN_TABLE"),
+	(19, "SIZE_OF_FIXED_STRING_DOESNT_MATCH"),
+	(20, "NUMBER_OF_COLUMNS_DOESNT_MATCH"),
+	(23, "CANNOT_READ_FROM_ISTREAM"),
+	(24, "CANNOT_WRITE_TO_OSTREAM"),
+	(25, "CANNOT_PARSE_ESCAPE_SEQUENCE"),
+	(26, "CANNOT_PARSE_QUOTED_STRING"),
+	(27, "CANNOT_PARSE_INPUT_ASSERTION_FAILED"),
+	(32, "ATTEMPT_TO_READ_AFTER_EOF"),
+	(33, "CANNOT_READ_ALL_DATA"),
+	(34, "TOO_MANY_ARGUMENTS_FOR_FUNCTION"),
+	(35, "TOO_FEW_ARGUMENTS_FOR_FUNCTION"),
+	(36, "BAD_ARGUMENTS"),
+	(37, "UNKNOWN_ELEMENT_IN_AST"),
+	(38, "CANNOT_PARSE_DATE"),
+	(39, "TOO_LARGE_SIZE_COMPRESSED"),
+	(40, "CHECKSUM_DOESNT_MATCH"),
+	(41, "CANNOT_PARSE_DATETIME"),
+	(42, "NUMBER_OF_ARGUMENTS_DOESNT_MATCH"),
+	(43, "ILLEGAL_TYPE_OF_ARGUMENT"),
+	(44, "ILLEGAL_COLUMN"),
+	(46, "UNKNOWN_FUNCTION"),
+	(47, "UNKNOWN_IDENTIFIER"),
+	(48, "NOT_IMPLEMENTED"),
+	(49, "LOGICAL_ERROR"),
+	(50, "UNKNOWN_TYPE"),
+	(51, "EMPTY_LIST_OF_COLUMNS_QUERIED"),
+	(52, "COLUMN_QUERIED_MORE_THAN_ONCE"),
+	(53, "TYPE_MISMATCH"),
+	(55, "STORAGE_REQUIRES_PARAMETER"),
+	(56, "UNKNOWN_STORAGE"),
+	(57, "TABLE_ALREADY_EXISTS"),
+	(58, "TABLE_METADATA_ALREADY_EXISTS"),
+	(59, "ILLEGAL_TYPE_OF_COLUMN_FOR_FILTER"),
+	(60, "UNKNOWN_TABLE"),
+	(62, "SYNTAX_ERROR"),
+	(63, "UNKNOWN_AGGREGATE_FUNCTION"),
+	(69, "ARGUMENT_OUT_OF_BOUND"),
+	(70, "CANNOT_CONVERT_TYPE"),
+	(72, "CANNOT_PARSE_NUMBER"),
+	(73, "UNKNOWN_FORMAT"),
+	(76, "CANNOT_OPEN_FILE"),
+	(78, "UNKNOWN_TYPE_OF_QUERY"),
+	(80, "INCORRECT_QUERY"),
+	(81, "UNKNOWN_DATABASE"),
+	(82, "DATABASE_ALREADY_EXISTS"),
+	(85, "FORMAT_IS_NOT_SUITABLE_FOR_INPUT"),
+	(89, "UNKNOWN_COMPRESSION_METHOD"),
+	(92, "EMPTY_DATA_PASSED"),
+	(95, "CANNOT_READ_FROM_SOCKET"),
+	(96, "CANNOT_WRITE_TO_SOCKET"),
+	(107, "FILE_DOESNT_EXIST"),
+	(108, "NO_DATA_TO_INSERT"),
+	(113, "THERE_IS_NO_SESSION"),
+	(115, "UNKNOWN_SETTING"),
+	(117, "INCORRECT_DATA"),
+	(119, "ENGINE_REQUIRED"),
+	(122, "INCOMPATIBLE_COLUMNS"),
+	(125, "INCORRECT_RESULT_OF_SCALAR_SUBQUERY"),
+	(128, "TOO_LARGE_ARRAY_SIZE"),
+	(131, "TOO_LARGE_STRING_SIZE"),
+	(153, "ILLEGAL_DIVISION"),
+	(158, "TOO_MANY_ROWS"),
+	(159, "TIMEOUT_EXCEEDED"),
+	(160, "TOO_SLOW"),
+	(161, "TOO_MANY_COLUMNS"),
+	(162, "TOO_DEEP_SUBQUERIES"),
+	(164, "READONLY"),
+	(167, "TOO_DEEP_AST"),
+	(168, "TOO_BIG_AST"),
+	(169, "BAD_TYPE_OF_FIELD"),
+	(173, "CANNOT_ALLOCATE_MEMORY"),
+	(174, "CYCLIC_ALIASES"),
+	(179, "MULTIPLE_EXPRESSIONS_FOR_ALIAS"),
+	(181, "ILLEGAL_FINAL"),
+	(184, "ILLEGAL_AGGREGATION"),
+	(190, "SIZES_OF_ARRAYS_DONT_MATCH"),
+	(191, "SET_SIZE_LIMIT_EXCEEDED"),
+	(192, "UNKNOWN_USER"),
+	(193, "WRONG_PASSWORD"),
+	(194, "REQUIRED_PASSWORD"),
+	(195, "IP_ADDRESS_NOT_ALLOWED"),
+	(198, "DNS_ERROR"),
+	(201, "QUOTA_EXCEEDED"),
+	(202, "TOO_MANY_SIMULTANEOUS_QUERIES"),
+	(203, "NO_FREE_CONNECTION"),
+	(206, "ALIAS_REQUIRED"),
+	(207, "AMBIGUOUS_IDENTIFIER"),
+	(209, "SOCKET_TIMEOUT"),
+	(210, "NETWORK_ERROR"),
+	(211, "EMPTY_QUERY"),
+	(215, "NOT_AN_AGGREGATE"),
+	(216, "QUERY_WITH_SAME_ID_IS_ALREADY_RUNNING"),
+	(218, "TABLE_IS_DROPPED"),
+	(219, "DATABASE_NOT_EMPTY"),
+	(225, "NO_ZOOKEEPER"),
+	(236, "ABORTED"),
+	(241, "MEMORY_LIMIT_EXCEEDED"),
+	(242, "TABLE_IS_READ_ONLY"),
+	(243, "NOT_ENOUGH_SPACE"),
+	(252, "TOO_MANY_PARTS"),
+	(285, "TOO_FEW_LIVE_REPLICAS"),
+	(290, "LIMIT_EXCEEDED"),
+	(319, "UNKNOWN_STATUS_OF_INSERT"),
+	(344, "SUPPORT_IS_DISABLED"),
+	(352, "AMBIGUOUS_COLUMN_NAME"),
+	(372, "SESSION_NOT_FOUND"),
+	(373, "SESSION_IS_LOCKED"),
+	(386, "NO_COMMON_TYPE"),
+	(394, "QUERY_WAS_CANCELLED"),
+	(395, "FUNCTION_THROW_IF_VALUE_IS_NON_ZERO"),
+	(396, "TOO_MANY_ROWS_OR_BYTES"),
+	(407, "DECIMAL_OVERFLOW"),
+	(425, "SYSTEM_ERROR"),
+	(439, "CANNOT_SCHEDULE_TASK"),
+	(497, "ACCESS_DENIED"),
+	(516, "AUTHENTICATION_FAILED"),
+];
+
+/// name of server exception code, e.g. `SYNTAX_ERROR` for 62.
+pub fn error_name(code: i32) -> Option<&'static str> {
+	match code {
+		999 => Some("KEEPER_EXCEPTION"),
+		1000 => Some("POCO_EXCEPTION"),
+		1001 => Some("STD_EXCEPTION"),
+		1002 => Some("UNKNOWN_EXCEPTION"),
+		code => KNOWN_CODES.binary_search_by_key(&code, |(code, _)| *code).ok().map(|idx| KNOWN_CODES[idx].1),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_error_name() {
+		assert!(KNOWN_CODES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+		assert_eq!(Some("SYNTAX_ERROR"), error_name(62));
+		assert_eq!(Some("MEMORY_LIMIT_EXCEEDED"), error_name(241));
+		assert_eq!(Some("POCO_EXCEPTION"), error_name(1000));
+		assert_eq!(None, error_name(5));
+	}
+}
@@ -0,0 +1,378 @@
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::client::Client;
+use crate::dsn::http_url;
+use crate::dsn::Dsn;
+use crate::error::Error;
+use crate::error::Result;
+use crate::hosts::LoadBalancing;
+use crate::settings::parse_bool;
+use crate::settings::Settings;
+use crate::tls::TlsOptions;
+
+/// Connection of clickhouse-client config, absent fields are taken from the top level of config.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct ConnectionConfig {
+	/// empty for the top level.
+	pub name: String,
+	pub hostname: Option<String>,
+	/// native port like 9000 or 9440, it's replaced by the HTTP port, see [`Dsn`].
+	pub port: Option<u16>,
+	pub secure: Option<bool>,
+	pub user: Option<String>,
+	pub password: Option<String>,
+	pub database: Option<String>,
+	pub accept_invalid_certificate: Option<bool>,
+}
+
+/// password is redacted.
+impl Debug for ConnectionConfig {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("ConnectionConfig")
+			.field("name", &self.name)
+			.field("hostname", &self.hostname)
+			.field("port", &self.port)
+			.field("secure", &self.secure)
+			.field("user", &self.user)
+			.field("password", &self.password.as_ref().map(|_| "***"))
+			.field("database", &self.database)
+			.field("accept_invalid_certificate", &self.accept_invalid_certificate)
+			.finish()
+	}
+}
+
+/// Config file of clickhouse-client like `~/.clickhouse-client/config.xml` or `config.yaml`.
+///
+/// Connections are picked like clickhouse-client does: by name of `connections_credentials`, or
+/// the connection named after the top level `host` if no name is given. Values can be read from
+/// environment variables by `from_env` attributes, e.g. `<password from_env="CH_PASSWORD"/>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigFile {
+	/// top level `host`, `port`, `user` and so on.
+	pub defaults: ConnectionConfig,
+	/// `connections_credentials`.
+	pub connections: Vec<ConnectionConfig>,
+	/// `openSSL.client`: `caConfig`, `certificateFile`, `privateKeyFile` and `verificationMode`.
+	pub tls: TlsOptions,
+}
+
+impl ConfigFile {
+	/// load XML or YAML config by extension of the path.
+	pub fn load(path: impl AsRef<Path>) -> Result<ConfigFile> {
+		let path = path.as_ref();
+		let content = std::fs::read_to_string(path).map_err(|e| config_error(path.display(), e))?;
+		let root = match path.extension().and_then(|ext| ext.to_str()) {
+			Some("yaml" | "yml") => Node::parse_yaml(&content),
+			_ => Node::parse_xml(&content),
+		};
+		ConfigFile::from_node(&root.map_err(|e| config_error(path.display(), e))?)
+	}
+
+	/// load the first config of clickhouse-client found in `./clickhouse-client.xml`,
+	/// `~/.clickhouse-client/config.xml` and `/etc/clickhouse-client/config.xml`, or `.yaml` and
+	/// `.yml` of them.
+	pub fn load_default() -> Result<Option<ConfigFile>> {
+		let home = std::env::var_os("HOME").map(PathBuf::from);
+		let dirs = [
+			Some((PathBuf::new(), "clickhouse-client")),
+			home.map(|home| (home.join(".clickhouse-client"), "config")),
+			Some((PathBuf::from("/etc/clickhouse-client"), "config")),
+		];
+		let paths = dirs
+			.into_iter()
+			.flatten()
+			.flat_map(|(dir, stem)| ["xml", "yaml", "yml"].map(|ext| dir.join(format!("{}.{}", stem, ext))));
+		paths.into_iter().find(|path| path.is_file()).map(ConfigFile::load).transpose()
+	}
+
+	/// DSN of the named connection, or of the connection named after the top level `host`.
+	pub fn dsn(&self, connection: Option<&str>) -> Result<Dsn> {
+		let default_host = self.defaults.hostname.as_deref().unwrap_or("localhost");
+		let name = connection.unwrap_or(default_host);
+		let found = self.connections.iter().find(|c| c.name == name);
+		if let (Some(name), None) = (connection, found) {
+			return Err(Error::ConfigError(format!("no connection {} in connections_credentials", name)));
+		}
+		let empty = ConnectionConfig::default();
+		let (c, d) = (found.unwrap_or(&empty), &self.defaults);
+
+		let host = match found {
+			Some(found) => found.hostname.as_deref().unwrap_or(&found.name),
+			None => default_host,
+		};
+		let host = match host.contains(':') && !host.starts_with('[') {
+			true => format!("[{}]", host),
+			false => host.to_owned(),
+		};
+		let secure = c.secure.or(d.secure).unwrap_or(false);
+		let address = match c.port.or(d.port) {
+			Some(port) => format!("{}:{}", host, port),
+			None => host,
+		};
+		let skip_verify = c.accept_invalid_certificate.or(d.accept_invalid_certificate);
+		Ok(Dsn {
+			hosts: vec![http_url(&address, secure, true)?],
+			user: c.user.clone().or_else(|| d.user.clone()),
+			password: c.password.clone().or_else(|| d.password.clone()),
+			database: c.database.clone().or_else(|| d.database.clone()),
+			secure,
+			tls: match skip_verify {
+				Some(skip) => self.tls.clone().with_insecure_skip_verify(skip),
+				None => self.tls.clone(),
+			},
+			load_balancing: LoadBalancing::default(),
+			settings: Settings::new(),
+		})
+	}
+
+	/// create client of the connection, see [`ConfigFile::dsn`].
+	pub fn client(&self, connection: Option<&str>) -> Result<Client> {
+		self.dsn(connection)?.client()
+	}
+
+	fn from_node(root: &Node) -> Result<ConfigFile> {
+		let defaults = ConnectionConfig::from_node(root, String::new(), "host")?;
+		let mut connections = Vec::new();
+		for connection in root.children("connections_credentials").flat_map(|node| node.children("connection")) {
+			let name = connection.text("name")?;
+			let name = name.ok_or_else(|| Error::ConfigError("name of connection is not specified".to_owned()))?;
+			connections.push(ConnectionConfig::from_node(connection, name, "hostname")?);
+		}
+
+		let mut tls = TlsOptions::new();
+		if let Some(client) = root.children("openSSL").flat_map(|node| node.children("client")).next() {
+			if let Some(path) = client.text("caConfig")? {
+				tls = tls.with_ca_file(path)?;
+			}
+			if let (Some(cert), Some(key)) = (client.text("certificateFile")?, client.text("privateKeyFile")?) {
+				tls = tls.with_client_cert_file(cert, key)?;
+			}
+			let mode = client.text("verificationMode")?;
+			tls = tls.with_insecure_skip_verify(mode.is_some_and(|mode| mode.eq_ignore_ascii_case("none")));
+		}
+		Ok(ConfigFile { defaults, connections, tls })
+	}
+}
+
+impl ConnectionConfig {
+	fn from_node(node: &Node, name: String, host_key: &str) -> Result<ConnectionConfig> {
+		Ok(ConnectionConfig {
+			name,
+			hostname: node.text(host_key)?,
+			port: node.parse("port")?,
+			secure: node.parse_bool("secure")?,
+			user: node.text("user")?,
+			password: node.text("password")?,
+			database: node.text("database")?,
+			accept_invalid_certificate: node.parse_bool("accept-invalid-certificate")?,
+		})
+	}
+}
+
+/// Element of XML or YAML config, repeated elements are children of the same name.
+#[derive(Debug, Default)]
+struct Node {
+	text: String,
+	/// name of environment variable holding the value.
+	from_env: Option<String>,
+	children: Vec<(String, Node)>,
+}
+
+impl Node {
+	fn parse_xml(content: &str) -> std::result::Result<Node, String> {
+		let document = roxmltree::Document::parse(content).map_err(|e| e.to_string())?;
+		Ok(Node::from_xml(document.root_element()))
+	}
+
+	fn from_xml(element: roxmltree::Node) -> Node {
+		let children = element.children().filter(roxmltree::Node::is_element);
+		Node {
+			text: element.children().filter(roxmltree::Node::is_text).filter_map(|text| text.text()).collect(),
+			from_env: element.attribute("from_env").map(str::to_owned),
+			children: children.map(|child| (child.tag_name().name().to_owned(), Node::from_xml(child))).collect(),
+		}
+	}
+
+	fn parse_yaml(content: &str) -> std::result::Result<Node, String> {
+		let value = serde_yaml::from_str::<serde_yaml::Value>(content).map_err(|e| e.to_string())?;
+		Ok(Node::from_yaml(&value))
+	}
+
+	/// attributes are keys starting with `@`, e.g. `password: {"@from_env": CH_PASSWORD}`.
+	fn from_yaml(value: &serde_yaml::Value) -> Node {
+		use serde_yaml::Value;
+
+		let mut node = Node::default();
+		match value {
+			Value::Mapping(mapping) => {
+				for (key, value) in mapping {
+					let key = scalar(key).unwrap_or_default();
+					match (key.strip_prefix('@'), value) {
+						(Some("from_env"), value) => node.from_env = scalar(value),
+						(Some(_), _) => (),
+						(None, Value::Sequence(items)) => {
+							node.children.extend(items.iter().map(|item| (key.clone(), Node::from_yaml(item))))
+						}
+						(None, value) => node.children.push((key, Node::from_yaml(value))),
+					}
+				}
+			}
+			value => node.text = scalar(value).unwrap_or_default(),
+		}
+		node
+	}
+
+	fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Node> + 'a {
+		self.children.iter().filter(move |(n, _)| n == name).map(|(_, node)| node)
+	}
+
+	/// trimmed text of the last child of the name, `from_env` is resolved.
+	fn text(&self, name: &str) -> Result<Option<String>> {
+		let Some(node) = self.children(name).last() else {
+			return Ok(None);
+		};
+		match &node.from_env {
+			Some(var) => match std::env::var(var) {
+				Ok(value) => Ok(Some(value)),
+				Err(e) => Err(Error::ConfigError(format!("{}: environment variable {}: {}", name, var, e))),
+			},
+			None => Ok(Some(node.text.trim().to_owned())),
+		}
+	}
+
+	fn parse<T: FromStr>(&self, name: &str) -> Result<Option<T>> {
+		let Some(text) = self.text(name)? else {
+			return Ok(None);
+		};
+		text.parse().map(Some).map_err(|_| Error::ConfigError(format!("{}: invalid value {}", name, text)))
+	}
+
+	fn parse_bool(&self, name: &str) -> Result<Option<bool>> {
+		let Some(text) = self.text(name)? else {
+			return Ok(None);
+		};
+		let value = parse_bool(&text).ok_or_else(|| Error::ConfigError(format!("{}: invalid value {}", name, text)))?;
+		Ok(Some(value))
+	}
+}
+
+fn scalar(value: &serde_yaml::Value) -> Option<String> {
+	match value {
+		serde_yaml::Value::String(s) => Some(s.clone()),
+		serde_yaml::Value::Number(n) => Some(n.to_string()),
+		serde_yaml::Value::Bool(b) => Some(b.to_string()),
+		_ => None,
+	}
+}
+
+fn config_error(path: impl std::fmt::Display, e: impl std::fmt::Display) -> Error {
+	Error::ConfigError(format!("{}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::ConfigFile;
+	use crate::Error;
+
+	const XML: &str = r#"<config>
+		<host>ch.internal</host>
+		<user>reader</user>
+		<password from_env="RICKHOUSE_TEST_PASSWORD"/>
+		<connections_credentials>
+			<connection>
+				<name>prod</name>
+				<hostname>prod.example.com</hostname>
+				<port>9440</port>
+				<secure>1</secure>
+				<user>admin</user>
+				<database>analytics</database>
+			</connection>
+			<connection>
+				<name>ch.internal</name>
+				<port>8123</port>
+				<accept-invalid-certificate>true</accept-invalid-certificate>
+			</connection>
+		</connections_credentials>
+		<openSSL><client><verificationMode>none</verificationMode></client></openSSL>
+	</config>"#;
+
+	const YAML: &str = r#"
+host: ch.internal
+user: reader
+password:
+  "@from_env": RICKHOUSE_TEST_PASSWORD
+connections_credentials:
+  connection:
+    - name: prod
+      hostname: prod.example.com
+      port: 9440
+      secure: true
+      user: admin
+      database: analytics
+    - name: ch.internal
+      port: 8123
+      accept-invalid-certificate: true
+openSSL:
+  client:
+    verificationMode: none
+"#;
+
+	#[test]
+	fn test_config_file() {
+		std::env::set_var("RICKHOUSE_TEST_PASSWORD", "secret");
+		let dir = tempfile::tempdir().unwrap();
+		std::fs::write(dir.path().join("config.xml"), XML).unwrap();
+		std::fs::write(dir.path().join("config.yaml"), YAML).unwrap();
+		let xml = ConfigFile::load(dir.path().join("config.xml")).unwrap();
+		let yaml = ConfigFile::load(dir.path().join("config.yaml")).unwrap();
+		assert_eq!(xml, yaml);
+		assert_eq!(2, xml.connections.len());
+		assert!(!format!("{:?}", xml).contains("secret"));
+
+		let prod = xml.dsn(Some("prod")).unwrap();
+		assert_eq!(vec!["https://prod.example.com:8443"], prod.hosts);
+		let credentials = (prod.user.as_deref(), prod.password.as_deref(), prod.database.as_deref());
+		assert_eq!((Some("admin"), Some("secret"), Some("analytics")), credentials);
+		assert!(prod.secure);
+
+		// the connection named after the top level host.
+		let default = xml.dsn(None).unwrap();
+		assert_eq!(vec!["http://ch.internal:8123"], default.hosts);
+		assert_eq!((Some("reader"), None), (default.user.as_deref(), default.database.as_deref()));
+		xml.client(None).unwrap();
+
+		// native port of the secure connection is replaced by the https port.
+		let mut native = ConfigFile::default();
+		native.defaults.port = Some(9000);
+		assert_eq!(vec!["http://localhost:8123"], native.dsn(None).unwrap().hosts);
+		native.defaults.secure = Some(true);
+		assert_eq!(vec!["https://localhost:8443"], native.dsn(None).unwrap().hosts);
+
+		let empty = ConfigFile::default().dsn(None).unwrap();
+		assert_eq!((vec!["http://localhost:8123".to_owned()], None), (empty.hosts, empty.user));
+		assert!(matches!(xml.dsn(Some("staging")), Err(Error::ConfigError(_))));
+
+		let invalid = [
+			("bad.xml", "<config><port>native</port></config>", "port: invalid value native"),
+			(
+				"bad.yaml",
+				"connections_credentials:\n  connection:\n    hostname: a\n",
+				"name of connection is not specified",
+			),
+			("env.xml", "<config><user from_env=\"RICKHOUSE_TEST_MISSING\"/></config>", "user: environment variable"),
+			("broken.xml", "<config>", "broken.xml: "),
+		];
+		for (name, content, reason) in invalid {
+			std::fs::write(dir.path().join(name), content).unwrap();
+			match ConfigFile::load(dir.path().join(name)) {
+				Err(Error::ConfigError(e)) => assert!(e.contains(reason), "{}: {}", name, e),
+				result => panic!("{}: {:?}", name, result),
+			}
+		}
+		assert!(ConfigFile::load(dir.path().join("missing.xml")).is_err());
+	}
+}
@@ -0,0 +1,430 @@
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Wake;
+use std::task::Waker;
+
+use bytes::Buf;
+use bytes::BytesMut;
+use http_body_util::BodyExt;
+use hyper::body::Body;
+use hyper::body::Incoming;
+use rickhouse_common::metadata::check_schema;
+use rickhouse_common::metadata::Metadata;
+use rickhouse_common::metadata::Row;
+use rickhouse_common::metadata::SchemaColumn;
+use rickhouse_common::metadata::Strictness;
+use rickhouse_common::metadata::Tz;
+use rickhouse_common::rowbinary;
+use rickhouse_common::rowbinary::FormatOptions;
+use serde::de::DeserializeOwned;
+
+use crate::cancel::CancelHandle;
+use crate::error::find_exception;
+use crate::error::starts_with_exception;
+use crate::error::Error;
+use crate::error::Result;
+use crate::response::ResponseInfo;
+use crate::response::Summary;
+
+/// Cursor of rows in `RowBinaryWithNamesAndTypes` format, rows are decoded as the body arrives.
+///
+/// `DateTime` columns without timezone are decoded in the timezone of server.
+pub struct RowCursor<T> {
+	body: Incoming,
+	buffer: BytesMut,
+	metadata: Option<Arc<Metadata>>,
+	options: FormatOptions,
+	info: ResponseInfo,
+	cancel: CancelHandle,
+	/// expected columns, the header is checked before rows are decoded.
+	schema: Option<(Vec<SchemaColumn>, Strictness)>,
+	/// body is read to the end.
+	finished: bool,
+	_marker: PhantomData<fn() -> T>,
+}
+
+impl<T> RowCursor<T> {
+	pub(crate) fn new(info: ResponseInfo, body: Incoming, options: FormatOptions, cancel: CancelHandle) -> Self {
+		RowCursor {
+			body,
+			buffer: BytesMut::new(),
+			metadata: None,
+			options,
+			info,
+			cancel,
+			schema: None,
+			finished: false,
+			_marker: PhantomData,
+		}
+	}
+
+	pub(crate) fn with_schema(mut self, columns: Vec<SchemaColumn>, strictness: Strictness) -> Self {
+		self.schema = Some((columns, strictness));
+		self
+	}
+
+	/// handle to cancel the query from another task.
+	pub fn cancel_handle(&self) -> CancelHandle {
+		self.cancel.clone()
+	}
+
+	/// information from response headers.
+	pub fn info(&self) -> &ResponseInfo {
+		&self.info
+	}
+
+	pub fn query_id(&self) -> Option<&str> {
+		self.info.query_id.as_deref()
+	}
+
+	pub fn summary(&self) -> Option<&Summary> {
+		self.info.summary.as_ref()
+	}
+
+	/// columns of result, `None` before the header is read.
+	pub fn metadata(&self) -> Option<&Arc<Metadata>> {
+		self.metadata.as_ref()
+	}
+
+	/// next row as dynamic values, `None` if all rows are read.
+	///
+	/// exception written by server after some rows is returned as [`Error::Server`].
+	pub async fn next_row(&mut self) -> Result<Option<Row>> {
+		if self.cancel.is_cancelled() {
+			return Err(Error::Cancelled(self.cancel.query_id().to_owned()));
+		}
+		let mut finished = false;
+		loop {
+			if !self.buffer.is_empty() {
+				match (starts_with_exception(&self.buffer, self.info.exception_tag.as_deref()), &self.metadata) {
+					(Some(true), _) => return Err(self.read_exception(0).await),
+					// may be the beginning of an exception, wait for more data.
+					(None, _) if !finished => (),
+					(_, None) => {
+						match decode(&mut self.buffer, |buf| rowbinary::read_header_with(buf, &self.options)) {
+							Ok(Some(metadata)) => {
+								let metadata = self.with_server_timezone(metadata);
+								if let Some((columns, strictness)) = &self.schema {
+									check_schema(columns, &metadata, *strictness)?;
+								}
+								self.metadata = Some(Arc::new(metadata));
+								continue;
+							}
+							Ok(None) => (),
+							Err(e) => return Err(self.exception_or(e).await),
+						}
+					}
+					(_, Some(metadata)) => match decode(&mut self.buffer, |buf| rowbinary::read_row(buf, metadata)) {
+						Ok(Some(row)) => return Ok(Some(row)),
+						Ok(None) => (),
+						Err(e) => return Err(self.exception_or(e).await),
+					},
+				}
+			}
+
+			if finished {
+				return match self.buffer.is_empty() {
+					true => Ok(None),
+					false => {
+						let e = Error::BadResponse(format!("{} bytes of incomplete row at the end", self.buffer.len()));
+						Err(self.exception_or(e).await)
+					}
+				};
+			}
+			finished = !self.fill().await?;
+		}
+	}
+
+	/// exception in buffer if any, otherwise `e`, it's checked when rows fail to decode.
+	async fn exception_or(&mut self, e: Error) -> Error {
+		match find_exception(&self.buffer, self.info.exception_tag.as_deref()) {
+			Some(offset) => self.read_exception(offset).await,
+			None => e,
+		}
+	}
+
+	/// read the rest of body and parse the exception at `offset` of buffer.
+	async fn read_exception(&mut self, offset: usize) -> Error {
+		loop {
+			match self.fill().await {
+				Ok(true) => (),
+				Ok(false) => break,
+				Err(e) => return e,
+			}
+		}
+		let err = Error::from_stream_exception(&self.buffer[offset..], self.info.exception_tag.as_deref());
+		self.buffer.clear();
+		err
+	}
+
+	/// read next frame of body into buffer, return false at the end of body.
+	async fn fill(&mut self) -> Result<bool> {
+		loop {
+			let frame = tokio::select! {
+				frame = self.body.frame() => frame,
+				_ = self.cancel.cancelled() => return Err(Error::Cancelled(self.cancel.query_id().to_owned())),
+			};
+			match frame {
+				Some(frame) => {
+					if let Ok(data) = frame?.into_data() {
+						self.buffer.extend_from_slice(&data);
+						return Ok(true);
+					}
+				}
+				None => {
+					self.finished = true;
+					return Ok(false);
+				}
+			}
+		}
+	}
+
+	fn with_server_timezone(&self, metadata: Metadata) -> Metadata {
+		match self.info.timezone.as_deref().and_then(|tz| tz.parse::<Tz>().ok()) {
+			Some(tz) => metadata.into_iter().map(|(name, data_type)| (name, data_type.with_timezone(&tz))).collect(),
+			None => metadata,
+		}
+	}
+}
+
+/// the query is killed if the cursor is dropped before the body is received to the end.
+impl<T> Drop for RowCursor<T> {
+	fn drop(&mut self) {
+		if !self.finished && !self.body.is_end_stream() && !self.skip_received() {
+			self.cancel.cancel_in_background();
+		}
+	}
+}
+
+impl<T> RowCursor<T> {
+	/// skip frames of body received already without waiting, return true if the body ends, e.g.
+	/// the end of chunked body is received after the last row.
+	fn skip_received(&mut self) -> bool {
+		let waker = Waker::from(Arc::new(NoopWaker));
+		let mut cx = Context::from_waker(&waker);
+		loop {
+			match Pin::new(&mut self.body).poll_frame(&mut cx) {
+				Poll::Ready(Some(Ok(_))) => (),
+				Poll::Ready(None) => return true,
+				Poll::Ready(Some(Err(_))) | Poll::Pending => return false,
+			}
+		}
+	}
+}
+
+struct NoopWaker;
+
+impl Wake for NoopWaker {
+	fn wake(self: Arc<Self>) {}
+}
+
+impl<T: DeserializeOwned> RowCursor<T> {
+	/// next row, `None` if all rows are read.
+	pub async fn next(&mut self) -> Result<Option<T>> {
+		match self.next_row().await? {
+			Some(row) => Ok(Some(row.deserialize_into()?)),
+			None => Ok(None),
+		}
+	}
+}
+
+/// decode from the buffered data, `None` if more data is needed.
+fn decode<R>(
+	buffer: &mut BytesMut,
+	decode_fn: impl FnOnce(&mut &[u8]) -> rickhouse_common::Result<R>,
+) -> Result<Option<R>> {
+	let mut data = &buffer[..];
+	match decode_fn(&mut data) {
+		Ok(value) => {
+			let consumed = buffer.len() - data.len();
+			buffer.advance(consumed);
+			Ok(Some(value))
+		}
+		Err(rickhouse_common::Error::NotEnoughData) => Ok(None),
+		Err(e) => Err(e.into()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::sync::Mutex;
+	use std::time::Duration;
+
+	use bytes::BytesMut;
+	use rickhouse_common::metadata::RowSchema;
+	use rickhouse_common::metadata::SchemaColumn;
+	use rickhouse_common::rowbinary;
+	use rickhouse_common::values::value::Value;
+	use rickhouse_derive::Row;
+	use serde::Deserialize;
+	use serde::Serialize;
+
+	use super::decode;
+	use crate::client::tests::MockServer;
+	use crate::client::tests::Reply;
+	use crate::error::Error;
+
+	#[derive(Debug, PartialEq, Serialize, Deserialize, Row)]
+	struct Event {
+		id: u32,
+		#[row(type = "DateTime")]
+		ts: u32,
+	}
+
+	fn body(events: &[Event]) -> Vec<u8> {
+		let columns = Event::columns();
+		let mut body = Vec::new();
+		rowbinary::write_header(&mut body, &columns);
+		for event in events {
+			rowbinary::write_row(&mut body, event, &columns).unwrap();
+		}
+		body
+	}
+
+	#[tokio::test]
+	async fn test_cursor() {
+		let server = MockServer::start_with(|_| {
+			Reply::ok(body(&[Event { id: 1, ts: 1706702400 }, Event { id: 2, ts: 0 }]))
+				.with_header("X-ClickHouse-Query-Id", "q-1")
+				.with_header("X-ClickHouse-Timezone", "Asia/Shanghai")
+				.with_header("X-ClickHouse-Format", "RowBinaryWithNamesAndTypes")
+				.with_header("X-ClickHouse-Progress", r#"{"read_rows":"1","total_rows_to_read":"2"}"#)
+				.with_header("X-ClickHouse-Progress", r#"{"read_rows":"2","total_rows_to_read":"2"}"#)
+				.with_header("X-ClickHouse-Summary", r#"{"read_rows":"2","result_rows":"2"}"#)
+		})
+		.await;
+		let progress = Arc::new(Mutex::new(Vec::new()));
+		let reported = progress.clone();
+
+		let query = server.client().query("SELECT id, ts FROM events");
+		let query = query.on_progress(move |p| reported.lock().unwrap().push(p.read_rows));
+		let mut cursor = query.fetch::<Event>().await.unwrap();
+		assert_eq!(Some("q-1"), cursor.query_id());
+		assert_eq!(2, cursor.summary().unwrap().result_rows);
+		assert_eq!(vec![1, 2], *progress.lock().unwrap());
+
+		let row = cursor.next_row().await.unwrap().unwrap();
+		assert_eq!(&Value::DateTime(1706702400, Some("Asia/Shanghai".parse().unwrap())), &row.values()[1]);
+		assert_eq!("2024-01-31 20:00:00", row.get::<String>("ts").unwrap());
+		assert_eq!(Some(Event { id: 2, ts: 0 }), cursor.next().await.unwrap());
+		assert_eq!(None, cursor.next().await.unwrap());
+		assert_eq!(Some("1"), server.requests()[0].param("send_progress_in_http_headers"));
+	}
+
+	#[tokio::test]
+	async fn test_exception_after_rows() {
+		let server = MockServer::start_with(|request| {
+			let mut data = body(&[Event { id: 1, ts: 0 }]);
+			match request.query.as_str() {
+				"SELECT text" => {
+					data.extend_from_slice(
+						b"Code: 241. DB::Exception: Memory limit exceeded: would use 9.31 GiB. \
+						(MEMORY_LIMIT_EXCEEDED) (version 24.3.1.1)\n",
+					);
+					Reply::ok(data)
+				}
+				_ => {
+					data.extend_from_slice(
+						b"__exception__\r\nxyz\r\nCode: 159. DB::Exception: Timeout exceeded: \
+						elapsed 5 seconds. (TIMEOUT_EXCEEDED)\n94 xyz\r\n__exception__\r\n",
+					);
+					Reply::ok(data).with_header("X-ClickHouse-Exception-Tag", "xyz")
+				}
+			}
+		})
+		.await;
+		let client = server.client();
+
+		let mut cursor = client.query("SELECT text").fetch::<Event>().await.unwrap();
+		assert_eq!(Some(Event { id: 1, ts: 0 }), cursor.next().await.unwrap());
+		let err = cursor.next().await.unwrap_err();
+		assert_eq!(
+			"Server error: Code: 241. Memory limit exceeded: would use 9.31 GiB. (MEMORY_LIMIT_EXCEEDED)",
+			err.to_string()
+		);
+
+		let err = client.query("SELECT tag").fetch_all::<Event>().await.unwrap_err();
+		let Error::Server { code, name, message, stack_trace } = err else { panic!("not server error: {}", err) };
+		assert_eq!((159, "TIMEOUT_EXCEEDED", "Timeout exceeded: elapsed 5 seconds.", None), {
+			(code, name.as_str(), message.as_str(), stack_trace)
+		});
+	}
+
+	#[tokio::test]
+	async fn test_cancel() {
+		let server = MockServer::start_with(|request| match request.query.starts_with("KILL QUERY") {
+			true => Reply::ok(""),
+			false => Reply::ok(body(&[Event { id: 1, ts: 0 }, Event { id: 2, ts: 0 }])).pending(),
+		})
+		.await;
+		let client = server.client();
+
+		let query = client.query("SELECT id, ts FROM events");
+		let query_id = query.query_id().to_owned();
+		let mut cursor = query.fetch::<Event>().await.unwrap();
+		assert_eq!(Some(Event { id: 1, ts: 0 }), cursor.next().await.unwrap());
+		drop(cursor);
+		let requests = server.wait_requests(2).await;
+		assert_eq!(Some(query_id.as_str()), requests[0].param("query_id"));
+		assert_eq!(format!("KILL QUERY WHERE query_id = '{}' ASYNC", query_id), requests[1].query);
+
+		let query = client.query("SELECT id, ts FROM events").with_query_id("q-2");
+		let handle = query.cancel_handle();
+		let task = tokio::spawn(async move {
+			let mut cursor = query.fetch::<Event>().await?;
+			while cursor.next().await?.is_some() {}
+			Ok::<_, Error>(())
+		});
+		handle.cancel().await.unwrap();
+		assert!(matches!(task.await.unwrap(), Err(Error::Cancelled(id)) if id == "q-2"));
+		let requests = server.requests();
+		assert_eq!("KILL QUERY WHERE query_id = 'q-2' ASYNC", requests.last().unwrap().query);
+		handle.cancel().await.unwrap();
+		assert_eq!(requests.len(), server.requests().len());
+	}
+
+	#[tokio::test]
+	async fn test_no_cancel_at_end() {
+		let rows = [Event { id: 1, ts: 0 }, Event { id: 2, ts: 0 }];
+		let server = MockServer::start_with(move |request| match request.query.starts_with("KILL QUERY") {
+			true => Reply::ok(""),
+			false => Reply::ok(body(&rows)).chunked(3),
+		})
+		.await;
+		let client = server.client();
+
+		let row = client.query("SELECT id, ts FROM events").fetch_optional::<Event>().await.unwrap();
+		assert_eq!(Some(Event { id: 1, ts: 0 }), row);
+		// all rows are read, but the end of chunked body isn't polled yet.
+		let mut cursor = client.query("SELECT id, ts FROM events").fetch::<Event>().await.unwrap();
+		assert_eq!(Some(Event { id: 1, ts: 0 }), cursor.next().await.unwrap());
+		assert_eq!(Some(Event { id: 2, ts: 0 }), cursor.next().await.unwrap());
+		tokio::time::sleep(Duration::from_millis(50)).await;
+		drop(cursor);
+
+		tokio::time::sleep(Duration::from_millis(50)).await;
+		let requests = server.requests();
+		assert_eq!(2, requests.len());
+		assert!(requests.iter().all(|r| !r.query.starts_with("KILL QUERY")));
+	}
+
+	#[test]
+	fn test_decode_partial() {
+		let data = body(&[Event { id: 1, ts: 0 }]);
+		let columns: Vec<SchemaColumn> = Event::columns();
+		let mut buffer = BytesMut::from(&data[..data.len() - 1]);
+		let metadata = decode(&mut buffer, |buf| rowbinary::read_header(buf)).unwrap().unwrap();
+		assert_eq!(columns.len(), metadata.len());
+
+		let metadata = Arc::new(metadata);
+		assert!(decode(&mut buffer, |buf| rowbinary::read_row(buf, &metadata)).unwrap().is_none());
+		assert_eq!(7, buffer.len());
+		buffer.extend_from_slice(&data[data.len() - 1..]);
+		assert!(decode(&mut buffer, |buf| rowbinary::read_row(buf, &metadata)).unwrap().is_some());
+		assert!(buffer.is_empty());
+	}
+}
@@ -0,0 +1,207 @@
+use crate::codes::error_name;
+
+pub type Result<T> = core::result::Result<T, crate::error::Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error("Invalid url: {0}")]
+	InvalidUrl(String),
+
+	#[error("Io error: {0}")]
+	IoError(#[from] std::io::Error),
+
+	#[error("Http error: {0}")]
+	HttpError(#[from] hyper::Error),
+
+	#[error("Bad response: {0}")]
+	BadResponse(String),
+
+	#[error("Server error: Code: {code}. {message}{}", display_name(name))]
+	Server { code: i32, name: String, message: String, stack_trace: Option<String> },
+
+	#[error("Config error: {0}")]
+	ConfigError(String),
+
+	#[error("Invalid dsn: {0}")]
+	InvalidDsn(String),
+
+	#[error("Invalid query parameter: {0}")]
+	InvalidParam(String),
+
+	#[error("Invalid setting: {0}")]
+	InvalidSetting(String),
+
+	#[error("Auth error: {0}")]
+	AuthError(String),
+
+	#[error("Tls error: {0}")]
+	TlsError(String),
+
+	#[error("Invalid sharding: {0}")]
+	InvalidSharding(String),
+
+	#[error("Query cancelled: {0}")]
+	Cancelled(String),
+
+	#[error("Timeout: {0}")]
+	Timeout(String),
+
+	#[error("Spool error: {0}")]
+	SpoolError(String),
+
+	#[error(transparent)]
+	CommonError(#[from] rickhouse_common::Error),
+}
+
+impl Error {
+	/// whether the error is transient, e.g. network errors and some server exceptions.
+	pub fn is_retryable(&self) -> bool {
+		match self {
+			Error::IoError(_) | Error::HttpError(_) => true,
+			Error::Server { code, .. } => crate::retry::is_retryable_code(*code),
+			_ => false,
+		}
+	}
+
+	/// parse server exception like `Code: 62. DB::Exception: Syntax error. (SYNTAX_ERROR) (version
+	/// 24.1.1)`, `code` is from `X-ClickHouse-Exception-Code` header in case the body has no code.
+	pub(crate) fn from_exception(code: Option<&str>, body: &str) -> Option<Error> {
+		let body = body.trim();
+		let (code, mut text) = match body.strip_prefix("Code: ").and_then(|rest| rest.split_once('.')) {
+			Some((code, text)) => (code.trim().parse().ok()?, text.trim()),
+			None => (code?.trim().parse().ok()?, body),
+		};
+		text = text.strip_prefix("DB::Exception:").map(str::trim_start).unwrap_or(text);
+
+		// e.g. `(MEMORY_LIMIT_EXCEEDED), Stack trace (when copying this message, always include the lines
+		// below):`
+		let (mut text, stack_trace) = match text.split_once("Stack trace") {
+			Some((text, trace)) => {
+				let trace = trace.split_once(':').map(|(_, trace)| trace.trim().to_owned());
+				(text.trim_end().trim_end_matches(',').trim_end(), trace)
+			}
+			None => (text, None),
+		};
+		if let Some((rest, version)) = text.rsplit_once(" (version ") {
+			if version.ends_with(')') {
+				text = rest.trim_end();
+			}
+		}
+		let mut name = None;
+		if let Some((rest, suffix)) = text.strip_suffix(')').and_then(|text| text.rsplit_once('(')) {
+			if !suffix.is_empty() && suffix.bytes().all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'_') {
+				name = Some(suffix.to_owned());
+				text = rest.trim_end();
+			}
+		}
+		let name = name.or_else(|| error_name(code).map(str::to_owned)).unwrap_or_default();
+		let stack_trace = stack_trace.filter(|s| !s.is_empty());
+		Some(Error::Server { code, name, message: text.to_owned(), stack_trace })
+	}
+
+	/// parse exception written into body after some rows, it's either the text of
+	/// [`Error::from_exception`] or, with `X-ClickHouse-Exception-Tag`, the text wrapped like
+	/// `__exception__\r\n{tag}\r\n{text}\n{len} {tag}\r\n__exception__\r\n`.
+	pub(crate) fn from_stream_exception(data: &[u8], tag: Option<&str>) -> Error {
+		let text = String::from_utf8_lossy(data);
+		let mut text = text.trim();
+		if let Some(rest) = text.strip_prefix(EXCEPTION_MARKER) {
+			text = rest.strip_suffix(EXCEPTION_MARKER).unwrap_or(rest).trim();
+			if let Some(tag) = tag {
+				text = text.strip_prefix(tag).unwrap_or(text).trim_start();
+				if let Some((rest, trailer)) = text.rsplit_once('\n') {
+					if trailer.trim_end().ends_with(tag) {
+						text = rest.trim_end();
+					}
+				}
+			}
+		}
+		Error::from_exception(None, text).unwrap_or_else(|| Error::BadResponse(format!("exception in body: {}", text)))
+	}
+}
+
+/// marker of exception sent with `X-ClickHouse-Exception-Tag`.
+const EXCEPTION_MARKER: &str = "__exception__";
+
+/// whether `data` starts with an exception written after some rows, `None` if more data is needed
+/// to decide.
+pub(crate) fn starts_with_exception(data: &[u8], tag: Option<&str>) -> Option<bool> {
+	if tag.is_some() {
+		return match (starts_with(data, EXCEPTION_MARKER.as_bytes()), data.strip_prefix(b"\r\n")) {
+			(Some(false), Some(rest)) => starts_with(rest, EXCEPTION_MARKER.as_bytes()),
+			(Some(false), None) if data == b"\r" => None,
+			(decided, _) => decided,
+		};
+	}
+	if !starts_with(data, b"Code: ")? {
+		return Some(false);
+	}
+	let rest = &data[6..];
+	match rest.iter().take_while(|b| b.is_ascii_digit()).count() {
+		digits if digits == rest.len() => None,
+		0 => Some(false),
+		digits => starts_with(&rest[digits..], b". DB::Exception:"),
+	}
+}
+
+/// offset of exception in `data`, used when rows fail to decode.
+pub(crate) fn find_exception(data: &[u8], tag: Option<&str>) -> Option<usize> {
+	(0..data.len()).find(|&idx| starts_with_exception(&data[idx..], tag) == Some(true))
+}
+
+fn starts_with(data: &[u8], prefix: &[u8]) -> Option<bool> {
+	let len = data.len().min(prefix.len());
+	match data[..len] == prefix[..len] {
+		false => Some(false),
+		true if len == prefix.len() => Some(true),
+		true => None,
+	}
+}
+
+fn display_name(name: &str) -> String {
+	match name.is_empty() {
+		true => String::new(),
+		false => format!(" ({})", name),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_from_exception() {
+		let text = "Code: 60. DB::Exception: Table default.t does not exist. (UNKNOWN_TABLE) (version 24.3.1.1)\n";
+		let Some(Error::Server { code, name, message, stack_trace }) = Error::from_exception(None, text) else {
+			panic!("not server error");
+		};
+		assert_eq!((60, "UNKNOWN_TABLE", "Table default.t does not exist.", None), {
+			(code, name.as_str(), message.as_str(), stack_trace)
+		});
+
+		let text = "Code: 241. DB::Exception: Memory limit exceeded. Stack trace (when copying this message, always \
+			include the lines below):\n\n0. DB::Exception::Exception()\n";
+		let err = Error::from_exception(None, text).unwrap();
+		assert_eq!("Server error: Code: 241. Memory limit exceeded. (MEMORY_LIMIT_EXCEEDED)", err.to_string());
+		assert!(matches!(err, Error::Server { stack_trace: Some(s), .. } if s == "0. DB::Exception::Exception()"));
+
+		let err = Error::from_exception(Some("1234"), "Something (odd)").unwrap();
+		assert_eq!("Server error: Code: 1234. Something (odd)", err.to_string());
+		assert!(Error::from_exception(None, "upstream error").is_none());
+	}
+
+	#[test]
+	fn test_stream_exception() {
+		assert_eq!(Some(true), starts_with_exception(b"Code: 62. DB::Exception: x", None));
+		assert_eq!(None, starts_with_exception(b"Code: 62", None));
+		assert_eq!(Some(false), starts_with_exception(b"Code: x", None));
+		assert_eq!(Some(false), starts_with_exception(b"\x01\x02", None));
+		assert_eq!(None, starts_with_exception(b"\r\n__exc", Some("tag")));
+		assert_eq!(Some(3), find_exception(b"\x01\x02\x03Code: 1. DB::Exception: x", None));
+
+		let data = b"__exception__\r\nabc\r\nCode: 395. DB::Exception: boom. (FUNCTION_THROW_IF_VALUE_IS_NON_ZERO)\n\
+			79 abc\r\n__exception__\r\n";
+		let err = Error::from_stream_exception(data, Some("abc"));
+		assert!(matches!(err, Error::Server { code: 395, ref message, .. } if message == "boom."), "{}", err);
+	}
+}
@@ -0,0 +1,284 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand::seq::SliceRandom;
+use url::Url;
+
+use crate::pool::Pool;
+use crate::pool::PoolMetrics;
+use crate::pool::PoolOptions;
+use crate::tls::TlsConfig;
+use crate::tls::TlsOptions;
+
+/// Strategy to choose the host of a query among replicas, hosts marked down are tried last.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LoadBalancing {
+	/// hosts in turn.
+	#[default]
+	RoundRobin,
+	Random,
+	/// the first healthy host in the given order, others are fallbacks.
+	InOrder,
+	/// the host with the lowest latency of connecting and `/ping`.
+	NearestByLatency,
+}
+
+/// Status of a host of client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostStatus {
+	pub url: String,
+	/// the host failed and is being checked in background.
+	pub down: bool,
+	pub latency: Option<Duration>,
+	pub pool: PoolMetrics,
+}
+
+/// Replica of the client with its connection pool.
+#[derive(Debug)]
+pub(crate) struct Endpoint {
+	pub url: Url,
+	pub pool: Arc<Pool>,
+	down: AtomicBool,
+	checking: AtomicBool,
+}
+
+impl Endpoint {
+	pub(crate) fn is_down(&self) -> bool {
+		self.down.load(Ordering::Acquire)
+	}
+
+	pub(crate) fn mark_up(&self) {
+		self.down.store(false, Ordering::Release);
+	}
+}
+
+/// Hosts of client, shared by its clones.
+#[derive(Debug)]
+pub(crate) struct Hosts {
+	endpoints: Vec<Arc<Endpoint>>,
+	pub options: PoolOptions,
+	pub balancing: LoadBalancing,
+	/// interval of checking hosts marked down.
+	pub recheck_interval: Duration,
+	/// TLS config set by client, `https` hosts use default options without it.
+	pub tls: Option<Arc<TlsConfig>>,
+	next: AtomicUsize,
+}
+
+impl Hosts {
+	pub(crate) fn new(
+		urls: Vec<Url>,
+		options: PoolOptions,
+		balancing: LoadBalancing,
+		recheck_interval: Duration,
+		tls: Option<Arc<TlsConfig>>,
+	) -> Hosts {
+		let https = match &tls {
+			None if urls.iter().any(|url| url.scheme() == "https") => {
+				Some(Arc::new(TlsOptions::default().build().expect("default TLS options are valid")))
+			}
+			tls => tls.clone(),
+		};
+		let endpoints = urls
+			.into_iter()
+			.map(|url| {
+				let (host, port) = (url.host_str().unwrap_or_default(), url.port_or_known_default().unwrap_or(8123));
+				let tls = https.clone().filter(|_| url.scheme() == "https");
+				let pool = Pool::new(host, port, tls, options.clone());
+				let (down, checking) = (AtomicBool::new(false), AtomicBool::new(false));
+				Arc::new(Endpoint { url, pool: Arc::new(pool), down, checking })
+			})
+			.collect();
+		Hosts { endpoints, options, balancing, recheck_interval, tls, next: AtomicUsize::new(0) }
+	}
+
+	/// hosts of the first candidate only, sharing its connection pool.
+	pub(crate) fn pinned(&self) -> Hosts {
+		let endpoints = self.candidates().into_iter().take(1).collect();
+		let (options, balancing, tls) = (self.options.clone(), self.balancing, self.tls.clone());
+		Hosts { endpoints, options, balancing, recheck_interval: self.recheck_interval, tls, next: AtomicUsize::new(0) }
+	}
+
+	pub(crate) fn urls(&self) -> Vec<Url> {
+		self.endpoints.iter().map(|endpoint| endpoint.url.clone()).collect()
+	}
+
+	pub(crate) fn endpoints(&self) -> &[Arc<Endpoint>] {
+		&self.endpoints
+	}
+
+	/// hosts in the order to try.
+	pub(crate) fn candidates(&self) -> Vec<Arc<Endpoint>> {
+		let len = self.endpoints.len();
+		let mut order = (0..len).collect::<Vec<_>>();
+		match self.balancing {
+			LoadBalancing::RoundRobin => order.rotate_left(self.next.fetch_add(1, Ordering::Relaxed) % len),
+			LoadBalancing::Random => order.shuffle(&mut rand::thread_rng()),
+			LoadBalancing::InOrder => (),
+			// hosts not measured yet are tried first to measure them.
+			LoadBalancing::NearestByLatency => order.sort_by_key(|&idx| self.endpoints[idx].pool.latency()),
+		}
+		order.sort_by_key(|&idx| self.endpoints[idx].is_down());
+		order.into_iter().map(|idx| self.endpoints[idx].clone()).collect()
+	}
+
+	/// mark the host down and check it by `/ping` in background until it's up again.
+	pub(crate) fn mark_down(&self, endpoint: &Arc<Endpoint>) {
+		endpoint.down.store(true, Ordering::Release);
+		let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+			return;
+		};
+		if endpoint.checking.swap(true, Ordering::AcqRel) {
+			return;
+		}
+		let endpoint = Arc::downgrade(endpoint);
+		let interval = self.recheck_interval;
+		runtime.spawn(async move {
+			loop {
+				tokio::time::sleep(interval).await;
+				// stop if the client is dropped.
+				let Some(endpoint) = endpoint.upgrade() else {
+					return;
+				};
+				if endpoint.pool.check().await.is_ok() {
+					endpoint.checking.store(false, Ordering::Release);
+					endpoint.mark_up();
+					return;
+				}
+			}
+		});
+	}
+
+	pub(crate) fn status(&self) -> Vec<HostStatus> {
+		self.endpoints
+			.iter()
+			.map(|endpoint| HostStatus {
+				url: endpoint.url.to_string(),
+				down: endpoint.is_down(),
+				latency: endpoint.pool.latency(),
+				pool: endpoint.pool.metrics(),
+			})
+			.collect()
+	}
+
+	/// metrics of all pools, `max_wait` is the max of them and others are summed.
+	pub(crate) fn metrics(&self) -> PoolMetrics {
+		self.endpoints.iter().map(|endpoint| endpoint.pool.metrics()).fold(PoolMetrics::default(), |sum, m| {
+			PoolMetrics {
+				created: sum.created + m.created,
+				reused: sum.reused + m.reused,
+				health_check_failures: sum.health_check_failures + m.health_check_failures,
+				waits: sum.waits + m.waits,
+				total_wait: sum.total_wait + m.total_wait,
+				max_wait: sum.max_wait.max(m.max_wait),
+				idle: sum.idle + m.idle,
+				in_use: sum.in_use + m.in_use,
+			}
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use hyper::StatusCode;
+	use tokio::net::TcpListener;
+
+	use super::LoadBalancing;
+	use crate::client::tests::MockServer;
+	use crate::client::tests::Reply;
+	use crate::Client;
+
+	async fn server() -> MockServer {
+		MockServer::start(|_| (StatusCode::OK, "1\n".to_owned())).await
+	}
+
+	#[tokio::test]
+	async fn test_load_balancing() {
+		let (a, b) = (server().await, server().await);
+		let urls = [format!("http://{}", a.addr), format!("http://{}", b.addr)];
+		let client = Client::from_hosts(&urls).unwrap();
+		for _ in 0..4 {
+			client.query("SELECT 1").execute().await.unwrap();
+		}
+		assert_eq!((2, 2), (a.requests().len(), b.requests().len()));
+
+		let client = client.with_load_balancing(LoadBalancing::InOrder);
+		for _ in 0..2 {
+			client.query("SELECT 1").execute().await.unwrap();
+		}
+		assert_eq!((4, 2), (a.requests().len(), b.requests().len()));
+
+		let client = client.with_load_balancing(LoadBalancing::Random);
+		for _ in 0..4 {
+			client.query("SELECT 1").execute().await.unwrap();
+		}
+		assert_eq!(10, a.requests().len() + b.requests().len());
+
+		let client = client.with_load_balancing(LoadBalancing::NearestByLatency);
+		client.ping().await.unwrap();
+		let hosts = client.hosts();
+		assert!(hosts[0].latency.is_some() && hosts[1].latency.is_none());
+		client.query("SELECT 1").execute().await.unwrap();
+		assert_eq!(Some("SELECT 1"), b.requests().last().map(|r| r.query.as_str()));
+		assert!(client.hosts().iter().all(|host| host.latency.is_some()));
+		assert!(Client::from_hosts(Vec::<String>::new()).is_err());
+	}
+
+	#[tokio::test]
+	async fn test_failover() {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let down = listener.local_addr().unwrap();
+		drop(listener);
+		let up = server().await;
+		let urls = [format!("http://{}", down), format!("http://{}", up.addr)];
+		let client = Client::from_hosts(&urls)
+			.unwrap()
+			.with_load_balancing(LoadBalancing::InOrder)
+			.with_recheck_interval(Duration::from_millis(20));
+
+		client.query("SELECT 1").fetch::<u8>().await.unwrap();
+		let hosts = client.hosts();
+		assert!(hosts[0].down && !hosts[1].down);
+		client.query("SELECT 1").execute().await.unwrap();
+		assert_eq!(2, up.requests().iter().filter(|r| r.query == "SELECT 1").count());
+
+		let recovered = MockServer::start_at(down, |_| Reply::ok("")).await;
+		for _ in 0..100 {
+			if !client.hosts()[0].down {
+				break;
+			}
+			tokio::time::sleep(Duration::from_millis(10)).await;
+		}
+		assert!(!client.hosts()[0].down);
+		client.query("SELECT 1").execute().await.unwrap();
+		assert_eq!(Some("SELECT 1"), recovered.requests().last().map(|r| r.query.as_str()));
+	}
+
+	#[tokio::test]
+	async fn test_kill_query() {
+		let (a, b) = (server().await, server().await);
+		let urls = [format!("http://{}", a.addr), format!("http://{}", b.addr)];
+		let client = Client::from_hosts(&urls).unwrap();
+		client.kill_query("q").await.unwrap();
+		for server in [&a, &b] {
+			assert_eq!(
+				Some("KILL QUERY WHERE query_id = 'q' ASYNC"),
+				server.requests().last().map(|r| r.query.as_str())
+			);
+		}
+
+		// the query is killed on hosts up, the error of the host down is returned.
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let down = listener.local_addr().unwrap();
+		drop(listener);
+		let urls = [format!("http://{}", down), format!("http://{}", a.addr)];
+		let client = Client::from_hosts(&urls).unwrap().with_load_balancing(LoadBalancing::InOrder);
+		assert!(client.kill_query("q-2").await.is_err());
+		assert_eq!(Some("KILL QUERY WHERE query_id = 'q-2' ASYNC"), a.requests().last().map(|r| r.query.as_str()));
+	}
+}
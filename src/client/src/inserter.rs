@@ -0,0 +1,642 @@
+use std::marker::PhantomData;
+use std::mem;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use bytes::Bytes;
+use bytes::BytesMut;
+use rickhouse_common::metadata::RowSchema;
+use rickhouse_common::metadata::SchemaColumn;
+use rickhouse_common::rowbinary;
+use rickhouse_common::sql::quote_table;
+use serde::Serialize;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+use crate::client::Client;
+use crate::error::Error;
+use crate::error::Result;
+use crate::retry::RetryPolicy;
+use crate::spool::Spool;
+use crate::spool::SpooledBatch;
+
+/// Stats of a batch sent by inserter.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchStats {
+	pub rows: u64,
+	/// size of RowBinary data.
+	pub bytes: u64,
+	/// time taken by the INSERT request, including retries.
+	pub elapsed: Duration,
+	pub retries: u32,
+	/// `insert_deduplication_token` of the batch.
+	pub dedup_token: Option<String>,
+	/// batch is persisted in spool instead of inserted.
+	pub spooled: bool,
+	/// query id of the INSERT returned by server.
+	pub query_id: Option<String>,
+}
+
+/// How server acknowledges inserts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InsertMode {
+	/// a part is written for each insert before the response.
+	#[default]
+	Sync,
+	/// `async_insert=1`, server buffers data of many inserts and writes them in one part. With
+	/// `wait`, the response is sent after the buffer is flushed (`wait_for_async_insert=1`),
+	/// otherwise once data is buffered. Batches with deduplication token are sent with
+	/// `async_insert_deduplicate=1`, which works for replicated tables only.
+	Async { wait: bool },
+}
+
+impl InsertMode {
+	fn settings(&self) -> &'static [(&'static str, &'static str)] {
+		match self {
+			InsertMode::Sync => &[],
+			InsertMode::Async { wait: true } => &[("async_insert", "1"), ("wait_for_async_insert", "1")],
+			InsertMode::Async { wait: false } => &[("async_insert", "1"), ("wait_for_async_insert", "0")],
+		}
+	}
+}
+
+/// Status of an async insert in `system.asynchronous_insert_log`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsyncInsertStatus {
+	Ok,
+	ParsingError(String),
+	FlushError(String),
+}
+
+/// Stats of all batches sent by inserter, spooled batches are only counted by `spooled`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InsertStats {
+	pub batches: u64,
+	pub rows: u64,
+	pub bytes: u64,
+	pub spooled: u64,
+	/// batches moved out of spool by [`Inserter::quarantine_spooled`].
+	pub quarantined: u64,
+}
+
+type BatchCallback = Box<dyn Fn(&BatchStats) + Send + Sync>;
+
+/// Inserter buffers rows and sends them with `INSERT INTO table FORMAT RowBinary` in batches.
+///
+/// A batch is sent once max rows, max bytes or period is reached, or by [`Inserter::commit`]. Rows
+/// are written into a fresh buffer while the previous batch is in flight, at most one batch is in
+/// flight at a time. Buffered rows are lost if inserter is dropped without [`Inserter::end`].
+///
+/// With deduplication, each batch carries `insert_deduplication_token`, so a batch retried after a
+/// network error is inserted once by ReplicatedMergeTree tables. Non-replicated MergeTree tables
+/// need `non_replicated_deduplication_window` to be set.
+///
+/// With spool, each batch is persisted before it's sent and replayed in order with spooled batches
+/// by the [`InsertMode`] of inserter, so rows are kept during outages of server. A batch failed by
+/// other errors stays at the head of spool and fails later batches, see
+/// [`Inserter::quarantine_spooled`].
+pub struct Inserter<T> {
+	client: Client,
+	sql: String,
+	columns: Vec<SchemaColumn>,
+	max_rows: u64,
+	max_bytes: u64,
+	period: Option<Duration>,
+	on_batch: Option<BatchCallback>,
+	retry: RetryPolicy,
+	deduplicate: bool,
+	dedup_token: Option<String>,
+	spool: Option<Arc<Mutex<Spool>>>,
+	mode: InsertMode,
+	confirm_timeout: Option<Duration>,
+	buffer: BytesMut,
+	rows: u64,
+	batch_start: Instant,
+	in_flight: Option<JoinHandle<Result<BatchStats>>>,
+	stats: InsertStats,
+	_marker: PhantomData<fn(&T)>,
+}
+
+impl<T: Serialize + RowSchema> Inserter<T> {
+	pub(crate) fn new(client: Client, table: &str) -> Self {
+		// columns of tuples and scalars are unnamed, they are inserted by position.
+		let sql = match T::columns().iter().all(|column| column.name.is_empty()) {
+			true => format!("INSERT INTO {} FORMAT RowBinary", quote_table(table)),
+			false => format!("INSERT INTO {} ({}) FORMAT RowBinary", quote_table(table), T::column_list()),
+		};
+		Inserter {
+			client,
+			sql,
+			columns: T::columns(),
+			max_rows: 100_000,
+			max_bytes: 64 * 1024 * 1024,
+			period: None,
+			on_batch: None,
+			retry: RetryPolicy::none(),
+			deduplicate: false,
+			dedup_token: None,
+			spool: None,
+			mode: InsertMode::Sync,
+			confirm_timeout: None,
+			buffer: BytesMut::new(),
+			rows: 0,
+			batch_start: Instant::now(),
+			in_flight: None,
+			stats: InsertStats::default(),
+			_marker: PhantomData,
+		}
+	}
+
+	/// max rows of a batch, 100000 by default.
+	pub fn with_max_rows(mut self, max_rows: u64) -> Self {
+		self.max_rows = max_rows;
+		self
+	}
+
+	/// max bytes of a batch, 64MiB by default.
+	pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+		self.max_bytes = max_bytes;
+		self
+	}
+
+	/// max time between the first row of a batch and sending it, no limit by default.
+	pub fn with_period(mut self, period: Duration) -> Self {
+		self.period = Some(period);
+		self
+	}
+
+	/// callback called once a batch is inserted.
+	pub fn on_batch<F: Fn(&BatchStats) + Send + Sync + 'static>(mut self, callback: F) -> Self {
+		self.on_batch = Some(Box::new(callback));
+		self
+	}
+
+	/// retry policy of batches, no retries by default. Enable deduplication as well, otherwise a
+	/// retried batch may be inserted twice.
+	pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+		self.retry = retry;
+		self
+	}
+
+	/// whether to send content hash of batches as deduplication token, false by default.
+	pub fn with_deduplication(mut self, deduplicate: bool) -> Self {
+		self.deduplicate = deduplicate;
+		self
+	}
+
+	/// deduplication token of the buffered batch, it replaces the content hash.
+	pub fn set_dedup_token(&mut self, token: impl Into<String>) {
+		self.dedup_token = Some(token.into());
+	}
+
+	/// spool of batches failed by retryable errors, deduplication is enabled as well.
+	pub fn with_spool(mut self, spool: Spool) -> Self {
+		self.spool = Some(Arc::new(Mutex::new(spool)));
+		self.deduplicate = true;
+		self
+	}
+
+	/// insert mode, [`InsertMode::Sync`] by default.
+	pub fn with_mode(mut self, mode: InsertMode) -> Self {
+		self.mode = mode;
+		self
+	}
+
+	/// poll `system.asynchronous_insert_log` after each async insert until it is flushed, fails if
+	/// it is not flushed in `timeout`. Log is flushed to the table every 7.5 seconds by default, so
+	/// `timeout` should be longer than that.
+	pub fn with_flush_confirmation(mut self, timeout: Duration) -> Self {
+		self.confirm_timeout = Some(timeout);
+		self
+	}
+
+	/// number of batches in spool.
+	pub async fn spooled_batches(&self) -> usize {
+		match &self.spool {
+			Some(spool) => spool.lock().await.len(),
+			None => 0,
+		}
+	}
+
+	/// move the oldest spooled batch to a `.failed` file after it's rejected by server, e.g. by a
+	/// parsing error, so later batches can be inserted. Return the path of the file, see
+	/// [`Spool::quarantine`].
+	pub async fn quarantine_spooled(&mut self) -> Result<Option<PathBuf>> {
+		let Some(spool) = &self.spool else {
+			return Ok(None);
+		};
+		let path = spool.lock().await.quarantine()?;
+		self.stats.quarantined += path.is_some() as u64;
+		Ok(path)
+	}
+
+	/// buffer a row, the batch is sent if any threshold is reached.
+	pub async fn write(&mut self, row: &T) -> Result<()> {
+		if self.rows == 0 {
+			self.batch_start = Instant::now();
+		}
+		let size = self.buffer.len();
+		if let Err(e) = rowbinary::write_row(&mut self.buffer, row, &self.columns) {
+			self.buffer.truncate(size);
+			return Err(e.into());
+		}
+		self.rows += 1;
+
+		if self.rows >= self.max_rows
+			|| self.buffer.len() as u64 >= self.max_bytes
+			|| self.time_left() == Some(Duration::ZERO)
+		{
+			self.flush().await?;
+		}
+		Ok(())
+	}
+
+	/// send the batch if period is reached, call it periodically when rows come slowly.
+	pub async fn tick(&mut self) -> Result<()> {
+		match self.time_left() {
+			Some(Duration::ZERO) => self.flush().await,
+			_ => Ok(()),
+		}
+	}
+
+	/// time left before the buffered batch should be sent, `None` if no period or no rows.
+	pub fn time_left(&self) -> Option<Duration> {
+		match self.period {
+			Some(period) if self.rows > 0 => Some(period.saturating_sub(self.batch_start.elapsed())),
+			_ => None,
+		}
+	}
+
+	/// rows buffered and not sent yet.
+	pub fn pending_rows(&self) -> u64 {
+		self.rows
+	}
+
+	/// send buffered rows and wait until all batches are inserted, return stats of the batch sent.
+	/// Spooled batches are replayed even if no rows are buffered.
+	pub async fn commit(&mut self) -> Result<Option<BatchStats>> {
+		let sent = self.rows > 0;
+		self.flush().await?;
+		let stats = self.wait_in_flight().await?;
+		if !sent {
+			self.replay_spool().await?;
+		}
+		Ok(stats.filter(|_| sent))
+	}
+
+	/// commit buffered rows and return stats of all batches.
+	pub async fn end(mut self) -> Result<InsertStats> {
+		self.commit().await?;
+		Ok(self.stats)
+	}
+
+	/// stats of batches inserted so far.
+	pub fn stats(&self) -> InsertStats {
+		self.stats
+	}
+
+	/// replay spooled batches, they are kept if server is still unavailable.
+	async fn replay_spool(&self) -> Result<()> {
+		let Some(spool) = &self.spool else {
+			return Ok(());
+		};
+		match spool.lock().await.replay(&self.client, &self.retry, self.mode).await {
+			Err(e) if !e.is_retryable() => Err(e),
+			_ => Ok(()),
+		}
+	}
+
+	/// send buffered rows in background after the previous batch is inserted.
+	async fn flush(&mut self) -> Result<()> {
+		self.wait_in_flight().await?;
+		if self.rows == 0 {
+			return Ok(());
+		}
+
+		let body = mem::take(&mut self.buffer).freeze();
+		let rows = mem::take(&mut self.rows);
+		let token = self.dedup_token.take().or_else(|| self.deduplicate.then(|| content_token(&body)));
+		let client = self.client.clone();
+		let sql = self.sql.clone();
+		let retry = self.retry.clone();
+		let spool = self.spool.clone();
+		let mode = self.mode;
+		let confirm_timeout = self.confirm_timeout.filter(|_| mode != InsertMode::Sync);
+		self.in_flight = Some(tokio::spawn(async move {
+			let start = Instant::now();
+			let bytes = body.len() as u64;
+			let mut stats = BatchStats { rows, bytes, dedup_token: token, ..Default::default() };
+			let result = match &spool {
+				None => insert_batch(&client, &sql, body, stats.dedup_token.as_deref(), mode, &retry).await,
+				// the batch is persisted first and replayed after spooled batches to keep the order.
+				Some(spool) => {
+					let mut spool = spool.lock().await;
+					spool.append(&SpooledBatch { sql, dedup_token: stats.dedup_token.clone(), rows, data: body })?;
+					let mut inserted = (0, None);
+					loop {
+						match spool.replay_next(&client, &retry, mode).await {
+							// the batch is the last one in spool.
+							Ok(Some(result)) => inserted = result,
+							Ok(None) => break Ok(inserted),
+							Err(e) => break Err(e),
+						}
+					}
+				}
+			};
+
+			match (result, spool) {
+				(Ok((retries, query_id)), _) => {
+					stats.retries = retries;
+					stats.query_id = query_id;
+				}
+				(Err(e), Some(_)) if e.is_retryable() => stats.spooled = true,
+				(Err(e), _) => return Err(e),
+			}
+
+			if let (Some(timeout), Some(query_id)) = (confirm_timeout, &stats.query_id) {
+				match client.wait_async_insert(query_id, timeout).await? {
+					AsyncInsertStatus::Ok => (),
+					AsyncInsertStatus::ParsingError(e) | AsyncInsertStatus::FlushError(e) => {
+						return Err(Error::from_exception(None, &e).unwrap_or(Error::BadResponse(e)));
+					}
+				}
+			}
+			stats.elapsed = start.elapsed();
+			Ok(stats)
+		}));
+		Ok(())
+	}
+
+	async fn wait_in_flight(&mut self) -> Result<Option<BatchStats>> {
+		let Some(handle) = self.in_flight.take() else {
+			return Ok(None);
+		};
+
+		let stats = handle.await.map_err(|e| std::io::Error::other(e.to_string()))??;
+		if stats.spooled {
+			self.stats.spooled += 1;
+		} else {
+			self.stats.batches += 1;
+			self.stats.rows += stats.rows;
+			self.stats.bytes += stats.bytes;
+		}
+		if let Some(callback) = &self.on_batch {
+			callback(&stats);
+		}
+		Ok(Some(stats))
+	}
+}
+
+/// send a batch with retries, return the number of retries and query id.
+pub(crate) async fn insert_batch(
+	client: &Client,
+	sql: &str,
+	body: Bytes,
+	token: Option<&str>,
+	mode: InsertMode,
+	retry: &RetryPolicy,
+) -> Result<(u32, Option<String>)> {
+	let mut settings = mode.settings().to_vec();
+	if let Some(token) = token {
+		settings.push(("insert_deduplication_token", token));
+		// async inserts are deduplicated only with the setting.
+		if mode != InsertMode::Sync {
+			settings.push(("async_insert_deduplicate", "1"));
+		}
+	}
+	let mut retries = 0;
+	loop {
+		match client.execute(sql, &settings, body.clone()).await {
+			Ok(info) => return Ok((retries, info.query_id)),
+			Err(e) if retry.should_retry(&e, retries + 1) => {
+				retries += 1;
+				tokio::time::sleep(retry.backoff(retries)).await;
+			}
+			Err(e) => return Err(e),
+		}
+	}
+}
+
+/// deduplication token of batch data, it is the hex of FNV-1a 64 hash.
+pub(crate) fn content_token(data: &[u8]) -> String {
+	let hash = data.iter().fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3));
+	format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::AtomicU64;
+	use std::sync::atomic::Ordering;
+	use std::sync::Arc;
+	use std::time::Duration;
+
+	use hyper::StatusCode;
+	use rickhouse_derive::Row;
+	use serde::Serialize;
+
+	use bytes::Bytes;
+	use rickhouse_common::metadata::DataType;
+	use rickhouse_common::metadata::SchemaColumn;
+	use rickhouse_common::rowbinary;
+
+	use super::content_token;
+	use super::AsyncInsertStatus;
+	use super::InsertMode;
+	use crate::client::tests::MockServer;
+	use crate::client::tests::Reply;
+	use crate::retry::RetryPolicy;
+	use crate::spool::Spool;
+
+	#[derive(Serialize, Row)]
+	struct Event {
+		id: u32,
+		name: String,
+	}
+
+	#[tokio::test]
+	async fn test_inserter() {
+		let server = MockServer::start(|_| (StatusCode::OK, String::new())).await;
+		let batches = Arc::new(AtomicU64::new(0));
+		let counter = batches.clone();
+		let mut inserter = server.client().inserter::<Event>("events").with_max_rows(2).on_batch(move |_| {
+			counter.fetch_add(1, Ordering::SeqCst);
+		});
+
+		for id in 0..5 {
+			inserter.write(&Event { id, name: "a".to_owned() }).await.unwrap();
+		}
+		assert_eq!(1, inserter.pending_rows());
+		let stats = inserter.commit().await.unwrap().unwrap();
+		assert_eq!(1, stats.rows);
+		assert_eq!(6, stats.bytes);
+		assert_eq!(None, inserter.commit().await.unwrap());
+
+		let stats = inserter.end().await.unwrap();
+		assert_eq!((3, 5, 30), (stats.batches, stats.rows, stats.bytes));
+		assert_eq!(3, batches.load(Ordering::SeqCst));
+
+		let requests = server.requests();
+		assert_eq!("INSERT INTO `events` (`id`,`name`) FORMAT RowBinary", requests[0].query);
+		assert_eq!(&[0, 0, 0, 0, 1, b'a', 1, 0, 0, 0, 1, b'a'][..], &requests[0].body[..]);
+
+		// tuples are inserted by position.
+		let mut inserter = server.client().inserter::<(u32, String)>("events");
+		inserter.write(&(7, "b".to_owned())).await.unwrap();
+		inserter.end().await.unwrap();
+		assert_eq!("INSERT INTO `events` FORMAT RowBinary", server.requests()[3].query);
+	}
+
+	#[tokio::test]
+	async fn test_period_and_error() {
+		let server =
+			MockServer::start(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Code: 241. DB::Exception".to_owned())).await;
+		let mut inserter = server.client().inserter::<Event>("events").with_period(Duration::from_millis(10));
+		assert_eq!(None, inserter.time_left());
+
+		inserter.write(&Event { id: 1, name: "a".to_owned() }).await.unwrap();
+		assert!(inserter.time_left().unwrap() <= Duration::from_millis(10));
+		tokio::time::sleep(Duration::from_millis(20)).await;
+		inserter.tick().await.unwrap();
+		assert_eq!(0, inserter.pending_rows());
+
+		let err = inserter.commit().await.unwrap_err();
+		assert!(err.to_string().contains("Code: 241"));
+	}
+
+	#[tokio::test]
+	async fn test_retry_with_dedup_token() {
+		let server = MockServer::start(|request| match request.param("insert_deduplication_token") {
+			Some("batch-1") => (StatusCode::OK, String::new()),
+			_ => (StatusCode::SERVICE_UNAVAILABLE, "Code: 252. DB::Exception: Too many parts".to_owned()),
+		})
+		.await;
+		let retry = RetryPolicy::default().with_backoff(Duration::from_millis(1), Duration::from_millis(1));
+		let mut inserter = server.client().inserter::<Event>("events").with_retry(retry).with_deduplication(true);
+
+		inserter.write(&Event { id: 1, name: "a".to_owned() }).await.unwrap();
+		let err = inserter.commit().await.unwrap_err();
+		assert!(err.is_retryable());
+		let requests = server.requests();
+		assert_eq!(4, requests.len());
+		let token = content_token(&requests[0].body);
+		assert!(requests.iter().all(|r| r.param("insert_deduplication_token") == Some(token.as_str())));
+
+		inserter.write(&Event { id: 1, name: "a".to_owned() }).await.unwrap();
+		inserter.set_dedup_token("batch-1");
+		let stats = inserter.commit().await.unwrap().unwrap();
+		assert_eq!((0, Some("batch-1")), (stats.retries, stats.dedup_token.as_deref()));
+	}
+
+	#[tokio::test]
+	async fn test_spool() {
+		let available = Arc::new(AtomicU64::new(0));
+		let flag = available.clone();
+		let server = MockServer::start(move |_| match flag.load(Ordering::SeqCst) {
+			0 => (StatusCode::SERVICE_UNAVAILABLE, "Code: 210. DB::NetException: Connection refused".to_owned()),
+			_ => (StatusCode::OK, String::new()),
+		})
+		.await;
+		let dir = tempfile::tempdir().unwrap();
+		let spool = Spool::open(dir.path(), 1024 * 1024).unwrap();
+		let mut inserter = server.client().inserter::<Event>("events").with_max_rows(1).with_spool(spool);
+
+		inserter.write(&Event { id: 1, name: "a".to_owned() }).await.unwrap();
+		inserter.write(&Event { id: 2, name: "b".to_owned() }).await.unwrap();
+		assert!(inserter.commit().await.unwrap().is_none());
+		assert_eq!(2, inserter.spooled_batches().await);
+		assert_eq!((0, 2), (inserter.stats().rows, inserter.stats().spooled));
+
+		// still unavailable, batches are kept.
+		assert!(inserter.commit().await.unwrap().is_none());
+		assert_eq!(2, inserter.spooled_batches().await);
+
+		// replayed without new rows.
+		available.store(1, Ordering::SeqCst);
+		assert!(inserter.commit().await.unwrap().is_none());
+		assert_eq!(0, inserter.spooled_batches().await);
+		inserter.write(&Event { id: 3, name: "c".to_owned() }).await.unwrap();
+		assert!(inserter.commit().await.unwrap().is_none());
+
+		let requests = server.requests();
+		let bodies = requests.iter().skip(4).map(|r| r.body[0]).collect::<Vec<_>>();
+		assert_eq!(vec![1, 2, 3], bodies);
+		let tokens = requests.iter().map(|r| r.param("insert_deduplication_token").unwrap()).collect::<Vec<_>>();
+		assert_eq!(tokens[0], tokens[4]);
+	}
+
+	#[tokio::test]
+	async fn test_spool_rejected() {
+		let server = MockServer::start(|request| match request.body[0] {
+			2 => (StatusCode::BAD_REQUEST, "Code: 27. DB::Exception: Cannot parse input".to_owned()),
+			_ => (StatusCode::OK, String::new()),
+		})
+		.await;
+		let dir = tempfile::tempdir().unwrap();
+		let spool = Spool::open(dir.path(), 1024 * 1024).unwrap();
+		let mut inserter = server.client().inserter::<Event>("events").with_max_rows(1).with_spool(spool);
+
+		inserter.write(&Event { id: 1, name: "a".to_owned() }).await.unwrap();
+		inserter.write(&Event { id: 2, name: "b".to_owned() }).await.unwrap();
+		assert!(inserter.write(&Event { id: 3, name: "c".to_owned() }).await.is_err());
+		// the rejected batch is kept and fails the later batch, which is kept as well.
+		assert!(inserter.commit().await.is_err());
+		assert_eq!(2, inserter.spooled_batches().await);
+
+		let path = inserter.quarantine_spooled().await.unwrap().unwrap();
+		assert_eq!(Some("failed"), path.extension().and_then(|ext| ext.to_str()));
+		assert!(path.is_file());
+		assert!(inserter.commit().await.unwrap().is_none());
+		assert_eq!(0, inserter.spooled_batches().await);
+		assert_eq!((1, 1), (inserter.stats().rows, inserter.stats().quarantined));
+
+		let inserted = server.requests().into_iter().filter(|r| r.body[0] != 2).map(|r| r.body[0]);
+		assert_eq!(vec![1, 3], inserted.collect::<Vec<_>>());
+	}
+
+	#[tokio::test]
+	async fn test_async_insert() {
+		let server = MockServer::start_with(|request| {
+			if request.query.starts_with("INSERT") {
+				return Reply::ok(Bytes::new()).with_header("X-ClickHouse-Query-Id", "q-1");
+			}
+			let columns = vec![
+				SchemaColumn { name: "status".to_owned(), data_type: DataType::String, default: false },
+				SchemaColumn { name: "exception".to_owned(), data_type: DataType::String, default: false },
+			];
+			let mut body = Vec::new();
+			rowbinary::write_header(&mut body, &columns);
+			match request.param("param_query_id") {
+				Some("q-1") => rowbinary::write_row(&mut body, &("Ok", ""), &columns).unwrap(),
+				_ => rowbinary::write_row(&mut body, &("ParsingError", "Code: 27. DB::Exception: bad"), &columns)
+					.unwrap(),
+			}
+			Reply::ok(body)
+		})
+		.await;
+		let mut inserter = server
+			.client()
+			.inserter::<Event>("events")
+			.with_mode(InsertMode::Async { wait: false })
+			.with_flush_confirmation(Duration::from_secs(1));
+
+		inserter.write(&Event { id: 1, name: "a".to_owned() }).await.unwrap();
+		let stats = inserter.commit().await.unwrap().unwrap();
+		assert_eq!(Some("q-1"), stats.query_id.as_deref());
+		let requests = server.requests();
+		assert_eq!(Some("1"), requests[0].param("async_insert"));
+		assert_eq!(Some("0"), requests[0].param("wait_for_async_insert"));
+		assert_eq!(None, requests[0].param("async_insert_deduplicate"));
+		assert_eq!(Some("q-1"), requests[1].param("param_query_id"));
+
+		let status = server.client().wait_async_insert("q-2", Duration::from_secs(1)).await.unwrap();
+		assert_eq!(AsyncInsertStatus::ParsingError("Code: 27. DB::Exception: bad".to_owned()), status);
+	}
+
+	#[test]
+	fn test_content_token() {
+		assert_eq!("cbf29ce484222325", content_token(b""));
+		assert_eq!("af63dc4c8601ec8c", content_token(b"a"));
+	}
+}
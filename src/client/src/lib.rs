@@ -1,14 +1,64 @@
-pub fn add(left: usize, right: usize) -> usize {
-	left + right
-}
+// `#[derive(Row)]` refers to `::rickhouse`, also in tests of the crate.
+extern crate self as rickhouse;
 
-#[cfg(test)]
-mod tests {
-	use super::*;
+mod auth;
+mod cancel;
+mod client;
+mod codes;
+mod config;
+mod cursor;
+mod dsn;
+mod error;
+mod hosts;
+mod inserter;
+mod pool;
+mod query;
+mod response;
+mod retry;
+mod session;
+mod settings;
+mod sharding;
+mod spool;
+mod tls;
 
-	#[test]
-	fn it_works() {
-		let result = add(2, 2);
-		assert_eq!(result, 4);
-	}
-}
+pub use auth::Token;
+pub use cancel::CancelHandle;
+pub use client::Client;
+pub use codes::error_name;
+pub use config::ConfigFile;
+pub use config::ConnectionConfig;
+pub use cursor::RowCursor;
+pub use dsn::Dsn;
+pub use error::Error;
+pub use error::Result;
+pub use hosts::HostStatus;
+pub use hosts::LoadBalancing;
+pub use inserter::AsyncInsertStatus;
+pub use inserter::BatchStats;
+pub use inserter::InsertMode;
+pub use inserter::InsertStats;
+pub use inserter::Inserter;
+pub use pool::PoolMetrics;
+pub use pool::PoolOptions;
+pub use query::Query;
+pub use response::Progress;
+pub use response::ResponseInfo;
+pub use response::Summary;
+pub use retry::is_retryable_code;
+pub use retry::RetryPolicy;
+pub use rickhouse_common::hash::HashFunction;
+pub use rickhouse_common::metadata;
+pub use rickhouse_common::metadata::RowSchema;
+pub use rickhouse_common::metadata::Strictness;
+pub use rickhouse_common::sql;
+pub use rickhouse_common::values;
+pub use rickhouse_derive::Row;
+pub use session::Session;
+pub use settings::Readonly;
+pub use settings::Settings;
+pub use sharding::Shard;
+pub use sharding::ShardedInserter;
+pub use sharding::ShardingKey;
+pub use spool::Spool;
+pub use spool::SpooledBatch;
+pub use tls::TlsOptions;
@@ -0,0 +1,363 @@
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use bytes::Bytes;
+use http_body_util::BodyExt;
+use http_body_util::Full;
+use hyper::client::conn::http1::SendRequest;
+use hyper::header;
+use hyper::rt::Read;
+use hyper::rt::Write;
+use hyper::Request;
+use hyper::StatusCode;
+use hyper_util::rt::TokioIo;
+use tokio::net::TcpStream;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::tls::TlsConfig;
+
+/// Options of the pool of HTTP keep-alive connections.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolOptions {
+	/// idle connections kept open regardless of idle timeout.
+	pub min_size: usize,
+	/// max connections in use, queries wait for a free connection beyond it.
+	pub max_size: usize,
+	/// idle connections beyond `min_size` are closed after the timeout.
+	pub idle_timeout: Duration,
+	/// connections idle longer than it are checked by `GET /ping` before reuse, `None` to never
+	/// check.
+	pub health_check_after: Option<Duration>,
+}
+
+impl Default for PoolOptions {
+	fn default() -> Self {
+		PoolOptions {
+			min_size: 0,
+			max_size: 32,
+			idle_timeout: Duration::from_secs(60),
+			health_check_after: Some(Duration::from_secs(5)),
+		}
+	}
+}
+
+impl PoolOptions {
+	pub fn with_size(mut self, min_size: usize, max_size: usize) -> Self {
+		self.min_size = min_size.min(max_size);
+		self.max_size = max_size.max(1);
+		self
+	}
+
+	pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+		self.idle_timeout = idle_timeout;
+		self
+	}
+
+	pub fn with_health_check(mut self, after: Option<Duration>) -> Self {
+		self.health_check_after = after;
+		self
+	}
+}
+
+/// Metrics of connection pool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolMetrics {
+	/// connections opened.
+	pub created: u64,
+	/// idle connections reused.
+	pub reused: u64,
+	/// idle connections closed because `/ping` failed.
+	pub health_check_failures: u64,
+	/// times of waiting for a free connection.
+	pub waits: u64,
+	pub total_wait: Duration,
+	pub max_wait: Duration,
+	pub idle: usize,
+	pub in_use: usize,
+}
+
+impl PoolMetrics {
+	pub fn mean_wait(&self) -> Duration {
+		match self.waits {
+			0 => Duration::ZERO,
+			waits => self.total_wait / waits as u32,
+		}
+	}
+}
+
+/// Pool of connections to one host, connections are returned after response body is read.
+#[derive(Debug)]
+pub(crate) struct Pool {
+	host: String,
+	port: u16,
+	/// set for `https` hosts.
+	tls: Option<Arc<TlsConfig>>,
+	options: PoolOptions,
+	idle: Mutex<Vec<IdleConnection>>,
+	permits: Arc<Semaphore>,
+	metrics: Mutex<PoolMetrics>,
+	/// moving average of connect and `/ping` latency in microseconds, 0 if not measured.
+	latency: AtomicU64,
+}
+
+#[derive(Debug)]
+struct IdleConnection {
+	sender: SendRequest<Full<Bytes>>,
+	idle_since: Instant,
+}
+
+/// Connection taken from pool, it's in use until released.
+pub(crate) struct Connection {
+	pub sender: SendRequest<Full<Bytes>>,
+	/// it's an idle connection, the server may have closed it.
+	pub reused: bool,
+	permit: OwnedSemaphorePermit,
+}
+
+impl Pool {
+	pub(crate) fn new(host: &str, port: u16, tls: Option<Arc<TlsConfig>>, options: PoolOptions) -> Pool {
+		Pool {
+			host: host.to_owned(),
+			port,
+			tls,
+			permits: Arc::new(Semaphore::new(options.max_size)),
+			options,
+			idle: Mutex::new(Vec::new()),
+			metrics: Mutex::new(PoolMetrics::default()),
+			latency: AtomicU64::new(0),
+		}
+	}
+
+	pub(crate) fn latency(&self) -> Option<Duration> {
+		match self.latency.load(Ordering::Relaxed) {
+			0 => None,
+			micros => Some(Duration::from_micros(micros)),
+		}
+	}
+
+	/// `Host` header of requests.
+	pub(crate) fn host_header(&self) -> String {
+		format!("{}:{}", self.host, self.port)
+	}
+
+	pub(crate) fn metrics(&self) -> PoolMetrics {
+		let mut metrics = *self.metrics.lock().unwrap();
+		metrics.idle = self.idle.lock().unwrap().len();
+		metrics.in_use = self.options.max_size - self.permits.available_permits();
+		metrics
+	}
+
+	/// take an idle connection or open a new one, wait if `max_size` connections are in use.
+	pub(crate) async fn get(&self) -> Result<Connection> {
+		let start = Instant::now();
+		let permit = self.permits.clone().acquire_owned().await.expect("semaphore of pool is never closed");
+		let waited = start.elapsed();
+		self.record(|m| {
+			m.waits += 1;
+			m.total_wait += waited;
+			m.max_wait = m.max_wait.max(waited);
+		});
+
+		while let Some(idle) = self.take_idle() {
+			let mut sender = idle.sender;
+			if sender.is_closed() {
+				continue;
+			}
+			if self.options.health_check_after.is_some_and(|after| idle.idle_since.elapsed() >= after)
+				&& self.ping(&mut sender).await.is_err()
+			{
+				self.record(|m| m.health_check_failures += 1);
+				continue;
+			}
+			self.record(|m| m.reused += 1);
+			return Ok(Connection { sender, reused: true, permit });
+		}
+		self.connect(permit).await
+	}
+
+	/// check the host by `GET /ping` over a pooled connection.
+	pub(crate) async fn check(self: &Arc<Self>) -> Result<()> {
+		let mut connection = self.get().await?;
+		let result = self.ping(&mut connection.sender).await;
+		self.release(connection);
+		result
+	}
+
+	/// open a new connection regardless of idle ones, it's used when an idle one turns out to be
+	/// closed.
+	pub(crate) async fn reconnect(&self, connection: Connection) -> Result<Connection> {
+		self.connect(connection.permit).await
+	}
+
+	/// return the connection once the response body is read, it's closed if the body is dropped.
+	pub(crate) fn release(self: &Arc<Self>, connection: Connection) {
+		let pool = self.clone();
+		tokio::spawn(async move {
+			let Connection { mut sender, permit, .. } = connection;
+			if sender.ready().await.is_ok() {
+				pool.put(sender);
+			}
+			drop(permit);
+		});
+	}
+
+	async fn connect(&self, permit: OwnedSemaphorePermit) -> Result<Connection> {
+		let start = Instant::now();
+		let stream = TcpStream::connect((self.host.as_str(), self.port)).await?;
+		stream.set_nodelay(true)?;
+		let sender = match &self.tls {
+			Some(tls) => handshake(TokioIo::new(tls.connect(&self.host, stream).await?)).await?,
+			None => handshake(TokioIo::new(stream)).await?,
+		};
+		self.record_latency(start.elapsed());
+		self.record(|m| m.created += 1);
+		Ok(Connection { sender, reused: false, permit })
+	}
+
+	/// `GET /ping` over the connection, ClickHouse answers `Ok.`.
+	async fn ping(&self, sender: &mut SendRequest<Full<Bytes>>) -> Result<()> {
+		let start = Instant::now();
+		let request = Request::get("/ping").header(header::HOST, self.host_header()).body(Full::new(Bytes::new()));
+		let response = sender.send_request(request.map_err(|e| Error::InvalidUrl(e.to_string()))?).await?;
+		let status = response.status();
+		let body = response.into_body().collect().await?.to_bytes();
+		if status != StatusCode::OK {
+			return Err(Error::BadResponse(format!("{}: {}", status, String::from_utf8_lossy(&body).trim_end())));
+		}
+		self.record_latency(start.elapsed());
+		sender.ready().await?;
+		Ok(())
+	}
+
+	fn record_latency(&self, latency: Duration) {
+		let micros = (latency.as_micros() as u64).max(1);
+		let _ = self.latency.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |average| match average {
+			0 => Some(micros),
+			average => Some((average * 4 + micros) / 5),
+		});
+	}
+
+	fn put(&self, sender: SendRequest<Full<Bytes>>) {
+		let mut idle = self.idle.lock().unwrap();
+		idle.push(IdleConnection { sender, idle_since: Instant::now() });
+		self.prune(&mut idle);
+	}
+
+	/// the most recently used connection, it's the least likely to be closed by server.
+	fn take_idle(&self) -> Option<IdleConnection> {
+		let mut idle = self.idle.lock().unwrap();
+		self.prune(&mut idle);
+		idle.pop()
+	}
+
+	/// close connections idle longer than idle timeout except the newest `min_size` ones.
+	fn prune(&self, idle: &mut Vec<IdleConnection>) {
+		let expired = idle.len().saturating_sub(self.options.min_size);
+		let expired =
+			idle[..expired].iter().take_while(|c| c.idle_since.elapsed() >= self.options.idle_timeout).count();
+		idle.drain(..expired);
+		idle.retain(|c| !c.sender.is_closed());
+	}
+
+	fn record(&self, update: impl FnOnce(&mut PoolMetrics)) {
+		update(&mut self.metrics.lock().unwrap());
+	}
+}
+
+async fn handshake<T>(io: T) -> Result<SendRequest<Full<Bytes>>>
+where
+	T: Read + Write + Unpin + Send + 'static,
+{
+	let (sender, conn) = hyper::client::conn::http1::handshake(io).await?;
+	tokio::spawn(conn);
+	Ok(sender)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use hyper::StatusCode;
+
+	use super::PoolOptions;
+	use crate::client::tests::MockServer;
+	use crate::client::tests::Reply;
+	use crate::Client;
+
+	/// connections are returned to pool in background.
+	async fn wait_idle(client: &Client, idle: usize) {
+		for _ in 0..100 {
+			if client.pool_metrics().idle == idle {
+				return;
+			}
+			tokio::time::sleep(Duration::from_millis(10)).await;
+		}
+		panic!("{} idle connections expected: {:?}", idle, client.pool_metrics());
+	}
+
+	#[tokio::test]
+	async fn test_pool() {
+		let server = MockServer::start(|_| (StatusCode::OK, String::new())).await;
+		let options = PoolOptions::default().with_size(0, 2).with_health_check(None);
+		let client = server.client().with_pool(options.clone());
+		for _ in 0..3 {
+			client.query("SELECT 1").execute().await.unwrap();
+			wait_idle(&client, 1).await;
+		}
+		let metrics = client.pool_metrics();
+		assert_eq!((1, 2, 3, 0), (metrics.created, metrics.reused, metrics.waits, metrics.in_use));
+
+		let client = server.client().with_pool(options.clone().with_health_check(Some(Duration::ZERO)));
+		client.ping().await.unwrap();
+		wait_idle(&client, 1).await;
+		client.query("SELECT 1").execute().await.unwrap();
+		let paths = server.requests().iter().rev().take(3).map(|r| r.path.clone()).collect::<Vec<_>>();
+		assert_eq!(vec!["/", "/ping", "/ping"], paths);
+		assert_eq!((1, 1), (client.pool_metrics().created, client.pool_metrics().reused));
+
+		let client = server.client().with_pool(options.with_idle_timeout(Duration::ZERO));
+		client.query("SELECT 1").execute().await.unwrap();
+		client.query("SELECT 1").execute().await.unwrap();
+		assert_eq!((2, 0), (client.pool_metrics().created, client.pool_metrics().reused));
+	}
+
+	#[tokio::test]
+	async fn test_health_check_and_wait() {
+		let server = MockServer::start_with(|request| match request.path.as_str() {
+			"/ping" => Reply { status: StatusCode::SERVICE_UNAVAILABLE, ..Reply::ok("") },
+			_ if request.query.starts_with("SELECT") => Reply::ok("1").pending(),
+			_ => Reply::ok(""),
+		})
+		.await;
+		let options = PoolOptions::default().with_size(1, 1).with_health_check(Some(Duration::ZERO));
+		let client = server.client().with_pool(options);
+
+		let cursor = client.query("SELECT 1").fetch::<u8>().await.unwrap();
+		assert_eq!(1, client.pool_metrics().in_use);
+		let waiting = tokio::spawn({
+			let client = client.clone();
+			async move { client.query("KILL QUERY WHERE 1").execute().await }
+		});
+		tokio::time::sleep(Duration::from_millis(50)).await;
+		drop(cursor);
+		waiting.await.unwrap().unwrap();
+		// KILL QUERY of the dropped cursor is sent in background.
+		server.wait_requests(3).await;
+		wait_idle(&client, 1).await;
+		let metrics = client.pool_metrics();
+		assert!(metrics.max_wait >= Duration::from_millis(50), "{:?}", metrics);
+		assert!(metrics.mean_wait() > Duration::ZERO);
+
+		client.query("KILL QUERY WHERE 1").execute().await.unwrap();
+		let failures = client.pool_metrics().health_check_failures;
+		assert_eq!(metrics.health_check_failures + 1, failures);
+		assert_eq!(metrics.created + 1, client.pool_metrics().created);
+	}
+}
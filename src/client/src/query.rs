@@ -0,0 +1,555 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::future::Future;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use hyper::body::Incoming;
+use rickhouse_common::metadata::DataType;
+use rickhouse_common::metadata::Row;
+use rickhouse_common::metadata::RowSchema;
+use rickhouse_common::metadata::SchemaColumn;
+use rickhouse_common::metadata::Strictness;
+use rickhouse_common::rowbinary;
+use rickhouse_common::sql::quote_identifier;
+use rickhouse_common::values::value::Value;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::cancel::generate_query_id;
+use crate::cancel::CancelHandle;
+use crate::client::discard_body;
+use crate::client::Client;
+use crate::client::RequestBody;
+use crate::cursor::RowCursor;
+use crate::error::Error;
+use crate::error::Result;
+use crate::response::Progress;
+use crate::response::ResponseInfo;
+use crate::settings::Settings;
+
+type ProgressCallback = Arc<dyn Fn(&Progress) + Send + Sync>;
+
+/// Query of ClickHouse, result is fetched in `RowBinaryWithNamesAndTypes` format.
+#[derive(Clone)]
+pub struct Query {
+	client: Client,
+	sql: String,
+	settings: Settings,
+	params: Vec<(String, Value)>,
+	external_tables: Vec<ExternalTable>,
+	on_progress: Option<ProgressCallback>,
+	strictness: Strictness,
+	cancel: CancelHandle,
+}
+
+impl Query {
+	pub(crate) fn new(client: Client, sql: &str) -> Query {
+		let cancel = CancelHandle::new(client.clone(), generate_query_id());
+		let (settings, params, external_tables) = (Settings::new(), Vec::new(), Vec::new());
+		let strictness = client.strictness();
+		Query { client, sql: sql.to_owned(), settings, params, external_tables, on_progress: None, strictness, cancel }
+	}
+
+	/// set `query_id` instead of the generated one, handles got before are not affected.
+	pub fn with_query_id(mut self, query_id: impl Into<String>) -> Self {
+		self.cancel = CancelHandle::new(self.client.clone(), query_id.into());
+		self
+	}
+
+	pub fn query_id(&self) -> &str {
+		self.cancel.query_id()
+	}
+
+	/// handle to cancel the query from another task.
+	pub fn cancel_handle(&self) -> CancelHandle {
+		self.cancel.clone()
+	}
+
+	/// set setting of the query, see [`Settings::set`].
+	pub fn with_setting(mut self, name: &str, value: impl Display) -> Result<Self> {
+		self.settings = self.settings.set(name, value)?;
+		Ok(self)
+	}
+
+	/// merge settings of the query, they override settings of the client.
+	pub fn with_settings(mut self, settings: &Settings) -> Self {
+		self.settings = self.settings.merge(settings);
+		self
+	}
+
+	/// strictness of checking result columns against [`RowSchema`] of fetched rows, the one of
+	/// client by default.
+	pub fn with_strictness(mut self, strictness: Strictness) -> Self {
+		self.strictness = strictness;
+		self
+	}
+
+	/// bind value of placeholder `{name:Type}`, it's encoded by the type of placeholder, e.g.
+	/// `client.query("SELECT * FROM t WHERE id = {id:UInt64}").param("id", 42)`.
+	pub fn param(mut self, name: &str, value: impl Into<Value>) -> Self {
+		self.params.retain(|(n, _)| n != name);
+		self.params.push((name.to_owned(), value.into()));
+		self
+	}
+
+	/// send `rows` with the query as table `name` of `metadata` columns, rows are encoded in
+	/// RowBinary, e.g. `client.query("SELECT * FROM t WHERE id IN ids").external_table("ids",
+	/// &metadata, &ids)` instead of a huge `IN` list.
+	pub fn external_table<T: Serialize>(
+		mut self,
+		name: &str,
+		metadata: &[(String, DataType)],
+		rows: &[T],
+	) -> Result<Self> {
+		if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+			return Err(Error::InvalidParam(format!("invalid name of external table `{}`", name)));
+		}
+		if metadata.is_empty() {
+			return Err(Error::InvalidParam(format!("external table `{}` has no columns", name)));
+		}
+		let columns = metadata
+			.iter()
+			.map(|(name, data_type)| SchemaColumn { name: name.clone(), data_type: data_type.clone(), default: false })
+			.collect::<Vec<_>>();
+		let mut data = Vec::new();
+		for row in rows {
+			rowbinary::write_row(&mut data, row, &columns)?;
+		}
+		let structure = metadata.iter().map(|(name, data_type)| format!("{} {}", quote_identifier(name), data_type));
+		let structure = structure.collect::<Vec<_>>().join(", ");
+		self.external_tables.retain(|table| table.name != name);
+		self.external_tables.push(ExternalTable { name: name.to_owned(), structure, data: Bytes::from(data) });
+		Ok(self)
+	}
+
+	/// call `callback` with progress headers of response, `send_progress_in_http_headers` is
+	/// enabled for the query.
+	pub fn on_progress<F: Fn(&Progress) + Send + Sync + 'static>(mut self, callback: F) -> Self {
+		self.settings = self.settings.send_progress_in_http_headers(true);
+		self.on_progress = Some(Arc::new(callback));
+		self
+	}
+
+	/// execute query and ignore its result, e.g. DDL.
+	pub async fn execute(self) -> Result<()> {
+		let (info, body) = self.send(None).await?;
+		self.cancellable(discard_body(&info, body)).await
+	}
+
+	/// fetch rows by cursor, rows are decoded as they arrive.
+	///
+	/// columns of result are checked against [`RowSchema`] of `T` before the first row, all
+	/// mismatched columns are reported by [`Error::CommonError`]. Columns of tuples and scalars are
+	/// matched by position.
+	pub async fn fetch<T: RowSchema>(self) -> Result<RowCursor<T>> {
+		let strictness = self.strictness;
+		Ok(self.cursor().await?.with_schema(T::columns(), strictness))
+	}
+
+	/// fetch all rows as dynamic values.
+	pub async fn fetch_rows(self) -> Result<Vec<Row>> {
+		let mut cursor = self.cursor::<Row>().await?;
+		let mut rows = Vec::new();
+		while let Some(row) = cursor.next_row().await? {
+			rows.push(row);
+		}
+		Ok(rows)
+	}
+
+	/// fetch all rows into `T`.
+	pub async fn fetch_all<T: DeserializeOwned + RowSchema>(self) -> Result<Vec<T>> {
+		let mut cursor = self.fetch::<T>().await?;
+		let mut rows = Vec::new();
+		while let Some(row) = cursor.next().await? {
+			rows.push(row);
+		}
+		Ok(rows)
+	}
+
+	/// fetch the first row into `T`, `None` if there is no row. The rest of rows are read to the
+	/// end, so the query isn't killed and an exception after the first row is returned. Add `LIMIT
+	/// 1` to the query if more rows may be sent.
+	pub async fn fetch_optional<T: DeserializeOwned + RowSchema>(self) -> Result<Option<T>> {
+		let mut cursor = self.fetch::<T>().await?;
+		let row = cursor.next().await?;
+		while cursor.next_row().await?.is_some() {}
+		Ok(row)
+	}
+
+	async fn cursor<T>(self) -> Result<RowCursor<T>> {
+		let options = self.client.settings().clone().merge(&self.settings).format_options();
+		let (info, body) = self.send(Some("RowBinaryWithNamesAndTypes")).await?;
+		Ok(RowCursor::new(info, body, options, self.cancel))
+	}
+
+	async fn send(&self, format: Option<&str>) -> Result<(ResponseInfo, Incoming)> {
+		let params = self.encode_params()?;
+		let mut settings = self.settings.iter().collect::<Vec<_>>();
+		settings.extend(params.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+		settings.push(("query_id", self.cancel.query_id()));
+		if let Some(format) = format {
+			settings.push(("default_format", format));
+		}
+		let tables = self.external_tables.iter().flat_map(|table| {
+			[
+				(format!("{}_structure", table.name), table.structure.as_str()),
+				(format!("{}_format", table.name), "RowBinary"),
+			]
+		});
+		let tables = tables.collect::<Vec<_>>();
+		settings.extend(tables.iter().map(|(name, value)| (name.as_str(), *value)));
+		let body = match self.external_tables.is_empty() {
+			true => RequestBody::default(),
+			false => multipart(&self.external_tables),
+		};
+		// reads fail over to other hosts.
+		let send = self.client.send(&self.sql, &settings, body, format.is_some());
+		let (info, body) = self.cancellable(send).await?;
+		if let Some(callback) = &self.on_progress {
+			info.progress.iter().for_each(|progress| callback(progress));
+		}
+		Ok((info, body))
+	}
+
+	/// run `future` until the query is cancelled.
+	async fn cancellable<R>(&self, future: impl Future<Output = Result<R>>) -> Result<R> {
+		tokio::select! {
+			result = future => result,
+			_ = self.cancel.cancelled() => Err(Error::Cancelled(self.cancel.query_id().to_owned())),
+		}
+	}
+
+	/// encode params as `param_<name>` settings by types of their placeholders.
+	fn encode_params(&self) -> Result<Vec<(String, String)>> {
+		if self.params.is_empty() {
+			return Ok(Vec::new());
+		}
+
+		let placeholders = placeholders(&self.sql);
+		let mut encoded = Vec::with_capacity(self.params.len());
+		for (name, value) in &self.params {
+			let Some((_, type_name)) = placeholders.iter().find(|(n, _)| n == name) else {
+				return Err(Error::InvalidParam(format!("no placeholder for parameter `{}`", name)));
+			};
+			let text = match *type_name {
+				"Identifier" => quote_identifier(&value.to_string()),
+				type_name => {
+					let data_type = type_name
+						.parse::<DataType>()
+						.map_err(|e| Error::InvalidParam(format!("type of parameter `{}`: {}", name, e)))?;
+					value.to_param(&data_type)?
+				}
+			};
+			encoded.push((format!("param_{}", name), text));
+		}
+		Ok(encoded)
+	}
+}
+
+impl Debug for Query {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("Query")
+			.field("client", &self.client)
+			.field("sql", &self.sql)
+			.field("query_id", &self.query_id())
+			.field("settings", &self.settings)
+			.field("params", &self.params)
+			.field("external_tables", &self.external_tables.iter().map(|table| &table.name).collect::<Vec<_>>())
+			.finish_non_exhaustive()
+	}
+}
+
+/// Table sent with query, see [`Query::external_table`].
+#[derive(Debug, Clone)]
+struct ExternalTable {
+	name: String,
+	/// columns like `` `id` UInt64, `name` String ``.
+	structure: String,
+	/// rows in RowBinary.
+	data: Bytes,
+}
+
+/// `multipart/form-data` body of external tables, each file is named by its table.
+fn multipart(tables: &[ExternalTable]) -> RequestBody {
+	let boundary = format!("rickhouse-{}", generate_query_id());
+	let mut data = Vec::with_capacity(tables.iter().map(|table| table.data.len() + 256).sum());
+	for table in tables {
+		let header = format!(
+			"--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n\
+			Content-Type: application/octet-stream\r\n\r\n",
+			boundary, table.name, table.name
+		);
+		data.extend_from_slice(header.as_bytes());
+		data.extend_from_slice(&table.data);
+		data.extend_from_slice(b"\r\n");
+	}
+	data.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+	RequestBody { data: Bytes::from(data), content_type: Some(format!("multipart/form-data; boundary={}", boundary)) }
+}
+
+/// find placeholders `{name:Type}` in sql, returns names with types.
+fn placeholders(sql: &str) -> Vec<(&str, &str)> {
+	let bytes = sql.as_bytes();
+	let mut placeholders = Vec::new();
+	let mut pos = 0;
+	while pos < bytes.len() {
+		match bytes[pos] {
+			// braces in string literals and quoted identifiers aren't placeholders.
+			quote @ (b'\'' | b'"' | b'`') => pos = skip_quoted(bytes, pos + 1, quote),
+			b'{' => {
+				pos += 1;
+				let Some(end) = sql[pos..].find('}') else {
+					break;
+				};
+				if let Some((name, type_name)) = sql[pos..pos + end].split_once(':') {
+					let name = name.trim();
+					let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+					if valid && !type_name.trim().is_empty() {
+						placeholders.push((name, type_name.trim()));
+						pos += end + 1;
+					}
+				}
+			}
+			_ => pos += 1,
+		}
+	}
+	placeholders
+}
+
+/// position after the closing `quote`, quotes are escaped by backslash or doubled.
+fn skip_quoted(bytes: &[u8], mut pos: usize, quote: u8) -> usize {
+	while pos < bytes.len() {
+		match bytes[pos] {
+			b'\\' => pos += 2,
+			c if c == quote && bytes.get(pos + 1) == Some(&quote) => pos += 2,
+			c if c == quote => return pos + 1,
+			_ => pos += 1,
+		}
+	}
+	pos
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeMap;
+
+	use bytes::Bytes;
+	use rickhouse_common::metadata::DataType;
+	use rickhouse_common::metadata::RowSchema;
+	use rickhouse_common::metadata::SchemaColumn;
+	use rickhouse_common::metadata::Strictness;
+	use rickhouse_common::rowbinary;
+	use rickhouse_common::rowbinary::FormatOptions;
+	use rickhouse_derive::Row;
+	use serde::Deserialize;
+	use serde::Serialize;
+
+	use crate::client::tests::MockServer;
+	use crate::client::tests::Reply;
+	use crate::error::Error;
+	use crate::query::placeholders;
+	use crate::settings::Readonly;
+	use crate::settings::Settings;
+
+	const BINARY_TYPES: &str = "output_format_binary_encode_types_in_binary_format";
+
+	#[derive(Debug, PartialEq, Serialize, Deserialize, Row)]
+	struct Table {
+		name: String,
+		total_rows: Option<u64>,
+	}
+
+	#[tokio::test]
+	async fn test_fetch() {
+		let server = MockServer::start_with(|request| match request.query.as_str() {
+			"SELECT name, total_rows FROM system.tables" => {
+				let columns = Table::columns();
+				let mut body = Vec::new();
+				rowbinary::write_header(&mut body, &columns);
+				rowbinary::write_row(&mut body, &Table { name: "a".to_owned(), total_rows: Some(1) }, &columns)
+					.unwrap();
+				rowbinary::write_row(&mut body, &Table { name: "b".to_owned(), total_rows: None }, &columns).unwrap();
+				Reply::ok(body)
+			}
+			_ => Reply::ok(Bytes::new()),
+		})
+		.await;
+		let client = server.client();
+
+		let tables = client.query("SELECT name, total_rows FROM system.tables").fetch_all::<Table>().await.unwrap();
+		assert_eq!(Table { name: "b".to_owned(), total_rows: None }, tables[1]);
+		let rows = client.query("SELECT name, total_rows FROM system.tables").fetch_rows().await.unwrap();
+		assert_eq!(Some(1), rows[0].get::<u64>("total_rows"));
+		assert_eq!(None, client.query("SELECT 1 WHERE 0").fetch_optional::<u8>().await.unwrap());
+		client.query("CREATE TABLE t").execute().await.unwrap();
+
+		let requests = server.requests();
+		assert_eq!(Some("RowBinaryWithNamesAndTypes"), requests[0].param("default_format"));
+		assert_eq!(None, requests[3].param("default_format"));
+	}
+
+	#[tokio::test]
+	async fn test_fetch_schema_mismatch() {
+		let server = MockServer::start_with(|_| {
+			let columns =
+				[("name", "LowCardinality(String)"), ("total_rows", "Nullable(UInt32)"), ("engine", "String")].map(
+					|(name, ty)| SchemaColumn { name: name.to_owned(), data_type: ty.parse().unwrap(), default: false },
+				);
+			let mut body = Vec::new();
+			rowbinary::write_header(&mut body, &columns);
+			rowbinary::write_row(&mut body, &("a", Some(1u32), "Log"), &columns).unwrap();
+			Reply::ok(body)
+		})
+		.await;
+		let client = server.client();
+
+		let err = client.query("SELECT * FROM system.tables").fetch_all::<Table>().await.unwrap_err();
+		assert_eq!(
+			"Schema mismatch: column `name`: expected String, server sent LowCardinality(String); column \
+			 `total_rows`: expected Nullable(UInt64), server sent Nullable(UInt32); column `engine`: unexpected \
+			 column of type String",
+			err.to_string()
+		);
+		let query = client.query("SELECT * FROM system.tables").with_strictness(Strictness::AllowWidening);
+		let tables = query.fetch_all::<Table>().await.unwrap();
+		assert_eq!(vec![Table { name: "a".to_owned(), total_rows: Some(1) }], tables);
+		let client = client.with_strictness(Strictness::AllowWidening);
+		assert_eq!(tables, client.query("SELECT * FROM system.tables").fetch_all::<Table>().await.unwrap());
+		// dynamic rows are not checked.
+		assert_eq!(3, client.query("SELECT * FROM system.tables").fetch_rows().await.unwrap()[0].len());
+	}
+
+	#[test]
+	fn test_placeholders() {
+		let sql =
+			"SELECT {col:Identifier} FROM t WHERE id = {id: UInt64} AND m = {m:Map(String, Array(Int8))} AND s = '{x}'";
+		assert_eq!(vec![("col", "Identifier"), ("id", "UInt64"), ("m", "Map(String, Array(Int8))")], placeholders(sql));
+		let sql =
+			"SELECT '{a:String}', 'it\\'s {b:String}', 'it''s {c:String}', `{d:UInt8}` AS \"{e:UInt8}\", {f:UInt8}";
+		assert_eq!(vec![("f", "UInt8")], placeholders(sql));
+	}
+
+	#[tokio::test]
+	async fn test_params() {
+		let server = MockServer::start_with(|_| Reply::ok(Bytes::new())).await;
+		let client = server.client();
+
+		let sql = "SELECT {col:Identifier} FROM t WHERE name = {name:String} AND tags = {tags:Array(String)} \
+			AND pair = {pair:Tuple(UInt8, Nullable(String))} AND attrs = {attrs:Map(String, UInt32)} \
+			AND ts > {ts:DateTime64(3)}";
+		let ts = chrono::DateTime::from_timestamp(1706702400, 0).unwrap();
+		client
+			.query(sql)
+			.param("col", "name")
+			.param("name", "it's a\ttab\\")
+			.param("tags", vec!["a'b", "c\\d"])
+			.param("pair", (1, None::<String>))
+			.param("attrs", BTreeMap::from([("k", 1u32)]))
+			.param("ts", ts)
+			.execute()
+			.await
+			.unwrap();
+
+		let request = &server.requests()[0];
+		assert_eq!(Some("`name`"), request.param("param_col"));
+		assert_eq!(Some("it's a\\ttab\\\\"), request.param("param_name"));
+		assert_eq!(Some("['a\\'b','c\\\\d']"), request.param("param_tags"));
+		assert_eq!(Some("(1,NULL)"), request.param("param_pair"));
+		assert_eq!(Some("{'k':1}"), request.param("param_attrs"));
+		assert_eq!(Some("1706702400.000"), request.param("param_ts"));
+
+		let err = client.query("SELECT {id:UInt64}").param("name", 1).execute().await.unwrap_err();
+		assert!(matches!(err, Error::InvalidParam(_)));
+		let err = client.query("SELECT {id:UInt64}").param("id", vec![1]).execute().await.unwrap_err();
+		assert!(matches!(err, Error::CommonError(_)));
+		assert_eq!(1, server.requests().len());
+	}
+
+	#[tokio::test]
+	async fn test_external_table() {
+		let server = MockServer::start_with(|_| Reply::ok(Bytes::new())).await;
+		let metadata = vec![("id".to_owned(), DataType::UInt64), ("name".to_owned(), DataType::String)];
+		let rows = vec![(1u64, "a"), (2, "bc")];
+		server
+			.client()
+			.query("SELECT * FROM t WHERE (id, name) IN ids")
+			.external_table("ids", &metadata, &[(0u64, "replaced")])
+			.unwrap()
+			.external_table("ids", &metadata, &rows)
+			.unwrap()
+			.external_table("empty", &metadata[..1], &Vec::<(u64,)>::new())
+			.unwrap()
+			.execute()
+			.await
+			.unwrap();
+
+		let request = server.requests().pop().unwrap();
+		assert_eq!(Some("`id` UInt64, `name` String"), request.param("ids_structure"));
+		assert_eq!(
+			(Some("RowBinary"), Some("`id` UInt64")),
+			(request.param("ids_format"), request.param("empty_structure"))
+		);
+		let content_type = request.headers.iter().find(|(name, _)| name == "content-type").map(|(_, v)| v.as_str());
+		let boundary = content_type.and_then(|v| v.strip_prefix("multipart/form-data; boundary=")).unwrap();
+		let part = |name: &str, data: &[u8]| {
+			let mut part = format!(
+				"--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n\
+				Content-Type: application/octet-stream\r\n\r\n",
+				boundary, name, name
+			)
+			.into_bytes();
+			part.extend_from_slice(data);
+			part.extend_from_slice(b"\r\n");
+			part
+		};
+		let ids = [&1u64.to_le_bytes()[..], b"\x01a", &2u64.to_le_bytes(), b"\x02bc"].concat();
+		let body = [part("ids", &ids), part("empty", b""), format!("--{}--\r\n", boundary).into_bytes()].concat();
+		assert_eq!(Bytes::from(body), request.body);
+
+		let query = server.client().query("SELECT 1");
+		assert!(matches!(query.clone().external_table("a b", &metadata, &rows), Err(Error::InvalidParam(_))));
+		assert!(matches!(query.clone().external_table("ids", &Vec::new(), &rows), Err(Error::InvalidParam(_))));
+		assert!(query.external_table("ids", &metadata, &[("x", 1u64)]).is_err());
+	}
+
+	#[tokio::test]
+	async fn test_settings() {
+		let server = MockServer::start_with(|_| Reply::ok(Bytes::new())).await;
+		let settings = Settings::new().max_result_rows(10).readonly(Readonly::On);
+		let client = server.client().with_settings(&settings).with_setting("max_threads", 2).unwrap();
+
+		client.query("SELECT 1").with_setting("max_result_rows", 20).unwrap().execute().await.unwrap();
+		let request = &server.requests()[0];
+		assert_eq!(Some("20"), request.param("max_result_rows"));
+		assert_eq!(Some("1"), request.param("readonly"));
+		assert_eq!(Some("2"), request.param("max_threads"));
+		assert_eq!(1, request.params.iter().filter(|(name, _)| name == "max_result_rows").count());
+
+		assert!(client.query("SELECT 1").with_setting("readonly", "yes").is_err());
+		assert!(client.clone().with_setting("default_format", "JSON").is_err());
+	}
+
+	#[tokio::test]
+	async fn test_binary_types() {
+		let server = MockServer::start_with(|request| {
+			let columns = Table::columns();
+			let options = FormatOptions { binary_types: request.param(BINARY_TYPES) == Some("1") };
+			let mut body = Vec::new();
+			rowbinary::write_header_with(&mut body, &columns, &options);
+			rowbinary::write_row(&mut body, &Table { name: "a".to_owned(), total_rows: None }, &columns).unwrap();
+			Reply::ok(body)
+		})
+		.await;
+		let client = server.client();
+
+		let settings = Settings::new().output_format_binary_encode_types_in_binary_format(true);
+		let tables = client.query("SELECT 1").with_settings(&settings).fetch_all::<Table>().await.unwrap();
+		assert_eq!("a", tables[0].name);
+		let client = client.with_settings(&settings);
+		assert_eq!(1, client.query("SELECT 1").fetch_rows().await.unwrap().len());
+		let settings = Settings::new().output_format_binary_encode_types_in_binary_format(false);
+		assert_eq!(1, client.query("SELECT 1").with_settings(&settings).fetch_rows().await.unwrap().len());
+	}
+}
@@ -0,0 +1,126 @@
+use std::fmt::Formatter;
+
+use hyper::HeaderMap;
+use serde::de::Visitor;
+use serde::Deserialize;
+use serde::Deserializer;
+
+/// Progress of query, from `X-ClickHouse-Progress` headers sent with
+/// `send_progress_in_http_headers=1`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Progress {
+	#[serde(deserialize_with = "number")]
+	pub read_rows: u64,
+	#[serde(deserialize_with = "number")]
+	pub read_bytes: u64,
+	#[serde(deserialize_with = "number")]
+	pub written_rows: u64,
+	#[serde(deserialize_with = "number")]
+	pub written_bytes: u64,
+	#[serde(deserialize_with = "number")]
+	pub total_rows_to_read: u64,
+	#[serde(deserialize_with = "number")]
+	pub result_rows: u64,
+	#[serde(deserialize_with = "number")]
+	pub result_bytes: u64,
+	#[serde(deserialize_with = "number")]
+	pub elapsed_ns: u64,
+}
+
+/// Summary of query from `X-ClickHouse-Summary` header, it's the progress when response starts.
+pub type Summary = Progress;
+
+/// Information of response from headers of ClickHouse.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResponseInfo {
+	/// `X-ClickHouse-Query-Id`.
+	pub query_id: Option<String>,
+	/// `X-ClickHouse-Summary`.
+	pub summary: Option<Summary>,
+	/// `X-ClickHouse-Timezone`, timezone of server.
+	pub timezone: Option<String>,
+	/// `X-ClickHouse-Format`, format of body.
+	pub format: Option<String>,
+	/// `X-ClickHouse-Progress` in the order they are sent.
+	pub progress: Vec<Progress>,
+	/// `X-ClickHouse-Exception-Tag`, marker of exception written into body after some rows.
+	pub exception_tag: Option<String>,
+}
+
+impl ResponseInfo {
+	pub(crate) fn from_headers(headers: &HeaderMap) -> ResponseInfo {
+		let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_owned);
+		let progress = headers
+			.get_all("X-ClickHouse-Progress")
+			.iter()
+			.filter_map(|value| serde_json::from_slice(value.as_bytes()).ok())
+			.collect();
+		ResponseInfo {
+			query_id: header("X-ClickHouse-Query-Id"),
+			summary: header("X-ClickHouse-Summary").and_then(|s| serde_json::from_str(&s).ok()),
+			timezone: header("X-ClickHouse-Timezone"),
+			format: header("X-ClickHouse-Format"),
+			progress,
+			exception_tag: header("X-ClickHouse-Exception-Tag"),
+		}
+	}
+}
+
+/// numbers are quoted by ClickHouse, e.g. `{"read_rows":"1"}`.
+fn number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+	struct NumberVisitor;
+
+	impl Visitor<'_> for NumberVisitor {
+		type Value = u64;
+
+		fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+			f.write_str("number or quoted number")
+		}
+
+		fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<u64, E> {
+			Ok(v)
+		}
+
+		fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<u64, E> {
+			v.parse().map_err(E::custom)
+		}
+	}
+
+	deserializer.deserialize_any(NumberVisitor)
+}
+
+#[cfg(test)]
+mod tests {
+	use hyper::header::HeaderValue;
+	use hyper::HeaderMap;
+
+	use super::*;
+
+	#[test]
+	fn test_response_info() {
+		let mut headers = HeaderMap::new();
+		headers.insert("X-ClickHouse-Query-Id", HeaderValue::from_static("q-1"));
+		headers.insert("X-ClickHouse-Timezone", HeaderValue::from_static("Europe/Istanbul"));
+		headers.insert("X-ClickHouse-Format", HeaderValue::from_static("RowBinaryWithNamesAndTypes"));
+		headers
+			.append("X-ClickHouse-Progress", HeaderValue::from_static(r#"{"read_rows":"1","total_rows_to_read":"3"}"#));
+		headers.append("X-ClickHouse-Progress", HeaderValue::from_static(r#"{"read_rows":"3","read_bytes":24}"#));
+		headers.insert(
+			"X-ClickHouse-Summary",
+			HeaderValue::from_static(
+				r#"{"read_rows":"3","read_bytes":"24","written_rows":"0","written_bytes":"0","total_rows_to_read":"3","result_rows":"3","result_bytes":"72","elapsed_ns":"1500"}"#,
+			),
+		);
+
+		let info = ResponseInfo::from_headers(&headers);
+		assert_eq!(Some("q-1"), info.query_id.as_deref());
+		assert_eq!(Some("Europe/Istanbul"), info.timezone.as_deref());
+		assert_eq!(Some("RowBinaryWithNamesAndTypes"), info.format.as_deref());
+		assert_eq!(vec![(1, 0, 3), (3, 24, 0)], {
+			info.progress.iter().map(|p| (p.read_rows, p.read_bytes, p.total_rows_to_read)).collect::<Vec<_>>()
+		});
+		let summary = info.summary.unwrap();
+		assert_eq!((3, 72, 1500), (summary.result_rows, summary.result_bytes, summary.elapsed_ns));
+	}
+}
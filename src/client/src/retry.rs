@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+use crate::error::Error;
+
+/// Retry policy with exponential backoff.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+	/// max retries after the first attempt.
+	pub max_retries: u32,
+	pub initial_backoff: Duration,
+	pub max_backoff: Duration,
+	pub multiplier: f64,
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		RetryPolicy {
+			max_retries: 3,
+			initial_backoff: Duration::from_millis(100),
+			max_backoff: Duration::from_secs(10),
+			multiplier: 2.0,
+		}
+	}
+}
+
+impl RetryPolicy {
+	/// policy without retries.
+	pub fn none() -> Self {
+		RetryPolicy { max_retries: 0, ..Default::default() }
+	}
+
+	pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+		self.max_retries = max_retries;
+		self
+	}
+
+	pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+		self.initial_backoff = initial;
+		self.max_backoff = max;
+		self
+	}
+
+	pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+		self.multiplier = multiplier;
+		self
+	}
+
+	/// backoff before the `retry`th retry, starts from 1.
+	pub fn backoff(&self, retry: u32) -> Duration {
+		let factor = self.multiplier.max(1.0).powi(retry.saturating_sub(1) as i32);
+		self.initial_backoff.mul_f64(factor.min(u32::MAX as f64)).min(self.max_backoff)
+	}
+
+	/// whether `error` of the `retry`th retry should be retried.
+	pub fn should_retry(&self, error: &Error, retry: u32) -> bool {
+		retry <= self.max_retries && error.is_retryable()
+	}
+}
+
+/// server exception codes which are transient, the query may succeed if retried.
+const RETRYABLE_CODES: [i32; 17] = [
+	3,    // UNEXPECTED_END_OF_FILE
+	159,  // TIMEOUT_EXCEEDED
+	202,  // TOO_MANY_SIMULTANEOUS_QUERIES
+	203,  // NO_FREE_CONNECTION
+	209,  // SOCKET_TIMEOUT
+	210,  // NETWORK_ERROR
+	236,  // ABORTED
+	241,  // MEMORY_LIMIT_EXCEEDED
+	242,  // TABLE_IS_READ_ONLY
+	252,  // TOO_MANY_PARTS
+	285,  // TOO_FEW_LIVE_REPLICAS
+	319,  // UNKNOWN_STATUS_OF_INSERT
+	373,  // SESSION_IS_LOCKED
+	425,  // SYSTEM_ERROR
+	439,  // CANNOT_SCHEDULE_TASK
+	999,  // KEEPER_EXCEPTION
+	1000, // POCO_EXCEPTION
+];
+
+/// whether server exception code is transient.
+pub fn is_retryable_code(code: i32) -> bool {
+	RETRYABLE_CODES.contains(&code)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::*;
+
+	#[test]
+	fn test_retry_policy() {
+		let policy = RetryPolicy::default().with_backoff(Duration::from_millis(100), Duration::from_millis(300));
+		assert_eq!(Duration::from_millis(100), policy.backoff(1));
+		assert_eq!(Duration::from_millis(200), policy.backoff(2));
+		assert_eq!(Duration::from_millis(300), policy.backoff(3));
+
+		let network = Error::IoError(std::io::ErrorKind::ConnectionReset.into());
+		assert!(policy.should_retry(&network, 3));
+		assert!(!policy.should_retry(&network, 4));
+		assert!(!RetryPolicy::none().should_retry(&network, 1));
+
+		let server = |code| Error::Server { code, name: String::new(), message: String::new(), stack_trace: None };
+		assert!(policy.should_retry(&server(252), 1));
+		assert!(!policy.should_retry(&server(62), 1));
+	}
+}
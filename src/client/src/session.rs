@@ -0,0 +1,220 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use rickhouse_common::metadata::RowSchema;
+use rickhouse_common::sql::Sql;
+use serde::Serialize;
+use url::Url;
+
+use crate::client::Client;
+use crate::error::Result;
+use crate::inserter::Inserter;
+use crate::query::Query;
+
+/// Session of HTTP interface, see [`Client::session`].
+///
+/// Queries of the session are sent to the same host with `session_id`, so temporary tables and
+/// settings changed by `SET` are kept between them. Queries of a session must not run
+/// concurrently, server rejects them with `SESSION_IS_LOCKED`. Temporary tables are dropped when
+/// the session is closed or dropped, server drops them anyway after `session_timeout` of idleness.
+#[derive(Debug)]
+pub struct Session {
+	client: Client,
+	closed: bool,
+}
+
+/// State of session shared by clones of its client.
+#[derive(Debug)]
+pub(crate) struct SessionState {
+	id: String,
+	/// `session_timeout` in seconds, 60 seconds of server by default.
+	timeout: Option<u64>,
+	/// a query is done, later queries check that the session still exists.
+	started: AtomicBool,
+}
+
+impl SessionState {
+	pub(crate) fn new(id: String, timeout: Option<Duration>) -> SessionState {
+		let timeout = timeout.map(|timeout| timeout.as_secs().max(1));
+		SessionState { id, timeout, started: AtomicBool::new(false) }
+	}
+
+	/// append `session_id`, `session_timeout` and `session_check` to url of request.
+	pub(crate) fn append_to(&self, url: &mut Url) {
+		let mut pairs = url.query_pairs_mut();
+		pairs.append_pair("session_id", &self.id);
+		if let Some(timeout) = self.timeout {
+			pairs.append_pair("session_timeout", &timeout.to_string());
+		}
+		// the session expired is an error rather than a new session without temporary tables.
+		if self.is_started() {
+			pairs.append_pair("session_check", "1");
+		}
+	}
+
+	pub(crate) fn mark_started(&self) {
+		self.started.store(true, Ordering::Release);
+	}
+
+	fn is_started(&self) -> bool {
+		self.started.load(Ordering::Acquire)
+	}
+}
+
+impl Session {
+	pub(crate) fn new(client: Client) -> Session {
+		Session { client, closed: false }
+	}
+
+	/// `session_timeout` of server, the session expires if it's idle longer. It's rounded to
+	/// seconds and must be set before the first query.
+	pub fn with_timeout(mut self, timeout: Duration) -> Self {
+		let state = SessionState::new(self.id().to_owned(), Some(timeout));
+		self.client = self.client.in_session(state);
+		self
+	}
+
+	pub fn id(&self) -> &str {
+		&self.state().id
+	}
+
+	/// client of the session, e.g. to create cursors or inserters outliving borrows of session.
+	pub fn client(&self) -> &Client {
+		&self.client
+	}
+
+	/// create query of `sql` in the session.
+	pub fn query(&self, sql: &str) -> Query {
+		self.client.query(sql)
+	}
+
+	/// create inserter of `table` in the session, e.g. to fill a temporary table.
+	pub fn inserter<T: Serialize + RowSchema>(&self, table: &str) -> Inserter<T> {
+		self.client.inserter(table)
+	}
+
+	/// drop temporary tables of the session.
+	pub async fn close(mut self) -> Result<()> {
+		self.closed = true;
+		drop_temporary_tables(&self.client).await
+	}
+
+	fn state(&self) -> &SessionState {
+		self.client.session_state().expect("client of session has session state")
+	}
+}
+
+impl Drop for Session {
+	fn drop(&mut self) {
+		if self.closed || !self.state().is_started() {
+			return;
+		}
+		let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+			return;
+		};
+		let client = self.client.clone();
+		runtime.spawn(async move {
+			let _ = drop_temporary_tables(&client).await;
+		});
+	}
+}
+
+async fn drop_temporary_tables(client: &Client) -> Result<()> {
+	if !client.session_state().is_some_and(SessionState::is_started) {
+		return Ok(());
+	}
+	let tables: Vec<(String,)> =
+		client.query("SELECT name FROM system.tables WHERE is_temporary ORDER BY name").fetch_all().await?;
+	for (table,) in tables {
+		client.query(Sql::new("DROP TEMPORARY TABLE IF EXISTS ").identifier(&table).as_str()).execute().await?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use bytes::Bytes;
+	use rickhouse_common::metadata::RowSchema;
+	use rickhouse_common::rowbinary;
+	use rickhouse_derive::Row;
+	use serde::Serialize;
+
+	use crate::client::tests::MockServer;
+	use crate::client::tests::Reply;
+	use crate::Client;
+
+	const TEMPORARY_TABLES: &str = "SELECT name FROM system.tables WHERE is_temporary ORDER BY name";
+
+	#[derive(Serialize, Row)]
+	struct Table {
+		name: String,
+	}
+
+	async fn server() -> MockServer {
+		MockServer::start_with(|request| match request.query.as_str() {
+			TEMPORARY_TABLES => {
+				let columns = Table::columns();
+				let mut body = Vec::new();
+				rowbinary::write_header(&mut body, &columns);
+				for name in ["ids", "tmp 2"] {
+					rowbinary::write_row(&mut body, &Table { name: name.to_owned() }, &columns).unwrap();
+				}
+				Reply::ok(body)
+			}
+			_ => Reply::ok(Bytes::new()),
+		})
+		.await
+	}
+
+	#[tokio::test]
+	async fn test_session() {
+		let (server, other) = (server().await, server().await);
+		// hosts are chosen in turn, so queries of the session would alternate without pinning.
+		let client = Client::from_hosts([format!("http://{}", server.addr), format!("http://{}", other.addr)]).unwrap();
+		let session = client.session().with_timeout(Duration::from_millis(90_500));
+		session.query("CREATE TEMPORARY TABLE ids (id UInt64)").execute().await.unwrap();
+		session.query("SELECT count() FROM ids").execute().await.unwrap();
+		let id = session.id().to_owned();
+		session.close().await.unwrap();
+		assert!(other.requests().is_empty());
+
+		let requests = server.requests();
+		let params = |idx: usize| {
+			["session_id", "session_timeout", "session_check"].map(|name| requests[idx].param(name).map(str::to_owned))
+		};
+		assert_eq!([Some(id.clone()), Some("90".to_owned()), None], params(0));
+		assert_eq!([Some(id.clone()), Some("90".to_owned()), Some("1".to_owned())], params(1));
+		let queries = requests[2..].iter().map(|r| r.query.as_str()).collect::<Vec<_>>();
+		assert_eq!(
+			vec![TEMPORARY_TABLES, "DROP TEMPORARY TABLE IF EXISTS `ids`", "DROP TEMPORARY TABLE IF EXISTS `tmp 2`"],
+			queries
+		);
+		assert!(requests.iter().all(|r| r.param("session_id") == Some(id.as_str())));
+
+		// queries outside of the session and `KILL QUERY` have no session.
+		client.query("SELECT 1").execute().await.unwrap();
+		let session = client.session();
+		assert_ne!(id, session.id());
+		session.client().kill_query("q").await.unwrap();
+		let requests = [&server.requests()[5..], &other.requests()].concat();
+		assert_eq!(2, requests.len());
+		assert!(requests.iter().all(|r| r.param("session_id").is_none()));
+	}
+
+	#[tokio::test]
+	async fn test_session_drop() {
+		let server = server().await;
+		// nothing to drop before the first query.
+		drop(server.client().session());
+		let session = server.client().session();
+		session.query("CREATE TEMPORARY TABLE ids (id UInt64)").execute().await.unwrap();
+		drop(session);
+		let requests = server.wait_requests(4).await;
+		assert_eq!(TEMPORARY_TABLES, requests[1].query);
+		assert_eq!("DROP TEMPORARY TABLE IF EXISTS `tmp 2`", requests[3].query);
+		assert_eq!(Some("1"), requests[3].param("session_check"));
+	}
+}
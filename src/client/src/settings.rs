@@ -0,0 +1,225 @@
+use std::fmt::Display;
+use std::time::Duration;
+
+use rickhouse_common::rowbinary::FormatOptions;
+
+use crate::error::Error;
+use crate::error::Result;
+
+/// names set by the client itself, they can't be used as settings.
+const RESERVED_NAMES: [&str; 9] = [
+	"query",
+	"database",
+	"default_format",
+	"query_id",
+	"user",
+	"password",
+	"session_id",
+	"session_timeout",
+	"session_check",
+];
+
+/// ClickHouse settings sent with queries, later settings override earlier ones of the same name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Settings {
+	settings: Vec<(String, String)>,
+}
+
+/// Value of `readonly` setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Readonly {
+	/// all queries are allowed.
+	Off,
+	/// only read queries are allowed, settings can't be changed.
+	On,
+	/// only read queries are allowed, settings can be changed.
+	ChangeSettings,
+}
+
+impl Settings {
+	pub fn new() -> Settings {
+		Settings::default()
+	}
+
+	/// set arbitrary setting, name and value are validated, e.g. value of `readonly` must be 0, 1
+	/// or 2.
+	pub fn set(self, name: &str, value: impl Display) -> Result<Self> {
+		let value = value.to_string();
+		validate(name, &value)?;
+		Ok(self.set_unchecked(name, value))
+	}
+
+	/// cancel query if it runs longer than `time`.
+	pub fn max_execution_time(self, time: Duration) -> Self {
+		let secs = match time.subsec_nanos() {
+			0 => time.as_secs().to_string(),
+			_ => time.as_secs_f64().to_string(),
+		};
+		self.set_unchecked("max_execution_time", secs)
+	}
+
+	/// max memory usage of the query on a single server in bytes, 0 is unlimited.
+	pub fn max_memory_usage(self, bytes: u64) -> Self {
+		self.set_unchecked("max_memory_usage", bytes.to_string())
+	}
+
+	/// max rows of the result, 0 is unlimited.
+	pub fn max_result_rows(self, rows: u64) -> Self {
+		self.set_unchecked("max_result_rows", rows.to_string())
+	}
+
+	pub fn readonly(self, readonly: Readonly) -> Self {
+		let value = match readonly {
+			Readonly::Off => "0",
+			Readonly::On => "1",
+			Readonly::ChangeSettings => "2",
+		};
+		self.set_unchecked("readonly", value.to_owned())
+	}
+
+	/// fill non-matched rows of JOIN by NULL instead of default values.
+	pub fn join_use_nulls(self, enabled: bool) -> Self {
+		self.set_unchecked("join_use_nulls", bool_value(enabled))
+	}
+
+	/// set `output_format_<name>`, e.g. `output_format("json_quote_64bit_integers", 0)`.
+	pub fn output_format(self, name: &str, value: impl Display) -> Result<Self> {
+		self.set(&format!("output_format_{}", name), value)
+	}
+
+	/// encode types of `RowBinaryWithNamesAndTypes` header in binary instead of type names.
+	pub fn output_format_binary_encode_types_in_binary_format(self, enabled: bool) -> Self {
+		self.set_unchecked("output_format_binary_encode_types_in_binary_format", bool_value(enabled))
+	}
+
+	/// send progress of query in `X-ClickHouse-Progress` headers.
+	pub fn send_progress_in_http_headers(self, enabled: bool) -> Self {
+		self.set_unchecked("send_progress_in_http_headers", bool_value(enabled))
+	}
+
+	pub fn get(&self, name: &str) -> Option<&str> {
+		self.settings.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+		self.settings.iter().map(|(name, value)| (name.as_str(), value.as_str()))
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.settings.is_empty()
+	}
+
+	/// merge `other` into settings, settings of `other` win.
+	pub fn merge(mut self, other: &Settings) -> Self {
+		for (name, value) in &other.settings {
+			self = self.set_unchecked(name, value.clone());
+		}
+		self
+	}
+
+	/// options of RowBinary formats affected by the settings.
+	pub(crate) fn format_options(&self) -> FormatOptions {
+		let enabled = |name| self.get(name).is_some_and(|value| parse_bool(value) == Some(true));
+		FormatOptions { binary_types: enabled("output_format_binary_encode_types_in_binary_format") }
+	}
+
+	fn set_unchecked(mut self, name: &str, value: String) -> Self {
+		match self.settings.iter_mut().find(|(n, _)| n == name) {
+			Some((_, v)) => *v = value,
+			None => self.settings.push((name.to_owned(), value)),
+		}
+		self
+	}
+}
+
+fn bool_value(enabled: bool) -> String {
+	if enabled { "1" } else { "0" }.to_owned()
+}
+
+pub(crate) fn parse_bool(value: &str) -> Option<bool> {
+	match value.to_ascii_lowercase().as_str() {
+		"1" | "true" => Some(true),
+		"0" | "false" => Some(false),
+		_ => None,
+	}
+}
+
+/// validate name and value of setting, values of well known settings are checked by their types.
+fn validate(name: &str, value: &str) -> Result<()> {
+	let invalid = |reason: &str| Err(Error::InvalidSetting(format!("{}={}: {}", name, value, reason)));
+	if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+		return invalid("invalid name");
+	}
+	if RESERVED_NAMES.contains(&name) {
+		return invalid("name is reserved by client");
+	}
+	if name.starts_with("param_") {
+		return invalid("use `Query::param` for query parameters");
+	}
+
+	let valid = match name {
+		"readonly" => matches!(value, "0" | "1" | "2"),
+		"max_memory_usage" | "max_result_rows" | "max_threads" | "max_block_size" => value.parse::<u64>().is_ok(),
+		"max_execution_time" => value.parse::<f64>().is_ok_and(|secs| secs.is_finite() && secs >= 0.0),
+		"join_use_nulls" | "send_progress_in_http_headers" | "async_insert" | "wait_for_async_insert" => {
+			parse_bool(value).is_some()
+		}
+		name if name.starts_with("output_format_binary_") => parse_bool(value).is_some(),
+		_ => true,
+	};
+	match valid {
+		true => Ok(()),
+		false => invalid("invalid value"),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::*;
+
+	#[test]
+	fn test_settings() {
+		let settings = Settings::new()
+			.max_execution_time(Duration::from_millis(1500))
+			.max_memory_usage(1 << 30)
+			.max_result_rows(10)
+			.readonly(Readonly::ChangeSettings)
+			.join_use_nulls(true)
+			.output_format("json_quote_64bit_integers", 0)
+			.unwrap()
+			.set("max_result_rows", 20)
+			.unwrap();
+		assert_eq!(
+			vec![
+				("max_execution_time", "1.5"),
+				("max_memory_usage", "1073741824"),
+				("max_result_rows", "20"),
+				("readonly", "2"),
+				("join_use_nulls", "1"),
+				("output_format_json_quote_64bit_integers", "0"),
+			],
+			settings.iter().collect::<Vec<_>>()
+		);
+		assert!(!settings.format_options().binary_types);
+
+		let settings = Settings::new().output_format("binary_encode_types_in_binary_format", "true").unwrap();
+		assert!(settings.format_options().binary_types);
+		let settings = settings.merge(&Settings::new().output_format_binary_encode_types_in_binary_format(false));
+		assert!(!settings.format_options().binary_types);
+
+		for (name, value) in [
+			("readonly", "3"),
+			("max_memory_usage", "-1"),
+			("max_execution_time", "inf"),
+			("join_use_nulls", "yes"),
+			("output_format_binary_encode_types_in_binary_format", "2"),
+			("max threads", "1"),
+			("query", "SELECT 1"),
+			("param_id", "1"),
+		] {
+			assert!(matches!(Settings::new().set(name, value), Err(Error::InvalidSetting(_))), "{}", name);
+		}
+	}
+}
@@ -0,0 +1,329 @@
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use rickhouse_common::hash::HashFunction;
+use rickhouse_common::metadata::Metadata;
+use rickhouse_common::metadata::RowSchema;
+use rickhouse_common::metadata::SchemaColumn;
+use rickhouse_common::rowbinary;
+use rickhouse_common::values::value::Value;
+use serde::Serialize;
+
+use crate::client::Client;
+use crate::error::Error;
+use crate::error::Result;
+use crate::inserter::BatchStats;
+use crate::inserter::InsertStats;
+use crate::inserter::Inserter;
+
+/// Shard of a cluster, like `<shard>` of `remote_servers` in server config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shard {
+	/// rows are distributed to shards in proportion to their weights.
+	pub weight: u32,
+	/// urls of replicas, inserts fail over between them.
+	pub replicas: Vec<String>,
+}
+
+impl Shard {
+	pub fn new<I: IntoIterator<Item = S>, S: Into<String>>(replicas: I) -> Shard {
+		Shard { weight: 1, replicas: replicas.into_iter().map(Into::into).collect() }
+	}
+
+	pub fn with_weight(mut self, weight: u32) -> Self {
+		self.weight = weight;
+		self
+	}
+}
+
+/// Sharding key of [`ShardedInserter`], the same expressions as the sharding key of a Distributed
+/// table, e.g. `cityHash64(user_id, name)`, `rand()` or `user_id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShardingKey {
+	/// integer column, signed values are cast to unsigned of the same width.
+	Column(String),
+	/// hash function of columns.
+	Hash(HashFunction, Vec<String>),
+	/// `rand()`.
+	Rand,
+}
+
+impl FromStr for ShardingKey {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self> {
+		let invalid = || Error::InvalidSharding(format!("unsupported sharding key {}", s));
+		let Some((name, args)) = s.trim().strip_suffix(')').and_then(|s| s.split_once('(')) else {
+			return Ok(ShardingKey::Column(unquote(s).ok_or_else(invalid)?));
+		};
+		let args = args.split(',').filter(|arg| !arg.trim().is_empty()).map(unquote).collect::<Option<Vec<_>>>();
+		match (name.trim(), args.ok_or_else(invalid)?) {
+			("rand", args) if args.is_empty() => Ok(ShardingKey::Rand),
+			(name, args) => Ok(ShardingKey::Hash(HashFunction::from_name(name).ok_or_else(invalid)?, args)),
+		}
+	}
+}
+
+impl Display for ShardingKey {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ShardingKey::Column(column) => write!(f, "{}", column),
+			ShardingKey::Hash(function, columns) => write!(f, "{}({})", function.name(), columns.join(", ")),
+			ShardingKey::Rand => write!(f, "rand()"),
+		}
+	}
+}
+
+/// column name optionally quoted by backticks or double quotes.
+fn unquote(s: &str) -> Option<String> {
+	let s = s.trim();
+	let name = ['`', '"'].iter().find_map(|&q| s.strip_prefix(q)?.strip_suffix(q)).unwrap_or(s);
+	let valid = !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.');
+	valid.then(|| name.to_owned())
+}
+
+/// Inserter writing rows directly into local tables of shards instead of a Distributed table.
+///
+/// Shard of a row is chosen like a Distributed table does: the sharding key modulo the total weight
+/// of shards picks a slot, and each shard owns as many consecutive slots as its weight. Hashes are
+/// bit-for-bit the same as the functions of server, so rows land on the same shards as inserts
+/// through the Distributed table.
+pub struct ShardedInserter<T> {
+	inserters: Vec<Inserter<T>>,
+	/// shard index of each slot.
+	slots: Vec<usize>,
+	key: ShardingKey,
+	/// indexes of key columns in `metadata`.
+	key_columns: Vec<usize>,
+	columns: Vec<SchemaColumn>,
+	metadata: Arc<Metadata>,
+	buffer: Vec<u8>,
+}
+
+impl<T: Serialize + RowSchema> ShardedInserter<T> {
+	pub(crate) fn new(client: &Client, shards: &[Shard], table: &str, key: ShardingKey) -> Result<Self> {
+		let slots =
+			shards.iter().enumerate().flat_map(|(idx, shard)| (0..shard.weight).map(move |_| idx)).collect::<Vec<_>>();
+		if slots.is_empty() {
+			return Err(Error::InvalidSharding("total weight of shards is 0".to_owned()));
+		}
+		let inserters = shards
+			.iter()
+			.map(|shard| Ok(Inserter::new(client.for_hosts(&shard.replicas)?, table)))
+			.collect::<Result<Vec<_>>>()?;
+
+		let columns = T::columns();
+		let metadata = columns.iter().map(|c| (c.name.clone(), c.data_type.clone())).collect::<Metadata>();
+		let names = match &key {
+			ShardingKey::Column(column) => std::slice::from_ref(column),
+			ShardingKey::Hash(_, columns) => columns.as_slice(),
+			ShardingKey::Rand => &[],
+		};
+		let key_columns = names
+			.iter()
+			.map(|name| {
+				let idx = metadata.iter().position(|(column, _)| column == name);
+				idx.ok_or_else(|| Error::InvalidSharding(format!("no column {} of sharding key {}", name, key)))
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(ShardedInserter {
+			inserters,
+			slots,
+			key,
+			key_columns,
+			columns,
+			metadata: Arc::new(metadata),
+			buffer: Vec::new(),
+		})
+	}
+
+	/// configure the inserter of every shard, e.g. `|inserter| inserter.with_max_rows(1000)`.
+	pub fn with_inserters<F: FnMut(Inserter<T>) -> Inserter<T>>(mut self, f: F) -> Self {
+		self.inserters = self.inserters.into_iter().map(f).collect();
+		self
+	}
+
+	pub fn shards(&self) -> usize {
+		self.inserters.len()
+	}
+
+	/// index of the shard of the row.
+	pub fn shard_of(&mut self, row: &T) -> Result<usize> {
+		let key = match self.key {
+			ShardingKey::Rand => rand::random::<u32>() as u64,
+			ShardingKey::Column(_) => unsigned_key(&self.key_values(row)?[0])?,
+			ShardingKey::Hash(function, _) => {
+				let values = self.key_values(row)?;
+				let args = values
+					.iter()
+					.zip(&self.key_columns)
+					.map(|(value, &idx)| (value, &self.metadata[idx].1))
+					.collect::<Vec<_>>();
+				function.hash_values(&args)?
+			}
+		};
+		Ok(self.slots[(key % self.slots.len() as u64) as usize])
+	}
+
+	pub async fn write(&mut self, row: &T) -> Result<()> {
+		let shard = self.shard_of(row)?;
+		self.inserters[shard].write(row).await
+	}
+
+	/// send batches of due inserters, see [`Inserter::tick`].
+	pub async fn tick(&mut self) -> Result<()> {
+		for inserter in &mut self.inserters {
+			inserter.tick().await?;
+		}
+		Ok(())
+	}
+
+	/// send buffered rows of all shards, the first error is returned after all shards are tried.
+	pub async fn commit(&mut self) -> Result<Vec<Option<BatchStats>>> {
+		let mut result = Ok(Vec::with_capacity(self.inserters.len()));
+		for inserter in &mut self.inserters {
+			match (inserter.commit().await, &mut result) {
+				(Ok(stats), Ok(batches)) => batches.push(stats),
+				(Err(e), Ok(_)) => result = Err(e),
+				(_, Err(_)) => (),
+			}
+		}
+		result
+	}
+
+	/// send remaining rows of all shards and return stats of each shard.
+	pub async fn end(self) -> Result<Vec<InsertStats>> {
+		let mut result = Ok(Vec::with_capacity(self.inserters.len()));
+		for inserter in self.inserters {
+			match (inserter.end().await, &mut result) {
+				(Ok(stats), Ok(shards)) => shards.push(stats),
+				(Err(e), Ok(_)) => result = Err(e),
+				(_, Err(_)) => (),
+			}
+		}
+		result
+	}
+
+	/// stats of each shard.
+	pub fn stats(&self) -> Vec<InsertStats> {
+		self.inserters.iter().map(Inserter::stats).collect()
+	}
+
+	/// values of key columns, the row is encoded to RowBinary and decoded by its columns.
+	fn key_values(&mut self, row: &T) -> Result<Vec<Value>> {
+		self.buffer.clear();
+		rowbinary::write_row(&mut self.buffer, row, &self.columns)?;
+		let mut values = rowbinary::read_row(&mut self.buffer.as_slice(), &self.metadata)?.into_values();
+		Ok(self.key_columns.iter().map(|&idx| std::mem::replace(&mut values[idx], Value::Null)).collect())
+	}
+}
+
+fn unsigned_key(value: &Value) -> Result<u64> {
+	Ok(match *value {
+		Value::UInt8(v) => v as u64,
+		Value::UInt16(v) => v as u64,
+		Value::UInt32(v) => v as u64,
+		Value::UInt64(v) => v,
+		Value::Int8(v) => v as u8 as u64,
+		Value::Int16(v) => v as u16 as u64,
+		Value::Int32(v) => v as u32 as u64,
+		Value::Int64(v) => v as u64,
+		_ => return Err(Error::InvalidSharding(format!("sharding key must be an integer, got {:?}", value))),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use hyper::StatusCode;
+	use rickhouse_common::hash::HashFunction;
+	use rickhouse_derive::Row;
+	use serde::Serialize;
+
+	use super::Shard;
+	use super::ShardingKey;
+	use crate::client::tests::MockServer;
+
+	#[derive(Serialize, Row)]
+	struct Event {
+		id: i32,
+		name: String,
+	}
+
+	#[test]
+	fn test_sharding_key() {
+		let key = "cityHash64(id, `name`)".parse::<ShardingKey>().unwrap();
+		let columns = vec!["id".to_owned(), "name".to_owned()];
+		assert_eq!(ShardingKey::Hash(HashFunction::CityHash64, columns), key);
+		assert_eq!("cityHash64(id, name)", key.to_string());
+		assert_eq!(ShardingKey::Rand, " rand() ".parse().unwrap());
+		assert_eq!(ShardingKey::Column("user_id".to_owned()), "user_id".parse().unwrap());
+		let key = "murmurHash3_64(id)".parse::<ShardingKey>().unwrap();
+		assert_eq!(ShardingKey::Hash(HashFunction::MurmurHash3_64, vec!["id".to_owned()]), key);
+		assert!("sipHash64(id)".parse::<ShardingKey>().is_err());
+		assert!("id + 1".parse::<ShardingKey>().is_err());
+	}
+
+	/// ids of `Event` rows in RowBinary bodies of inserts, names are one byte long.
+	fn inserted_ids(server: &MockServer) -> Vec<i32> {
+		let bodies = server.requests().into_iter().map(|r| r.body).collect::<Vec<_>>();
+		bodies
+			.iter()
+			.flat_map(|body| body.chunks(6).map(|row| i32::from_le_bytes(row[..4].try_into().unwrap())))
+			.collect()
+	}
+
+	#[tokio::test]
+	async fn test_sharded_inserter() {
+		let (a, b) = (
+			MockServer::start(|_| (StatusCode::OK, String::new())).await,
+			MockServer::start(|_| (StatusCode::OK, String::new())).await,
+		);
+		let shards =
+			[Shard::new([format!("http://{}", a.addr)]), Shard::new([format!("http://{}", b.addr)]).with_weight(2)];
+		let client = a.client().with_user("default");
+
+		let key = ShardingKey::Hash(HashFunction::XxHash64, vec!["name".to_owned()]);
+		let mut inserter = client.sharded_inserter::<Event>(&shards, "events_local", key).unwrap();
+		for id in -10..10 {
+			inserter.write(&Event { id, name: "a".to_owned() }).await.unwrap();
+		}
+		let stats = inserter.end().await.unwrap();
+		let shard = [0, 1, 1][(HashFunction::XxHash64.hash_bytes(b"a") % 3) as usize];
+		assert_eq!((20, 0), (stats[shard].rows, stats[1 - shard].rows));
+		let (inserted, _) = if shard == 0 { (&a, &b) } else { (&b, &a) };
+		let request = inserted.requests().remove(0);
+		assert_eq!("INSERT INTO `events_local` (`id`,`name`) FORMAT RowBinary", request.query);
+		assert!(request.headers.iter().any(|(k, v)| k == "x-clickhouse-user" && v == "default"));
+		let before = (inserted_ids(&a).len(), inserted_ids(&b).len());
+
+		let key = "cityHash64(id)".parse().unwrap();
+		let mut inserter = client.sharded_inserter::<Event>(&shards, "events_local", key).unwrap();
+		let mut expected = (Vec::new(), Vec::new());
+		for id in -10..10 {
+			inserter.write(&Event { id, name: "a".to_owned() }).await.unwrap();
+			// integers are hashed by intHash64 of their value zero-extended to 64 bits.
+			let hash = HashFunction::CityHash64.hash_values(&[(&id.into(), &"Int32".parse().unwrap())]).unwrap();
+			match hash % 3 {
+				0 => expected.0.push(id),
+				_ => expected.1.push(id),
+			}
+		}
+		inserter.commit().await.unwrap();
+		assert!(!expected.0.is_empty() && !expected.1.is_empty());
+		assert_eq!(expected, (inserted_ids(&a)[before.0..].to_vec(), inserted_ids(&b)[before.1..].to_vec()));
+
+		let mut inserter = client.sharded_inserter::<Event>(&shards, "events_local", "id".parse().unwrap()).unwrap();
+		assert_eq!(0, inserter.shard_of(&Event { id: 3, name: String::new() }).unwrap());
+		assert_eq!(1, inserter.shard_of(&Event { id: 5, name: String::new() }).unwrap());
+		// -1 is 0xffffffff, 4294967295 % 3 == 0
+		assert_eq!(0, inserter.shard_of(&Event { id: -1, name: String::new() }).unwrap());
+
+		let empty = [Shard::new(["http://localhost:8123"]).with_weight(0)];
+		assert!(client.sharded_inserter::<Event>(&empty, "events_local", ShardingKey::Rand).is_err());
+		let key = ShardingKey::Column("missing".to_owned());
+		assert!(client.sharded_inserter::<Event>(&shards, "events_local", key).is_err());
+	}
+}
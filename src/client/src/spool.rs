@@ -0,0 +1,413 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use bytes::Buf;
+use bytes::BufMut;
+use bytes::Bytes;
+
+use crate::client::Client;
+use crate::error::Error;
+use crate::error::Result;
+use crate::inserter::insert_batch;
+use crate::inserter::InsertMode;
+use crate::retry::RetryPolicy;
+
+const SEGMENT_EXT: &str = "seg";
+const FAILED_EXT: &str = "failed";
+/// length and crc32 of record.
+const RECORD_HEADER_SIZE: u64 = 8;
+
+/// Batch persisted in spool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpooledBatch {
+	/// INSERT statement of the batch.
+	pub sql: String,
+	pub dedup_token: Option<String>,
+	pub rows: u64,
+	/// RowBinary data.
+	pub data: Bytes,
+}
+
+impl SpooledBatch {
+	fn encode(&self) -> Vec<u8> {
+		let token = self.dedup_token.as_deref();
+		let mut payload = Vec::with_capacity(self.sql.len() + self.data.len() + 32);
+		payload.put_u64_le(self.rows);
+		payload.put_u32_le(self.sql.len() as u32);
+		payload.put_slice(self.sql.as_bytes());
+		payload.put_u8(token.is_some() as u8);
+		payload.put_u32_le(token.map(str::len).unwrap_or_default() as u32);
+		payload.put_slice(token.unwrap_or_default().as_bytes());
+		payload.put_slice(&self.data);
+
+		let mut record = Vec::with_capacity(payload.len() + RECORD_HEADER_SIZE as usize);
+		record.put_u32_le(payload.len() as u32);
+		record.put_u32_le(crc32fast::hash(&payload));
+		record.extend(payload);
+		record
+	}
+
+	fn decode(mut payload: &[u8]) -> Option<SpooledBatch> {
+		let read_str = |buf: &mut &[u8]| -> Option<String> {
+			let len = (buf.remaining() >= 4).then(|| buf.get_u32_le() as usize)?;
+			let s = String::from_utf8(buf.get(..len)?.to_vec()).ok()?;
+			buf.advance(len);
+			Some(s)
+		};
+
+		let rows = (payload.remaining() >= 8).then(|| payload.get_u64_le())?;
+		let sql = read_str(&mut payload)?;
+		let has_token = (payload.remaining() >= 1).then(|| payload.get_u8() != 0)?;
+		let token = read_str(&mut payload)?;
+		Some(SpooledBatch { sql, dedup_token: has_token.then_some(token), rows, data: Bytes::copy_from_slice(payload) })
+	}
+}
+
+/// Position of a record in segment files.
+#[derive(Debug, Clone, Copy)]
+struct RecordPos {
+	segment: u64,
+	offset: u64,
+	size: u64,
+}
+
+/// Write-ahead spool of batches which can't be inserted, batches are appended to segment files and
+/// replayed in order.
+///
+/// Each record is prefixed by its length and crc32. On open, existing segments are scanned and a
+/// torn or corrupted tail left by a crash is truncated. A segment is deleted once all its batches
+/// are replayed. A crash during replay may send a batch again, its deduplication token makes the
+/// retry safe. A batch server always rejects can be moved out of the way by [`Spool::quarantine`].
+#[derive(Debug)]
+pub struct Spool {
+	dir: PathBuf,
+	max_bytes: u64,
+	segment_size: u64,
+	records: VecDeque<RecordPos>,
+	/// size of each segment file in order.
+	segments: VecDeque<(u64, u64)>,
+	writer: Option<(u64, File)>,
+	next_segment: u64,
+}
+
+impl Spool {
+	/// open spool in `dir`, total size of segment files is limited to `max_bytes`.
+	pub fn open(dir: impl AsRef<Path>, max_bytes: u64) -> Result<Spool> {
+		let dir = dir.as_ref().to_path_buf();
+		if !dir.is_dir() {
+			fs::create_dir_all(&dir)?;
+			if let Some(parent) = dir.parent() {
+				sync_dir(parent)?;
+			}
+		}
+
+		let mut segment_ids = Vec::new();
+		for entry in fs::read_dir(&dir)? {
+			let path = entry?.path();
+			if path.extension().and_then(|ext| ext.to_str()) == Some(SEGMENT_EXT) {
+				if let Some(id) = path.file_stem().and_then(|stem| stem.to_str()).and_then(|s| s.parse().ok()) {
+					segment_ids.push(id);
+				}
+			}
+		}
+		segment_ids.sort_unstable();
+
+		let mut spool = Spool {
+			dir,
+			max_bytes,
+			segment_size: 64 * 1024 * 1024,
+			records: VecDeque::new(),
+			segments: VecDeque::new(),
+			writer: None,
+			next_segment: segment_ids.last().map(|id| id + 1).unwrap_or_default(),
+		};
+		for id in segment_ids {
+			spool.recover_segment(id)?;
+		}
+		Ok(spool)
+	}
+
+	/// max size of a segment file, 64MiB by default.
+	pub fn with_segment_size(mut self, segment_size: u64) -> Self {
+		self.segment_size = segment_size;
+		self
+	}
+
+	/// number of batches not replayed.
+	pub fn len(&self) -> usize {
+		self.records.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.records.is_empty()
+	}
+
+	/// total size of segment files.
+	pub fn size(&self) -> u64 {
+		self.segments.iter().map(|(_, size)| size).sum()
+	}
+
+	/// append a batch, fails if disk quota is exceeded.
+	pub fn append(&mut self, batch: &SpooledBatch) -> Result<()> {
+		let record = batch.encode();
+		let size = record.len() as u64;
+		if self.size() + size > self.max_bytes {
+			return Err(Error::SpoolError(format!(
+				"quota of {} bytes exceeded, {} bytes used, batch is {} bytes",
+				self.max_bytes,
+				self.size(),
+				size
+			)));
+		}
+
+		let rotate = match (&self.writer, self.segments.back()) {
+			(Some((id, _)), Some((last, last_size))) => id != last || last_size + size > self.segment_size,
+			_ => true,
+		};
+		if rotate {
+			let id = self.next_segment;
+			let file = OpenOptions::new().create_new(true).append(true).open(self.segment_path(id))?;
+			sync_dir(&self.dir)?;
+			self.next_segment += 1;
+			self.writer = Some((id, file));
+			self.segments.push_back((id, 0));
+		}
+
+		let (id, file) = self.writer.as_mut().unwrap();
+		file.write_all(&record)?;
+		file.sync_data()?;
+		let (_, segment_size) = self.segments.back_mut().unwrap();
+		self.records.push_back(RecordPos { segment: *id, offset: *segment_size, size });
+		*segment_size += size;
+		Ok(())
+	}
+
+	/// the oldest batch not replayed.
+	pub fn peek(&self) -> Result<Option<SpooledBatch>> {
+		let Some(pos) = self.records.front() else {
+			return Ok(None);
+		};
+
+		let mut file = File::open(self.segment_path(pos.segment))?;
+		file.seek(SeekFrom::Start(pos.offset + RECORD_HEADER_SIZE))?;
+		let mut payload = vec![0; (pos.size - RECORD_HEADER_SIZE) as usize];
+		file.read_exact(&mut payload)?;
+		SpooledBatch::decode(&payload)
+			.map(Some)
+			.ok_or_else(|| Error::SpoolError(format!("invalid record in segment {}", pos.segment)))
+	}
+
+	/// remove the oldest batch, its segment is deleted if all batches are removed.
+	pub fn pop(&mut self) -> Result<()> {
+		let Some(pos) = self.records.pop_front() else {
+			return Ok(());
+		};
+		if self.records.front().is_some_and(|next| next.segment == pos.segment) {
+			return Ok(());
+		}
+
+		if self.writer.as_ref().is_some_and(|(id, _)| *id == pos.segment) {
+			self.writer = None;
+		}
+		self.segments.retain(|(id, _)| *id != pos.segment);
+		fs::remove_file(self.segment_path(pos.segment))?;
+		sync_dir(&self.dir)
+	}
+
+	/// insert batches in order by `mode`, stop at the first failure and keep the rest, return the
+	/// number of batches replayed.
+	pub async fn replay(&mut self, client: &Client, retry: &RetryPolicy, mode: InsertMode) -> Result<u64> {
+		let mut replayed = 0;
+		while self.replay_next(client, retry, mode).await?.is_some() {
+			replayed += 1;
+		}
+		Ok(replayed)
+	}
+
+	/// insert the oldest batch and remove it, return the number of retries and query id, `None` if
+	/// spool is empty.
+	pub(crate) async fn replay_next(
+		&mut self,
+		client: &Client,
+		retry: &RetryPolicy,
+		mode: InsertMode,
+	) -> Result<Option<(u32, Option<String>)>> {
+		let Some(batch) = self.peek()? else {
+			return Ok(None);
+		};
+		let token = batch.dedup_token.as_deref();
+		let inserted = insert_batch(client, &batch.sql, batch.data, token, mode, retry).await?;
+		self.pop()?;
+		Ok(Some(inserted))
+	}
+
+	/// move the oldest batch, e.g. one rejected by server, to a `.failed` file in the spool
+	/// directory, so later batches can be replayed. The file holds one record in the format of
+	/// segments and isn't counted in the quota. Return the path of the file, `None` if spool is
+	/// empty.
+	pub fn quarantine(&mut self) -> Result<Option<PathBuf>> {
+		let (Some(pos), Some(batch)) = (self.records.front().copied(), self.peek()?) else {
+			return Ok(None);
+		};
+		let path = self.dir.join(format!("{:020}-{:020}.{}", pos.segment, pos.offset, FAILED_EXT));
+		let mut file = File::create(&path)?;
+		file.write_all(&batch.encode())?;
+		file.sync_all()?;
+		sync_dir(&self.dir)?;
+		self.pop()?;
+		Ok(Some(path))
+	}
+
+	fn segment_path(&self, id: u64) -> PathBuf {
+		self.dir.join(format!("{:020}.{}", id, SEGMENT_EXT))
+	}
+
+	/// scan records of segment, the tail after the last valid record is truncated.
+	fn recover_segment(&mut self, id: u64) -> Result<()> {
+		let path = self.segment_path(id);
+		let data = fs::read(&path)?;
+		let mut offset = 0;
+		let mut records = Vec::new();
+		while let Some(header) = data.get(offset..offset + RECORD_HEADER_SIZE as usize) {
+			let mut header = header;
+			let len = header.get_u32_le() as usize;
+			let crc = header.get_u32_le();
+			let start = offset + RECORD_HEADER_SIZE as usize;
+			match data.get(start..start + len) {
+				Some(payload) if crc32fast::hash(payload) == crc => {
+					let size = RECORD_HEADER_SIZE + len as u64;
+					records.push(RecordPos { segment: id, offset: offset as u64, size });
+					offset += size as usize;
+				}
+				_ => break,
+			}
+		}
+
+		if records.is_empty() {
+			fs::remove_file(&path)?;
+			return sync_dir(&self.dir);
+		}
+		if offset < data.len() {
+			let file = OpenOptions::new().write(true).open(&path)?;
+			file.set_len(offset as u64)?;
+			file.sync_all()?;
+		}
+		self.records.extend(records);
+		self.segments.push_back((id, offset as u64));
+		Ok(())
+	}
+}
+
+/// persist entries of directory, e.g. segment files created or deleted. Directories can't be
+/// opened as files on windows, entries are persisted by the file system there.
+fn sync_dir(dir: &Path) -> Result<()> {
+	#[cfg(unix)]
+	File::open(dir)?.sync_all()?;
+	#[cfg(not(unix))]
+	let _ = dir;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+	use std::fs::OpenOptions;
+	use std::io::Write;
+
+	use bytes::Bytes;
+	use hyper::StatusCode;
+
+	use super::Spool;
+	use super::SpooledBatch;
+	use crate::client::tests::MockServer;
+	use crate::inserter::InsertMode;
+	use crate::retry::RetryPolicy;
+
+	fn batch(id: u8) -> SpooledBatch {
+		SpooledBatch {
+			sql: "INSERT INTO t (`id`) FORMAT RowBinary".to_owned(),
+			dedup_token: Some(format!("token-{}", id)),
+			rows: 1,
+			data: Bytes::from(vec![id]),
+		}
+	}
+
+	#[test]
+	fn test_spool() {
+		let dir = tempfile::tempdir().unwrap();
+		let mut spool = Spool::open(dir.path(), 1024).unwrap().with_segment_size(100);
+		for id in 0..3 {
+			spool.append(&batch(id)).unwrap();
+		}
+		assert_eq!(3, spool.len());
+		// each record is 70 bytes, so every record has its own segment.
+		assert_eq!(3, fs::read_dir(dir.path()).unwrap().count());
+		assert!(spool.append(&SpooledBatch { data: Bytes::from(vec![0; 1024]), ..batch(3) }).is_err());
+
+		assert_eq!(Some(batch(0)), spool.peek().unwrap());
+		spool.pop().unwrap();
+		assert_eq!(2, fs::read_dir(dir.path()).unwrap().count());
+		drop(spool);
+
+		// torn write of a crash
+		let last = fs::read_dir(dir.path()).unwrap().map(|e| e.unwrap().path()).max().unwrap();
+		OpenOptions::new().append(true).open(last).unwrap().write_all(&[1, 2, 3]).unwrap();
+		let mut spool = Spool::open(dir.path(), 1024).unwrap();
+		assert_eq!(2, spool.len());
+		assert_eq!(140, spool.size());
+		spool.append(&batch(3)).unwrap();
+		let ids = std::iter::from_fn(|| {
+			let batch = spool.peek().unwrap()?;
+			spool.pop().unwrap();
+			Some(batch.data[0])
+		});
+		assert_eq!(vec![1, 2, 3], ids.collect::<Vec<_>>());
+		assert_eq!(0, fs::read_dir(dir.path()).unwrap().count());
+	}
+
+	#[test]
+	fn test_corrupted_record() {
+		let dir = tempfile::tempdir().unwrap();
+		let mut spool = Spool::open(dir.path(), 1024).unwrap();
+		spool.append(&batch(0)).unwrap();
+		spool.append(&batch(1)).unwrap();
+		drop(spool);
+
+		let path = fs::read_dir(dir.path()).unwrap().next().unwrap().unwrap().path();
+		let mut data = fs::read(&path).unwrap();
+		*data.last_mut().unwrap() ^= 0xff;
+		fs::write(&path, data).unwrap();
+		let spool = Spool::open(dir.path(), 1024).unwrap();
+		assert_eq!(1, spool.len());
+		assert_eq!(70, fs::metadata(&path).unwrap().len());
+	}
+
+	#[tokio::test]
+	async fn test_replay() {
+		let server = MockServer::start(|_| (StatusCode::OK, String::new())).await;
+		let dir = tempfile::tempdir().unwrap();
+		let mut spool = Spool::open(dir.path(), 1024).unwrap();
+		spool.append(&batch(0)).unwrap();
+		spool.append(&batch(1)).unwrap();
+
+		assert_eq!(2, spool.replay(&server.client(), &RetryPolicy::none(), InsertMode::Sync).await.unwrap());
+		assert!(spool.is_empty());
+		let tokens = server.requests().into_iter().map(|r| r.param("insert_deduplication_token").unwrap().to_owned());
+		assert_eq!(vec!["token-0", "token-1"], tokens.collect::<Vec<_>>());
+
+		spool.append(&batch(2)).unwrap();
+		spool.replay(&server.client(), &RetryPolicy::none(), InsertMode::Async { wait: true }).await.unwrap();
+		let request = &server.requests()[2];
+		let settings =
+			["async_insert", "wait_for_async_insert", "async_insert_deduplicate"].map(|name| request.param(name));
+		assert_eq!([Some("1"); 3], settings);
+	}
+}
@@ -0,0 +1,257 @@
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::path::Path;
+use std::sync::Arc;
+
+use rustls::client::danger::HandshakeSignatureValid;
+use rustls::client::danger::ServerCertVerified;
+use rustls::client::danger::ServerCertVerifier;
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::CertificateDer;
+use rustls::pki_types::PrivateKeyDer;
+use rustls::pki_types::ServerName;
+use rustls::pki_types::UnixTime;
+use rustls::ClientConfig;
+use rustls::DigitallySignedStruct;
+use rustls::RootCertStore;
+use rustls::SignatureScheme;
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::TlsConnector;
+
+use crate::error::Error;
+use crate::error::Result;
+
+/// TLS options of `https` hosts, certificates of server are verified by webpki roots by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsOptions {
+	/// PEM of CA certificates trusted instead of webpki roots.
+	ca: Option<Vec<u8>>,
+	/// PEM of certificate chain and private key of client for mutual TLS.
+	client_cert: Option<(Vec<u8>, Vec<u8>)>,
+	server_name: Option<String>,
+	insecure_skip_verify: bool,
+}
+
+impl TlsOptions {
+	pub fn new() -> Self {
+		TlsOptions::default()
+	}
+
+	/// trust CA certificates of the PEM bundle instead of webpki roots.
+	pub fn with_ca_pem(mut self, pem: impl Into<Vec<u8>>) -> Self {
+		self.ca = Some(pem.into());
+		self
+	}
+
+	pub fn with_ca_file(self, path: impl AsRef<Path>) -> Result<Self> {
+		Ok(self.with_ca_pem(read_pem(path.as_ref())?))
+	}
+
+	/// certificate chain and private key in PEM sent to server for mutual TLS.
+	pub fn with_client_cert_pem(mut self, cert: impl Into<Vec<u8>>, key: impl Into<Vec<u8>>) -> Self {
+		self.client_cert = Some((cert.into(), key.into()));
+		self
+	}
+
+	pub fn with_client_cert_file(self, cert: impl AsRef<Path>, key: impl AsRef<Path>) -> Result<Self> {
+		Ok(self.with_client_cert_pem(read_pem(cert.as_ref())?, read_pem(key.as_ref())?))
+	}
+
+	/// name sent as SNI and verified against certificate of server instead of the host of url, e.g.
+	/// when hosts are connected by ip or internal names.
+	pub fn with_server_name(mut self, server_name: impl Into<String>) -> Self {
+		self.server_name = Some(server_name.into());
+		self
+	}
+
+	/// accept any certificate of server, only for development.
+	pub fn with_insecure_skip_verify(mut self, insecure_skip_verify: bool) -> Self {
+		self.insecure_skip_verify = insecure_skip_verify;
+		self
+	}
+
+	pub(crate) fn build(&self) -> Result<TlsConfig> {
+		let provider = Arc::new(rustls::crypto::ring::default_provider());
+		let builder = ClientConfig::builder_with_provider(provider.clone())
+			.with_safe_default_protocol_versions()
+			.map_err(tls_error)?;
+		let builder = match (self.insecure_skip_verify, &self.ca) {
+			(true, _) => builder.dangerous().with_custom_certificate_verifier(Arc::new(SkipVerification(provider))),
+			(false, Some(pem)) => {
+				let mut roots = RootCertStore::empty();
+				for cert in CertificateDer::pem_slice_iter(pem) {
+					roots.add(cert.map_err(tls_error)?).map_err(tls_error)?;
+				}
+				if roots.is_empty() {
+					return Err(Error::TlsError("no certificate in CA bundle".to_owned()));
+				}
+				builder.with_root_certificates(roots)
+			}
+			(false, None) => {
+				builder.with_root_certificates(RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()))
+			}
+		};
+		let config = match &self.client_cert {
+			Some((cert, key)) => {
+				let certs = CertificateDer::pem_slice_iter(cert).collect::<std::result::Result<Vec<_>, _>>();
+				let key = PrivateKeyDer::from_pem_slice(key).map_err(tls_error)?;
+				builder.with_client_auth_cert(certs.map_err(tls_error)?, key).map_err(tls_error)?
+			}
+			None => builder.with_no_client_auth(),
+		};
+		Ok(TlsConfig { connector: TlsConnector::from(Arc::new(config)), server_name: self.server_name.clone() })
+	}
+}
+
+/// TLS config shared by connection pools of `https` hosts.
+pub(crate) struct TlsConfig {
+	connector: TlsConnector,
+	server_name: Option<String>,
+}
+
+impl Debug for TlsConfig {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("TlsConfig").field("server_name", &self.server_name).finish_non_exhaustive()
+	}
+}
+
+impl TlsConfig {
+	/// handshake with server of `host`, failures are io errors so the next host is tried.
+	pub(crate) async fn connect(&self, host: &str, stream: TcpStream) -> Result<TlsStream<TcpStream>> {
+		let name = self.server_name.as_deref().unwrap_or(host);
+		let name = ServerName::try_from(name.to_owned()).map_err(|e| Error::TlsError(format!("{}: {}", name, e)))?;
+		Ok(self.connector.connect(name, stream).await?)
+	}
+}
+
+fn read_pem(path: &Path) -> Result<Vec<u8>> {
+	std::fs::read(path).map_err(|e| Error::TlsError(format!("{}: {}", path.display(), e)))
+}
+
+fn tls_error(e: impl std::fmt::Display) -> Error {
+	Error::TlsError(e.to_string())
+}
+
+/// verifier of `insecure_skip_verify`, signatures of handshake are still checked.
+#[derive(Debug)]
+struct SkipVerification(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for SkipVerification {
+	fn verify_server_cert(
+		&self,
+		_: &CertificateDer<'_>,
+		_: &[CertificateDer<'_>],
+		_: &ServerName<'_>,
+		_: &[u8],
+		_: UnixTime,
+	) -> std::result::Result<ServerCertVerified, rustls::Error> {
+		Ok(ServerCertVerified::assertion())
+	}
+
+	fn verify_tls12_signature(
+		&self,
+		message: &[u8],
+		cert: &CertificateDer<'_>,
+		dss: &DigitallySignedStruct,
+	) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+		rustls::crypto::verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+	}
+
+	fn verify_tls13_signature(
+		&self,
+		message: &[u8],
+		cert: &CertificateDer<'_>,
+		dss: &DigitallySignedStruct,
+	) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+		rustls::crypto::verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+	}
+
+	fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+		self.0.signature_verification_algorithms.supported_schemes()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use rcgen::BasicConstraints;
+	use rcgen::Certificate;
+	use rcgen::CertificateParams;
+	use rcgen::IsCa;
+	use rcgen::KeyPair;
+	use rustls::pki_types::PrivateKeyDer;
+	use rustls::server::WebPkiClientVerifier;
+	use rustls::RootCertStore;
+	use rustls::ServerConfig;
+
+	use super::TlsOptions;
+	use crate::client::tests::MockServer;
+	use crate::client::tests::Reply;
+	use crate::Client;
+	use crate::Error;
+
+	fn issue(name: &str, issuer: Option<(&Certificate, &KeyPair)>) -> (Certificate, KeyPair) {
+		let key = KeyPair::generate().unwrap();
+		let mut params = CertificateParams::new(vec![name.to_owned()]).unwrap();
+		let cert = match issuer {
+			Some((cert, issuer_key)) => params.signed_by(&key, cert, issuer_key).unwrap(),
+			None => {
+				params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+				params.self_signed(&key).unwrap()
+			}
+		};
+		(cert, key)
+	}
+
+	#[tokio::test]
+	async fn test_tls() {
+		let (ca, ca_key) = issue("ca", None);
+		let (server, server_key) = issue("clickhouse.internal", Some((&ca, &ca_key)));
+		let (client_cert, client_key) = issue("client", Some((&ca, &ca_key)));
+
+		let provider = Arc::new(rustls::crypto::ring::default_provider());
+		let mut roots = RootCertStore::empty();
+		roots.add(ca.der().clone()).unwrap();
+		let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone()).build().unwrap();
+		let config = ServerConfig::builder_with_provider(provider)
+			.with_safe_default_protocol_versions()
+			.unwrap()
+			.with_client_cert_verifier(verifier)
+			.with_single_cert(vec![server.der().clone()], PrivateKeyDer::Pkcs8(server_key.serialize_der().into()))
+			.unwrap();
+		let server = MockServer::start_tls(config, |_| Reply::ok("Ok.\n")).await;
+		let client = Client::new(&format!("https://{}", server.addr)).unwrap();
+
+		let dir = tempfile::tempdir().unwrap();
+		std::fs::write(dir.path().join("ca.pem"), ca.pem()).unwrap();
+		let options = TlsOptions::new()
+			.with_ca_file(dir.path().join("ca.pem"))
+			.unwrap()
+			.with_client_cert_pem(client_cert.pem(), client_key.serialize_pem())
+			.with_server_name("clickhouse.internal");
+		client.clone().with_tls(&options).unwrap().query("SELECT 1").execute().await.unwrap();
+		assert_eq!(Some("clickhouse.internal"), server.requests()[0].server_name.as_deref());
+
+		// certificate of server is for clickhouse.internal rather than 127.0.0.1.
+		let mismatch = options.clone().with_server_name("127.0.0.1");
+		assert!(client.clone().with_tls(&mismatch).unwrap().ping().await.is_err());
+		let unknown_ca = TlsOptions::new().with_server_name("clickhouse.internal");
+		assert!(client.clone().with_tls(&unknown_ca).unwrap().ping().await.is_err());
+		let insecure = TlsOptions::new()
+			.with_client_cert_pem(client_cert.pem(), client_key.serialize_pem())
+			.with_insecure_skip_verify(true);
+		client.clone().with_tls(&insecure).unwrap().ping().await.unwrap();
+		// server requires certificate of client.
+		assert!(client.clone().with_tls(&insecure.with_client_cert_pem("", "")).is_err());
+		assert!(client.with_tls(&TlsOptions::new().with_insecure_skip_verify(true)).unwrap().ping().await.is_err());
+		assert_eq!(1, server.requests().iter().filter(|r| r.path == "/ping").count());
+
+		let invalid = TlsOptions::new().with_ca_pem("not a certificate");
+		assert!(matches!(Client::new("https://localhost:8443").unwrap().with_tls(&invalid), Err(Error::TlsError(_))));
+		assert!(TlsOptions::new().with_ca_file(dir.path().join("missing.pem")).is_err());
+		assert!(Client::new("ftp://localhost").is_err());
+	}
+}
@@ -0,0 +1,13 @@
+pub type Result<T> = core::result::Result<T, crate::error::Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error("Parse schema error: {0}")]
+	ParseSchemaError(String),
+
+	#[error(transparent)]
+	CommonError(#[from] rickhouse_common::Error),
+
+	#[error("IO error: {0}")]
+	IoError(#[from] std::io::Error),
+}
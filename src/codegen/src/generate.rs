@@ -0,0 +1,266 @@
+use std::fmt::Write;
+
+use rickhouse_common::metadata::DataType;
+
+use crate::parse::Column;
+use crate::parse::Table;
+
+const KEYWORDS: [&str; 51] = [
+	"as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for", "if", "impl", "in",
+	"let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+	"trait", "true", "type", "unsafe", "use", "where", "while", "async", "await", "dyn", "abstract", "become", "box",
+	"do", "final", "macro", "override", "priv", "typeof", "unsized", "virtual", "yield", "try",
+];
+
+/// Generator of rust structs from table schemas.
+pub struct Generator {
+	derives: Vec<String>,
+	crate_path: Option<String>,
+	include_virtual: bool,
+}
+
+impl Default for Generator {
+	fn default() -> Self {
+		Generator {
+			derives: vec![
+				"Debug".to_owned(),
+				"Clone".to_owned(),
+				"serde::Serialize".to_owned(),
+				"serde::Deserialize".to_owned(),
+			],
+			crate_path: None,
+			include_virtual: false,
+		}
+	}
+}
+
+/// rust type of a column, enums are generated along with the struct.
+struct RustType {
+	name: String,
+	/// `ColumnType` of rust type is same as the column type.
+	exact: bool,
+}
+
+impl Generator {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// derives of structs besides `Row`, `Debug, Clone, serde::Serialize, serde::Deserialize` by
+	/// default.
+	pub fn with_derives<I: IntoIterator<Item = S>, S: Into<String>>(mut self, derives: I) -> Self {
+		self.derives = derives.into_iter().map(Into::into).collect();
+		self
+	}
+
+	/// path of `rickhouse` used by generated code, `rickhouse` by default.
+	pub fn with_crate_path(mut self, path: impl Into<String>) -> Self {
+		self.crate_path = Some(path.into());
+		self
+	}
+
+	/// whether to generate fields for MATERIALIZED, ALIAS and EPHEMERAL columns, false by default.
+	pub fn with_virtual_columns(mut self, include: bool) -> Self {
+		self.include_virtual = include;
+		self
+	}
+
+	pub fn generate(&self, tables: &[Table]) -> String {
+		let mut code = String::from("// Generated by rickhouse-codegen, do not edit.\n");
+		for table in tables {
+			code.push('\n');
+			self.generate_table(&mut code, table);
+		}
+		code
+	}
+
+	fn generate_table(&self, code: &mut String, table: &Table) {
+		let struct_name = to_camel_case(&table.name);
+		let crate_path = self.crate_path.as_deref().unwrap_or("rickhouse");
+		let mut enums = String::new();
+		let mut fields = String::new();
+		for column in
+			table.columns.iter().filter(|c| self.include_virtual || !c.default_kind.is_some_and(|k| k.is_virtual()))
+		{
+			let enum_prefix = format!("{}{}", struct_name, to_camel_case(column.spec.col_name()));
+			let rust_type = self.rust_type(column.spec.col_type(), &enum_prefix, &mut enums);
+			self.generate_field(&mut fields, column, &rust_type);
+		}
+
+		code.push_str(&enums);
+		let _ = writeln!(code, "/// Row of table `{}`.", table.name);
+		let derives = self
+			.derives
+			.iter()
+			.map(String::as_str)
+			.chain([format!("{}::Row", crate_path).as_str()])
+			.collect::<Vec<_>>()
+			.join(", ");
+		let _ = writeln!(code, "#[derive({})]", derives);
+		if let Some(path) = &self.crate_path {
+			let _ = writeln!(code, "#[row(crate = {:?})]", path);
+		}
+		let _ = writeln!(code, "pub struct {} {{\n{}}}", struct_name, fields);
+	}
+
+	fn generate_field(&self, code: &mut String, column: &Column, rust_type: &RustType) {
+		let col_name = column.spec.col_name();
+		let field_name = to_field_name(col_name);
+		if let Some(comment) = &column.comment {
+			comment.lines().for_each(|line| {
+				let _ = writeln!(code, "\t/// {}", line);
+			});
+		}
+
+		let mut row_attrs = Vec::new();
+		if field_name.trim_start_matches("r#") != col_name {
+			let _ = writeln!(code, "\t#[serde(rename = {:?})]", col_name);
+			row_attrs.push(format!("rename = {:?}", col_name));
+		}
+		if !rust_type.exact {
+			row_attrs.push(format!("type = {:?}", column.spec.col_type().to_string()));
+		}
+		if !row_attrs.is_empty() {
+			let _ = writeln!(code, "\t#[row({})]", row_attrs.join(", "));
+		}
+		let _ = writeln!(code, "\tpub {}: {},", field_name, rust_type.name);
+	}
+
+	/// map column type to rust type, `Option` for Nullable, `Vec` for Array, `BTreeMap` for Map and
+	/// so on.
+	fn rust_type(&self, data_type: &DataType, enum_prefix: &str, enums: &mut String) -> RustType {
+		let crate_path = self.crate_path.as_deref().unwrap_or("rickhouse");
+		let exact = |name: &str| RustType { name: name.to_owned(), exact: true };
+		let inexact = |name: String| RustType { name, exact: false };
+		match data_type {
+			DataType::Bool => exact("bool"),
+			DataType::Int8 => exact("i8"),
+			DataType::Int16 => exact("i16"),
+			DataType::Int32 => exact("i32"),
+			DataType::Int64 => exact("i64"),
+			DataType::Int128 => exact("i128"),
+			DataType::Int256 => exact(&format!("{}::values::bigint::i256", crate_path)),
+			DataType::UInt8 => exact("u8"),
+			DataType::UInt16 => exact("u16"),
+			DataType::UInt32 => exact("u32"),
+			DataType::UInt64 => exact("u64"),
+			DataType::UInt128 => exact("u128"),
+			DataType::UInt256 => exact(&format!("{}::values::bigint::u256", crate_path)),
+			DataType::Float32 => exact("f32"),
+			DataType::Float64 => exact("f64"),
+			DataType::String => exact("String"),
+			DataType::FixedString(size) => RustType { name: format!("[u8; {}]", size), exact: true },
+			DataType::Ipv4 => exact("std::net::Ipv4Addr"),
+			DataType::Ipv6 => exact("std::net::Ipv6Addr"),
+			DataType::Uuid => exact(&format!("{}::values::uuid::Uuid", crate_path)),
+			// days, seconds and ticks since epoch, they are (de)serialized as numbers.
+			DataType::Date => inexact("u16".to_owned()),
+			DataType::Date32 => inexact("i32".to_owned()),
+			DataType::DateTime(_) => inexact("u32".to_owned()),
+			DataType::DateTime64(..) => inexact("i64".to_owned()),
+			// text form keeps all digits of decimal.
+			DataType::Decimal(..)
+			| DataType::Decimal32(_)
+			| DataType::Decimal64(_)
+			| DataType::Decimal128(_)
+			| DataType::Decimal256(_) => inexact("String".to_owned()),
+			DataType::Enum8(variants) => inexact(generate_enum(enum_prefix, variants, enums, &self.derives)),
+			DataType::Enum16(variants) => inexact(generate_enum(enum_prefix, variants, enums, &self.derives)),
+			DataType::LowCardinality(inner) => inexact(self.rust_type(inner, enum_prefix, enums).name),
+			DataType::SimpleAggregateFunction(_, types) if types.len() == 1 => {
+				inexact(self.rust_type(&types[0], enum_prefix, enums).name)
+			}
+			DataType::AggregateFunction(..) | DataType::SimpleAggregateFunction(..) => inexact("Vec<u8>".to_owned()),
+			DataType::Json => inexact("String".to_owned()),
+			DataType::Nothing => inexact("()".to_owned()),
+			// type of value is decided at runtime, both can be NULL.
+			DataType::Variant(_) | DataType::Dynamic(_) => inexact("Option<serde_json::Value>".to_owned()),
+			DataType::Tuple(fields) => {
+				let types = fields
+					.iter()
+					.enumerate()
+					.map(|(idx, (_, field))| self.rust_type(field, &format!("{}{}", enum_prefix, idx), enums))
+					.collect::<Vec<_>>();
+				let names = types.iter().map(|t| t.name.as_str()).collect::<Vec<_>>();
+				RustType {
+					name: if names.len() == 1 { format!("({},)", names[0]) } else { format!("({})", names.join(", ")) },
+					exact: types.iter().all(|t| t.exact) && fields.iter().all(|(name, _)| name.is_empty()),
+				}
+			}
+			DataType::Array(inner) => {
+				let inner = self.rust_type(inner, enum_prefix, enums);
+				RustType { name: format!("Vec<{}>", inner.name), exact: inner.exact }
+			}
+			DataType::Map(key, value) => {
+				let key = self.rust_type(key, &format!("{}Key", enum_prefix), enums);
+				let value = self.rust_type(value, &format!("{}Value", enum_prefix), enums);
+				RustType {
+					name: format!("std::collections::BTreeMap<{}, {}>", key.name, value.name),
+					exact: key.exact && value.exact,
+				}
+			}
+			DataType::Nullable(inner) => {
+				let inner = self.rust_type(inner, enum_prefix, enums);
+				RustType { name: format!("Option<{}>", inner.name), exact: inner.exact }
+			}
+		}
+	}
+}
+
+/// generate rust enum for ClickHouse enum, variants are (de)serialized by name.
+fn generate_enum<T>(name: &str, variants: &[(String, T)], enums: &mut String, derives: &[String]) -> String {
+	let derives = derives.iter().map(String::as_str).filter(|d| *d != "Default").collect::<Vec<_>>().join(", ");
+	let _ = writeln!(enums, "#[derive({})]\npub enum {} {{", derives, name);
+	let mut used = Vec::new();
+	for (variant, _) in variants {
+		let mut ident = to_camel_case(variant);
+		if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+			ident = format!("V{}", ident);
+		}
+		while used.contains(&ident) {
+			ident.push('_');
+		}
+		let _ = writeln!(enums, "\t#[serde(rename = {:?})]\n\t{},", variant, ident);
+		used.push(ident);
+	}
+	let _ = writeln!(enums, "}}\n");
+	name.to_owned()
+}
+
+/// convert `user_events` or `user.events` to `UserEvents`.
+fn to_camel_case(s: &str) -> String {
+	s.split(|c: char| !c.is_ascii_alphanumeric())
+		.filter(|word| !word.is_empty())
+		.map(|word| {
+			let mut chars = word.chars();
+			chars.next().map(|c| c.to_ascii_uppercase().to_string() + chars.as_str()).unwrap_or_default()
+		})
+		.collect()
+}
+
+/// convert column name to snake case identifier, keywords are escaped.
+fn to_field_name(s: &str) -> String {
+	let mut name = String::with_capacity(s.len());
+	for (idx, c) in s.chars().enumerate() {
+		match c {
+			c if c.is_ascii_uppercase() => {
+				if idx > 0 && !name.ends_with('_') {
+					name.push('_');
+				}
+				name.push(c.to_ascii_lowercase());
+			}
+			c if c.is_ascii_alphanumeric() => name.push(c),
+			_ if !name.ends_with('_') => name.push('_'),
+			_ => (),
+		}
+	}
+
+	if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+		name.insert(0, '_');
+	}
+	match name.as_str() {
+		"self" | "Self" | "super" | "crate" => format!("{}_", name),
+		n if KEYWORDS.contains(&n) => format!("r#{}", name),
+		_ => name,
+	}
+}
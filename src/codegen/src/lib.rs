@@ -0,0 +1,147 @@
+//! Generate rust structs from ClickHouse table schemas.
+//!
+//! Schemas are `DESCRIBE TABLE` outputs in tab separated format or `CREATE TABLE` statements, so
+//! it runs against checked-in schema snapshots without a live server, e.g. in `build.rs`:
+//!
+//! ```no_run
+//! let out = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap()).join("schema.rs");
+//! rickhouse_codegen::generate_file(["schema/events.sql", "schema/users.tsv"], out).unwrap();
+//! ```
+//!
+//! and then `include!(concat!(env!("OUT_DIR"), "/schema.rs"));` in source.
+
+mod error;
+mod generate;
+mod parse;
+
+use std::path::Path;
+
+pub use error::Error;
+pub use error::Result;
+pub use generate::Generator;
+pub use parse::parse_create_table;
+pub use parse::parse_describe;
+pub use parse::Column;
+pub use parse::DefaultKind;
+pub use parse::Table;
+
+/// parse schema text, `CREATE` statement or `DESCRIBE` output named by `table`.
+pub fn parse_schema(table: &str, text: &str) -> Result<Table> {
+	match text.trim_start().to_ascii_uppercase().starts_with("CREATE") {
+		true => parse_create_table(text),
+		false => parse_describe(table, text),
+	}
+}
+
+/// parse schema file, table name of `DESCRIBE` output is the file stem.
+pub fn parse_file(path: impl AsRef<Path>) -> Result<Table> {
+	let path = path.as_ref();
+	let table = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+	parse_schema(&table, &std::fs::read_to_string(path)?)
+}
+
+/// generate structs of schema files to output file with default generator, it is used by
+/// `build.rs`. Cargo is told to rerun the build script if schema files change.
+pub fn generate_file<I, P>(inputs: I, output: impl AsRef<Path>) -> Result<()>
+where
+	I: IntoIterator<Item = P>,
+	P: AsRef<Path>,
+{
+	// `OUT_DIR` is set only for build scripts, the instruction is noise elsewhere.
+	let build_script = std::env::var_os("OUT_DIR").is_some();
+	let mut tables = Vec::new();
+	for input in inputs {
+		if build_script {
+			println!("cargo:rerun-if-changed={}", input.as_ref().display());
+		}
+		tables.push(parse_file(input)?);
+	}
+	std::fs::write(output, Generator::new().generate(&tables))?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use rickhouse_common::metadata::DataType;
+
+	use super::*;
+
+	const CREATE_TABLE: &str = r#"
+		-- events of users.
+		CREATE TABLE IF NOT EXISTS analytics.`user_events` ON CLUSTER default
+		(
+			`id` UInt64,
+			`type` LowCardinality(String) COMMENT 'event type',
+			`status` Enum8('new' = 1, 'done' = 2),
+			ts DateTime64(3, 'UTC') DEFAULT now64(),
+			`user.name` String NULL,
+			tags Array(String) CODEC(ZSTD(1)),
+			attrs Map(String, Tuple(UInt8, Nullable(Float64))),
+			day Date MATERIALIZED toDate(ts),
+			INDEX idx_type type TYPE set(0) GRANULARITY 1
+		)
+		ENGINE = MergeTree
+		ORDER BY (id, ts)
+	"#;
+
+	#[test]
+	fn test_parse_create_table() {
+		let table = parse_create_table(CREATE_TABLE).unwrap();
+		assert_eq!("user_events", table.name);
+		assert_eq!(
+			vec!["id", "type", "status", "ts", "user.name", "tags", "attrs", "day"],
+			table.columns.iter().map(|c| c.spec.col_name()).collect::<Vec<_>>()
+		);
+		assert_eq!(Some("event type".to_owned()), table.columns[1].comment);
+		assert_eq!(Some(DefaultKind::Default), table.columns[3].default_kind);
+		assert_eq!(&DataType::Nullable(DataType::String.into()), table.columns[4].spec.col_type());
+		assert_eq!(Some(DefaultKind::Materialized), table.columns[7].default_kind);
+	}
+
+	#[test]
+	fn test_parse_describe() {
+		let text = "name\ttype\tdefault_type\tdefault_expression\tcomment\n\
+		            id\tUInt64\t\t\t\n\
+		            ts\tDateTime\tDEFAULT\tnow()\tcreate\\ttime\n";
+		let table = parse_schema("events", text).unwrap();
+		assert_eq!(2, table.columns.len());
+		assert_eq!(&DataType::DateTime(None), table.columns[1].spec.col_type());
+		assert_eq!(Some("create\ttime".to_owned()), table.columns[1].comment);
+	}
+
+	#[test]
+	fn test_generate() {
+		let code = Generator::new().generate(&[parse_create_table(CREATE_TABLE).unwrap()]);
+		assert_eq!(
+			r#"// Generated by rickhouse-codegen, do not edit.
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum UserEventsStatus {
+	#[serde(rename = "new")]
+	New,
+	#[serde(rename = "done")]
+	Done,
+}
+
+/// Row of table `user_events`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, rickhouse::Row)]
+pub struct UserEvents {
+	pub id: u64,
+	/// event type
+	#[row(type = "LowCardinality(String)")]
+	pub r#type: String,
+	#[row(type = "Enum8('new' = 1, 'done' = 2)")]
+	pub status: UserEventsStatus,
+	#[row(type = "DateTime64(3, 'UTC')")]
+	pub ts: i64,
+	#[serde(rename = "user.name")]
+	#[row(rename = "user.name")]
+	pub user_name: Option<String>,
+	pub tags: Vec<String>,
+	pub attrs: std::collections::BTreeMap<String, (u8, Option<f64>)>,
+}
+"#,
+			code
+		);
+	}
+}
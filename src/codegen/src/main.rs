@@ -0,0 +1,60 @@
+use std::process::ExitCode;
+
+use rickhouse_codegen::parse_file;
+use rickhouse_codegen::Generator;
+
+const USAGE: &str = "Usage: rickhouse-codegen [-o OUTPUT] [--crate PATH] [--derive DERIVES] SCHEMA_FILE...
+
+Generate rust structs from `CREATE TABLE` statements or tab separated `DESCRIBE TABLE` outputs,
+table name of `DESCRIBE` output is the file stem.
+
+Options:
+  -o, --output PATH    write to file instead of stdout
+  --crate PATH         path of rickhouse used by generated code
+  --derive DERIVES     comma separated derives besides Row
+  --virtual            generate fields for MATERIALIZED, ALIAS and EPHEMERAL columns";
+
+fn main() -> ExitCode {
+	match run(std::env::args().skip(1).collect()) {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(err) => {
+			eprintln!("{}", err);
+			ExitCode::FAILURE
+		}
+	}
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+	let mut generator = Generator::new();
+	let mut output = None;
+	let mut inputs = Vec::new();
+	let mut args = args.into_iter();
+	while let Some(arg) = args.next() {
+		let mut value = |name: &str| args.next().ok_or_else(|| format!("missing value of {}\n\n{}", name, USAGE));
+		match arg.as_str() {
+			"-o" | "--output" => output = Some(value(&arg)?),
+			"--crate" => generator = generator.with_crate_path(value(&arg)?),
+			"--derive" => generator = generator.with_derives(value(&arg)?.split(',').map(|d| d.trim().to_owned())),
+			"--virtual" => generator = generator.with_virtual_columns(true),
+			"-h" | "--help" => return Err(USAGE.to_owned()),
+			_ if arg.starts_with('-') => return Err(format!("unknown option {}\n\n{}", arg, USAGE)),
+			_ => inputs.push(arg),
+		}
+	}
+
+	if inputs.is_empty() {
+		return Err(USAGE.to_owned());
+	}
+	let tables = inputs
+		.iter()
+		.map(|input| parse_file(input).map_err(|err| format!("{}: {}", input, err)))
+		.collect::<Result<Vec<_>, _>>()?;
+	let code = generator.generate(&tables);
+	match output {
+		Some(path) => std::fs::write(&path, code).map_err(|err| format!("{}: {}", path, err)),
+		None => {
+			print!("{}", code);
+			Ok(())
+		}
+	}
+}
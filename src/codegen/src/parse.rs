@@ -0,0 +1,291 @@
+use rickhouse_common::metadata::ColumnSpec;
+
+use crate::error::Error;
+use crate::error::Result;
+
+/// Table schema parsed from `DESCRIBE TABLE` output or `CREATE TABLE` statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+	pub name: String,
+	pub columns: Vec<Column>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column {
+	pub spec: ColumnSpec,
+	pub default_kind: Option<DefaultKind>,
+	pub comment: Option<String>,
+}
+
+/// Kind of column default expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefaultKind {
+	Default,
+	Materialized,
+	Alias,
+	Ephemeral,
+}
+
+impl DefaultKind {
+	fn parse(s: &str) -> Option<DefaultKind> {
+		match s.to_ascii_uppercase().as_str() {
+			"DEFAULT" => Some(DefaultKind::Default),
+			"MATERIALIZED" => Some(DefaultKind::Materialized),
+			"ALIAS" => Some(DefaultKind::Alias),
+			"EPHEMERAL" => Some(DefaultKind::Ephemeral),
+			_ => None,
+		}
+	}
+
+	/// column is not returned by `SELECT *` and can't be inserted.
+	pub fn is_virtual(&self) -> bool {
+		!matches!(self, DefaultKind::Default)
+	}
+}
+
+/// keywords may follow column type in `CREATE TABLE`.
+const TYPE_END_KEYWORDS: [&str; 12] = [
+	"DEFAULT",
+	"MATERIALIZED",
+	"ALIAS",
+	"EPHEMERAL",
+	"CODEC",
+	"TTL",
+	"COMMENT",
+	"NULL",
+	"NOT",
+	"PRIMARY",
+	"SETTINGS",
+	"STATISTICS",
+];
+
+/// parse tab separated output of `DESCRIBE TABLE`, header line of `TabSeparatedWithNames` is
+/// skipped.
+pub fn parse_describe(table: &str, text: &str) -> Result<Table> {
+	let mut columns = Vec::new();
+	for line in text.lines().filter(|line| !line.trim().is_empty()) {
+		let fields = line.split('\t').map(unescape_tsv).collect::<Vec<_>>();
+		if fields.len() < 2 {
+			return Err(Error::ParseSchemaError(format!("invalid describe line: {}", line)));
+		}
+		if columns.is_empty() && fields[0] == "name" && fields[1] == "type" {
+			continue;
+		}
+
+		let field = |idx: usize| fields.get(idx).filter(|s| !s.is_empty()).cloned();
+		columns.push(Column {
+			spec: ColumnSpec::try_new(fields[0].clone(), fields[1].clone())?,
+			default_kind: field(2).and_then(|kind| DefaultKind::parse(&kind)),
+			comment: field(4),
+		});
+	}
+	Ok(Table { name: table.to_owned(), columns })
+}
+
+/// parse `CREATE TABLE` statement, indices, projections and constraints are ignored.
+pub fn parse_create_table(sql: &str) -> Result<Table> {
+	let sql = strip_comments(sql);
+	let upper = sql.to_ascii_uppercase();
+	let table_idx = find_keyword(&upper, "TABLE")
+		.ok_or_else(|| Error::ParseSchemaError("no CREATE TABLE statement found".to_owned()))?;
+
+	let mut rest = sql[table_idx + 5..].trim_start();
+	if rest.to_ascii_uppercase().starts_with("IF NOT EXISTS") {
+		rest = rest[13..].trim_start();
+	}
+	let (name, _) = read_identifier(rest)?;
+	let name = split_top_level(&name, '.').pop().map(|s| unquote(&s)).unwrap_or_default();
+
+	let open = rest.find('(').ok_or_else(|| Error::ParseSchemaError(format!("no columns of table {}", name)))?;
+	let close = find_closing(rest, open)?;
+	let mut columns = Vec::new();
+	for element in split_top_level(&rest[(open + 1)..close], ',') {
+		let element = element.trim();
+		let first_word = element.split_whitespace().next().unwrap_or_default().to_ascii_uppercase();
+		if element.is_empty()
+			|| ["INDEX", "PROJECTION", "CONSTRAINT", "PRIMARY", "ORDER"].contains(&first_word.as_str())
+		{
+			continue;
+		}
+		columns.push(parse_column_definition(element)?);
+	}
+	Ok(Table { name, columns })
+}
+
+/// parse column definition like `` `id` Nullable(UInt64) DEFAULT 0 COMMENT 'id' ``.
+fn parse_column_definition(element: &str) -> Result<Column> {
+	let (name, rest) = read_identifier(element)?;
+	let rest = rest.trim_start();
+	let type_len = type_length(rest);
+	if type_len == 0 {
+		return Err(Error::ParseSchemaError(format!("no type of column: {}", element)));
+	}
+
+	let mut original_type = rest[..type_len].trim().to_owned();
+	let mut modifiers = rest[type_len..].trim();
+	// `NULL` or `NOT NULL` follows type directly.
+	if find_keyword(&modifiers.to_ascii_uppercase(), "NULL") == Some(0) {
+		original_type = format!("Nullable({})", original_type);
+		modifiers = modifiers[4..].trim_start();
+	} else if modifiers.to_ascii_uppercase().starts_with("NOT") {
+		modifiers = modifiers[3..].trim_start().get(4..).unwrap_or_default().trim_start();
+	}
+
+	let upper = modifiers.to_ascii_uppercase();
+	let default_kind = modifiers.split_whitespace().next().and_then(DefaultKind::parse);
+	let comment =
+		find_keyword(&upper, "COMMENT").and_then(|idx| read_string_literal(modifiers[idx + 7..].trim_start()));
+	Ok(Column { spec: ColumnSpec::try_new(unquote(&name), original_type)?, default_kind, comment })
+}
+
+/// length of type expression, it ends at a top level keyword.
+fn type_length(s: &str) -> usize {
+	let mut depth = 0;
+	let mut quote = None;
+	let mut prev_space = false;
+	for (idx, c) in s.char_indices() {
+		match (quote, c) {
+			(Some(q), c) if c == q => quote = None,
+			(Some(_), _) => (),
+			(None, '\'' | '"' | '`') => quote = Some(c),
+			(None, '(') => depth += 1,
+			(None, ')') => depth -= 1,
+			(None, c) if depth == 0 && prev_space && !c.is_whitespace() => {
+				let word = s[idx..].split(|c: char| !c.is_ascii_alphanumeric() && c != '_').next().unwrap_or_default();
+				if TYPE_END_KEYWORDS.contains(&word.to_ascii_uppercase().as_str()) {
+					return idx;
+				}
+			}
+			_ => (),
+		}
+		prev_space = c.is_whitespace();
+	}
+	s.len()
+}
+
+/// read identifier which may be quoted by backticks or double quotes, return it and the rest.
+fn read_identifier(s: &str) -> Result<(String, &str)> {
+	let s = s.trim_start();
+	let end = match s.chars().next() {
+		Some(q @ ('`' | '"')) => {
+			let mut escaped = false;
+			s.char_indices()
+				.skip(1)
+				.find(|&(_, c)| {
+					let end = c == q && !escaped;
+					escaped = c == '\\' && !escaped;
+					end
+				})
+				.map(|(idx, _)| idx + 1)
+				.ok_or_else(|| Error::ParseSchemaError(format!("unclosed identifier: {}", s)))?
+		}
+		_ => s.find(|c: char| c.is_whitespace() || c == '(' || c == ',').unwrap_or(s.len()),
+	};
+	// identifier like `db`.`table`
+	if s[end..].starts_with('.') {
+		let (next, rest) = read_identifier(&s[end + 1..])?;
+		return Ok((format!("{}.{}", &s[..end], next), rest));
+	}
+	Ok((s[..end].to_owned(), &s[end..]))
+}
+
+fn unquote(s: &str) -> String {
+	let s = s.trim();
+	match s.chars().next() {
+		Some(q @ ('`' | '"')) if s.len() >= 2 && s.ends_with(q) => {
+			s[1..s.len() - 1].replace(&format!("\\{}", q), &q.to_string())
+		}
+		_ => s.to_owned(),
+	}
+}
+
+/// read string literal like `'it\'s'`.
+fn read_string_literal(s: &str) -> Option<String> {
+	let mut chars = s.strip_prefix('\'')?.chars();
+	let mut literal = String::new();
+	while let Some(c) = chars.next() {
+		match c {
+			'\\' => literal.push(chars.next()?),
+			'\'' => return Some(literal),
+			c => literal.push(c),
+		}
+	}
+	None
+}
+
+fn find_closing(s: &str, open: usize) -> Result<usize> {
+	let mut depth = 0;
+	let mut quote = None;
+	for (idx, c) in s.char_indices().skip_while(|(idx, _)| *idx < open) {
+		match (quote, c) {
+			(Some(q), c) if c == q => quote = None,
+			(Some(_), _) => (),
+			(None, '\'' | '"' | '`') => quote = Some(c),
+			(None, '(') => depth += 1,
+			(None, ')') => {
+				depth -= 1;
+				if depth == 0 {
+					return Ok(idx);
+				}
+			}
+			_ => (),
+		}
+	}
+	Err(Error::ParseSchemaError("unbalanced parentheses".to_owned()))
+}
+
+/// split by separator which is not in parentheses or quotes.
+fn split_top_level(s: &str, sep: char) -> Vec<String> {
+	let mut parts = Vec::new();
+	let mut current = String::new();
+	let mut depth = 0;
+	let mut quote = None;
+	for c in s.chars() {
+		match (quote, c) {
+			(Some(q), c) if c == q => quote = None,
+			(Some(_), _) => (),
+			(None, '\'' | '"' | '`') => quote = Some(c),
+			(None, '(') => depth += 1,
+			(None, ')') => depth -= 1,
+			(None, c) if c == sep && depth == 0 => {
+				parts.push(std::mem::take(&mut current));
+				continue;
+			}
+			_ => (),
+		}
+		current.push(c);
+	}
+	parts.push(current);
+	parts
+}
+
+/// find keyword at word boundary, `s` must be upper case.
+fn find_keyword(s: &str, keyword: &str) -> Option<usize> {
+	let is_word = |c: Option<char>| c.is_some_and(|c| c.is_ascii_alphanumeric() || c == '_');
+	s.match_indices(keyword)
+		.map(|(idx, _)| idx)
+		.find(|&idx| !is_word(s[..idx].chars().next_back()) && !is_word(s[idx + keyword.len()..].chars().next()))
+}
+
+fn strip_comments(sql: &str) -> String {
+	sql.lines().filter(|line| !line.trim_start().starts_with("--")).collect::<Vec<_>>().join("\n")
+}
+
+fn unescape_tsv(s: &str) -> String {
+	let mut unescaped = String::with_capacity(s.len());
+	let mut chars = s.chars();
+	while let Some(c) = chars.next() {
+		match c {
+			'\\' => match chars.next() {
+				Some('t') => unescaped.push('\t'),
+				Some('n') => unescaped.push('\n'),
+				Some('r') => unescaped.push('\r'),
+				Some('0') => unescaped.push('\0'),
+				Some(c) => unescaped.push(c),
+				None => unescaped.push('\\'),
+			},
+			c => unescaped.push(c),
+		}
+	}
+	unescaped
+}
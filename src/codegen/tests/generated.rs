@@ -0,0 +1,62 @@
+//! generated code compiles against `rickhouse` and rows round trip through RowBinary.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use rickhouse::metadata::RowSchema;
+use rickhouse::values::bigint::i256;
+use rickhouse::values::bigint::u256;
+use rickhouse::values::uuid::Uuid;
+use rickhouse_codegen::parse_file;
+use rickhouse_codegen::Generator;
+use rickhouse_common::rowbinary;
+
+mod generated {
+	include!("generated/all_types.rs");
+}
+
+use generated::AllTypes;
+use generated::AllTypesStatus;
+
+#[test]
+fn test_generated_is_fresh() {
+	let table = parse_file("tests/schema/all_types.sql").unwrap();
+	let code = std::fs::read_to_string("tests/generated/all_types.rs").unwrap();
+	assert_eq!(code, Generator::new().generate(&[table]));
+}
+
+#[test]
+fn test_round_trip() {
+	let mut big = [0xff; 32];
+	big[0] = 0xfe;
+	let row = AllTypes {
+		id: 1,
+		big: i256(big),
+		ubig: u256([7; 32]),
+		uid: Uuid([1; 16]),
+		created: 1_700_000_000,
+		updated: 1_700_000_001,
+		ts: 1_700_000_000_123,
+		price: "12.3400".to_owned(),
+		total: Some("-1234567890123456789.01".to_owned()),
+		day: 19_782,
+		birthday: -25_567,
+		code: *b"abcd",
+		ip: "2001:db8::1".parse().unwrap(),
+		status: AllTypesStatus::Done,
+		tag: None,
+		flags: vec![Some(1), None],
+		attrs: BTreeMap::from([("a".to_owned(), 1)]),
+		pair: ("b".to_owned(), -2),
+	};
+	let columns = AllTypes::columns();
+	let mut body = Vec::new();
+	rowbinary::write_header(&mut body, &columns);
+	rowbinary::write_row(&mut body, &row, &columns).unwrap();
+
+	let mut buf = &body[..];
+	let metadata = Arc::new(rowbinary::read_header(&mut buf).unwrap());
+	let decoded: AllTypes = rowbinary::read_row_into(&mut buf, &metadata).unwrap();
+	assert!(buf.is_empty());
+	assert_eq!(format!("{:?}", row), format!("{:?}", decoded));
+}
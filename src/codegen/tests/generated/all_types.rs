@@ -0,0 +1,41 @@
+// Generated by rickhouse-codegen, do not edit.
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum AllTypesStatus {
+	#[serde(rename = "new")]
+	New,
+	#[serde(rename = "done")]
+	Done,
+}
+
+/// Row of table `all_types`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, rickhouse::Row)]
+pub struct AllTypes {
+	pub id: u64,
+	pub big: rickhouse::values::bigint::i256,
+	pub ubig: rickhouse::values::bigint::u256,
+	pub uid: rickhouse::values::uuid::Uuid,
+	#[row(type = "DateTime")]
+	pub created: u32,
+	#[row(type = "DateTime('UTC')")]
+	pub updated: u32,
+	#[row(type = "DateTime64(3)")]
+	pub ts: i64,
+	#[row(type = "Decimal(18, 4)")]
+	pub price: String,
+	#[row(type = "Nullable(Decimal128(2))")]
+	pub total: Option<String>,
+	#[row(type = "Date")]
+	pub day: u16,
+	#[row(type = "Date32")]
+	pub birthday: i32,
+	pub code: [u8; 4],
+	pub ip: std::net::Ipv6Addr,
+	#[row(type = "Enum8('new' = 1, 'done' = 2)")]
+	pub status: AllTypesStatus,
+	#[row(type = "LowCardinality(Nullable(String))")]
+	pub tag: Option<String>,
+	pub flags: Vec<Option<u8>>,
+	pub attrs: std::collections::BTreeMap<String, u64>,
+	pub pair: (String, i32),
+}
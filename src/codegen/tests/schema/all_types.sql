@@ -0,0 +1,23 @@
+CREATE TABLE all_types
+(
+	`id` UInt64,
+	`big` Int256,
+	`ubig` UInt256,
+	`uid` UUID,
+	`created` DateTime,
+	`updated` DateTime('UTC'),
+	`ts` DateTime64(3),
+	`price` Decimal(18, 4),
+	`total` Nullable(Decimal128(2)),
+	`day` Date,
+	`birthday` Date32,
+	`code` FixedString(4),
+	`ip` IPv6,
+	`status` Enum8('new' = 1, 'done' = 2),
+	`tag` LowCardinality(Nullable(String)),
+	`flags` Array(Nullable(UInt8)),
+	`attrs` Map(String, UInt64),
+	`pair` Tuple(String, Int32)
+)
+ENGINE = MergeTree
+ORDER BY id
@@ -39,6 +39,15 @@ pub enum Error {
 	#[error("Deserialize from any not supported")]
 	SerdeAnyNotSupported,
 
+	#[error("Column not found: {0}")]
+	ColumnNotFound(String),
+
+	#[error("Convert value error: {0}")]
+	ConvertError(String),
+
+	#[error("Schema mismatch: {}", .0.join("; "))]
+	SchemaMismatch(Vec<String>),
+
 	#[error(transparent)]
 	AnyError(#[from] anyhow::Error),
 }
@@ -48,3 +57,9 @@ impl serde::de::Error for Error {
 		Error::SerdeError(msg.to_string())
 	}
 }
+
+impl serde::ser::Error for Error {
+	fn custom<T: Display>(msg: T) -> Self {
+		Error::SerdeError(msg.to_string())
+	}
+}
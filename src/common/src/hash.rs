@@ -0,0 +1,498 @@
+//! Hash functions of ClickHouse, results are the same as `cityHash64`, `xxHash64` and
+//! `murmurHash3_64` of server.
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::metadata::DataType;
+use crate::rowbinary;
+use crate::values::value::Value;
+
+const K0: u64 = 0xc3a5c85c97cb3127;
+const K1: u64 = 0xb492b66fbe98f273;
+const K2: u64 = 0x9ae16a3b2f90404f;
+const K3: u64 = 0xc949d7c7509e6557;
+
+/// hash of no arguments, e.g. `cityHash64()`.
+const EMPTY_ARGUMENTS: u64 = 0xe28dbde7fe22e41c;
+
+/// Hash function of server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HashFunction {
+	/// `cityHash64`, CityHash v1.0.2 used by ClickHouse.
+	CityHash64,
+	/// `xxHash64` with seed 0.
+	XxHash64,
+	/// `murmurHash3_64`, xor of both halves of MurmurHash3_x64_128 with seed 0.
+	MurmurHash3_64,
+}
+
+impl HashFunction {
+	/// name of the function in sql.
+	pub fn name(&self) -> &'static str {
+		match self {
+			HashFunction::CityHash64 => "cityHash64",
+			HashFunction::XxHash64 => "xxHash64",
+			HashFunction::MurmurHash3_64 => "murmurHash3_64",
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<HashFunction> {
+		[HashFunction::CityHash64, HashFunction::XxHash64, HashFunction::MurmurHash3_64]
+			.into_iter()
+			.find(|function| function.name().eq_ignore_ascii_case(name))
+	}
+
+	/// hash of a String argument.
+	pub fn hash_bytes(&self, data: &[u8]) -> u64 {
+		match self {
+			HashFunction::CityHash64 => city_hash64(data),
+			HashFunction::XxHash64 => xx_hash64(data),
+			HashFunction::MurmurHash3_64 => murmur_hash3_64(data),
+		}
+	}
+
+	/// hash of arguments like `cityHash64(a, b)`, tuples are hashed as their elements.
+	pub fn hash_values(&self, args: &[(&Value, &DataType)]) -> Result<u64> {
+		let mut hash = None;
+		for (value, data_type) in args {
+			self.hash_into(&mut hash, value, data_type)?;
+		}
+		Ok(hash.unwrap_or(EMPTY_ARGUMENTS))
+	}
+
+	fn combine(&self, h1: u64, h2: u64) -> u64 {
+		match self {
+			HashFunction::CityHash64 | HashFunction::XxHash64 => hash128_to_64(h1, h2),
+			HashFunction::MurmurHash3_64 => int_hash64_impl(h1) ^ h2,
+		}
+	}
+
+	/// hash the argument and combine it into `hash`, `None` if it's the first argument.
+	fn hash_into(&self, hash: &mut Option<u64>, value: &Value, data_type: &DataType) -> Result<()> {
+		let mut push = |h: u64| *hash = Some(hash.map_or(h, |prev| self.combine(prev, h)));
+		match (value, data_type) {
+			(_, DataType::LowCardinality(inner)) => self.hash_into(hash, value, inner)?,
+			(Value::Null, _) => return Err(Error::ConvertError(format!("NULL can't be hashed by {}", self.name()))),
+			(_, DataType::Nullable(inner)) => self.hash_into(hash, value, inner)?,
+			(Value::String(s), DataType::String) => push(self.hash_bytes(s)),
+			(Value::Tuple(values), DataType::Tuple(types)) if values.len() == types.len() => {
+				for (value, (_, data_type)) in values.iter().zip(types) {
+					self.hash_into(hash, value, data_type)?;
+				}
+			}
+			(Value::Array(values), DataType::Array(inner)) => {
+				push(int_hash64_impl(values.len() as u64));
+				for value in values {
+					let mut element = None;
+					self.hash_into(&mut element, value, inner)?;
+					*hash = Some(self.combine(hash.unwrap_or_default(), element.unwrap_or(EMPTY_ARGUMENTS)));
+				}
+			}
+			(_, DataType::Map(..) | DataType::Json | DataType::Variant(_) | DataType::Dynamic(_))
+			| (_, DataType::Nothing | DataType::AggregateFunction(..) | DataType::SimpleAggregateFunction(..))
+			| (Value::Tuple(_) | Value::Array(_), _) => {
+				return Err(Error::ConvertError(format!("{} can't be hashed by {}", data_type, self.name())));
+			}
+			// other values are hashed by their bytes in memory, which is the same as RowBinary.
+			_ => {
+				let mut bytes = Vec::with_capacity(16);
+				rowbinary::write_value(&mut bytes, value, data_type)?;
+				if *self == HashFunction::CityHash64 && is_int_hashed(data_type) {
+					let mut word = [0; 8];
+					word[..bytes.len()].copy_from_slice(&bytes);
+					push(int_hash64_impl(u64::from_le_bytes(word)));
+				} else {
+					push(self.hash_bytes(&bytes));
+				}
+			}
+		}
+		Ok(())
+	}
+}
+
+/// native numbers and dates are hashed by intHash64 in `cityHash64`, values of other types like
+/// FixedString, Decimal32 or DateTime64 are hashed by their bytes even if they're 8 bytes or
+/// shorter.
+fn is_int_hashed(data_type: &DataType) -> bool {
+	matches!(
+		data_type,
+		DataType::Bool
+			| DataType::Int8
+			| DataType::Int16
+			| DataType::Int32
+			| DataType::Int64
+			| DataType::UInt8
+			| DataType::UInt16
+			| DataType::UInt32
+			| DataType::UInt64
+			| DataType::Float32
+			| DataType::Float64
+			| DataType::Date
+			| DataType::Date32
+			| DataType::DateTime(_)
+			| DataType::Enum8(_)
+			| DataType::Enum16(_)
+			| DataType::Ipv4
+	)
+}
+
+/// `intHash64` of server, the finalizer of MurmurHash3.
+pub fn int_hash64(mut x: u64) -> u64 {
+	x ^= x >> 33;
+	x = x.wrapping_mul(0xff51afd7ed558ccd);
+	x ^= x >> 33;
+	x = x.wrapping_mul(0xc4ceb9fe1a85ec53);
+	x ^= x >> 33;
+	x
+}
+
+/// hash of integer arguments of `cityHash64`.
+fn int_hash64_impl(x: u64) -> u64 {
+	int_hash64(x ^ 0x4cf2d2baae6da887)
+}
+
+/// `Hash128to64` of CityHash, it combines hashes of several arguments.
+pub fn hash128_to_64(low: u64, high: u64) -> u64 {
+	const MUL: u64 = 0x9ddfea08eb382d69;
+	let mut a = (low ^ high).wrapping_mul(MUL);
+	a ^= a >> 47;
+	let mut b = (high ^ a).wrapping_mul(MUL);
+	b ^= b >> 47;
+	b.wrapping_mul(MUL)
+}
+
+fn fetch64(s: &[u8], idx: usize) -> u64 {
+	u64::from_le_bytes(s[idx..idx + 8].try_into().unwrap())
+}
+
+fn fetch32(s: &[u8], idx: usize) -> u64 {
+	u32::from_le_bytes(s[idx..idx + 4].try_into().unwrap()) as u64
+}
+
+fn shift_mix(v: u64) -> u64 {
+	v ^ (v >> 47)
+}
+
+fn city_len0to16(s: &[u8]) -> u64 {
+	let len = s.len();
+	if len > 8 {
+		let a = fetch64(s, 0);
+		let b = fetch64(s, len - 8);
+		return hash128_to_64(a, b.wrapping_add(len as u64).rotate_right(len as u32)) ^ b;
+	}
+	if len >= 4 {
+		let a = fetch32(s, 0);
+		return hash128_to_64((len as u64).wrapping_add(a << 3), fetch32(s, len - 4));
+	}
+	if len > 0 {
+		let (a, b, c) = (s[0] as u32, s[len >> 1] as u32, s[len - 1] as u32);
+		let y = a.wrapping_add(b << 8);
+		let z = (len as u32).wrapping_add(c << 2);
+		return shift_mix((y as u64).wrapping_mul(K2) ^ (z as u64).wrapping_mul(K3)).wrapping_mul(K2);
+	}
+	K2
+}
+
+fn city_len17to32(s: &[u8]) -> u64 {
+	let len = s.len();
+	let a = fetch64(s, 0).wrapping_mul(K1);
+	let b = fetch64(s, 8);
+	let c = fetch64(s, len - 8).wrapping_mul(K2);
+	let d = fetch64(s, len - 16).wrapping_mul(K0);
+	hash128_to_64(
+		a.wrapping_sub(b).rotate_right(43).wrapping_add(c.rotate_right(30)).wrapping_add(d),
+		a.wrapping_add((b ^ K3).rotate_right(20)).wrapping_sub(c).wrapping_add(len as u64),
+	)
+}
+
+fn city_len33to64(s: &[u8]) -> u64 {
+	let len = s.len();
+	let mut z = fetch64(s, 24);
+	let mut a = fetch64(s, 0).wrapping_add((len as u64).wrapping_add(fetch64(s, len - 16)).wrapping_mul(K0));
+	let mut b = a.wrapping_add(z).rotate_right(52);
+	let mut c = a.rotate_right(37);
+	a = a.wrapping_add(fetch64(s, 8));
+	c = c.wrapping_add(a.rotate_right(7));
+	a = a.wrapping_add(fetch64(s, 16));
+	let vf = a.wrapping_add(z);
+	let vs = b.wrapping_add(a.rotate_right(31)).wrapping_add(c);
+	a = fetch64(s, 16).wrapping_add(fetch64(s, len - 32));
+	z = fetch64(s, len - 8);
+	b = a.wrapping_add(z).rotate_right(52);
+	c = a.rotate_right(37);
+	a = a.wrapping_add(fetch64(s, len - 24));
+	c = c.wrapping_add(a.rotate_right(7));
+	a = a.wrapping_add(fetch64(s, len - 16));
+	let wf = a.wrapping_add(z);
+	let ws = b.wrapping_add(a.rotate_right(31)).wrapping_add(c);
+	let r = shift_mix(vf.wrapping_add(ws).wrapping_mul(K2).wrapping_add(wf.wrapping_add(vs).wrapping_mul(K0)));
+	shift_mix(r.wrapping_mul(K0).wrapping_add(vs)).wrapping_mul(K2)
+}
+
+fn weak_hash_len32_with_seeds(s: &[u8], idx: usize, mut a: u64, mut b: u64) -> (u64, u64) {
+	let (w, x, y, z) = (fetch64(s, idx), fetch64(s, idx + 8), fetch64(s, idx + 16), fetch64(s, idx + 24));
+	a = a.wrapping_add(w);
+	b = b.wrapping_add(a).wrapping_add(z).rotate_right(21);
+	let c = a;
+	a = a.wrapping_add(x).wrapping_add(y);
+	b = b.wrapping_add(a.rotate_right(44));
+	(a.wrapping_add(z), b.wrapping_add(c))
+}
+
+/// `CityHash64` of CityHash v1.0.2, later versions of CityHash give different results.
+pub fn city_hash64(s: &[u8]) -> u64 {
+	let len = s.len();
+	match len {
+		0..=16 => return city_len0to16(s),
+		17..=32 => return city_len17to32(s),
+		33..=64 => return city_len33to64(s),
+		_ => (),
+	}
+
+	let mut x = fetch64(s, 0);
+	let mut y = fetch64(s, len - 16) ^ K1;
+	let mut z = fetch64(s, len - 56) ^ K0;
+	let mut v = weak_hash_len32_with_seeds(s, len - 64, len as u64, y);
+	let mut w = weak_hash_len32_with_seeds(s, len - 32, (len as u64).wrapping_mul(K1), K0);
+	z = z.wrapping_add(shift_mix(v.1).wrapping_mul(K1));
+	x = z.wrapping_add(x).rotate_right(39).wrapping_mul(K1);
+	y = y.rotate_right(33).wrapping_mul(K1);
+
+	// 64-byte chunks of the data rounded down.
+	for idx in (0..(len - 1) & !63).step_by(64) {
+		x = x.wrapping_add(y).wrapping_add(v.0).wrapping_add(fetch64(s, idx + 16)).rotate_right(37).wrapping_mul(K1);
+		y = y.wrapping_add(v.1).wrapping_add(fetch64(s, idx + 48)).rotate_right(42).wrapping_mul(K1);
+		x ^= w.1;
+		y ^= v.0;
+		z = (z ^ w.0).rotate_right(33);
+		v = weak_hash_len32_with_seeds(s, idx, v.1.wrapping_mul(K1), x.wrapping_add(w.0));
+		w = weak_hash_len32_with_seeds(s, idx + 32, z.wrapping_add(w.1), y);
+		std::mem::swap(&mut z, &mut x);
+	}
+	hash128_to_64(
+		hash128_to_64(v.0, w.0).wrapping_add(shift_mix(y).wrapping_mul(K1)).wrapping_add(z),
+		hash128_to_64(v.1, w.1).wrapping_add(x),
+	)
+}
+
+const P1: u64 = 11400714785074694791;
+const P2: u64 = 14029467366897019727;
+const P3: u64 = 1609587929392839161;
+const P4: u64 = 9650029242287828579;
+const P5: u64 = 2870177450012600261;
+
+fn xx_round(acc: u64, input: u64) -> u64 {
+	acc.wrapping_add(input.wrapping_mul(P2)).rotate_left(31).wrapping_mul(P1)
+}
+
+fn xx_merge(acc: u64, v: u64) -> u64 {
+	(acc ^ xx_round(0, v)).wrapping_mul(P1).wrapping_add(P4)
+}
+
+/// `XXH64` with seed 0.
+pub fn xx_hash64(s: &[u8]) -> u64 {
+	let len = s.len();
+	let mut idx = 0;
+	let mut h = if len >= 32 {
+		let mut v = [P1.wrapping_add(P2), P2, 0, 0u64.wrapping_sub(P1)];
+		while idx + 32 <= len {
+			for (lane, v) in v.iter_mut().enumerate() {
+				*v = xx_round(*v, fetch64(s, idx + lane * 8));
+			}
+			idx += 32;
+		}
+		let h = v[0].rotate_left(1).wrapping_add(v[1].rotate_left(7));
+		let h = h.wrapping_add(v[2].rotate_left(12)).wrapping_add(v[3].rotate_left(18));
+		v.into_iter().fold(h, xx_merge)
+	} else {
+		P5
+	};
+
+	h = h.wrapping_add(len as u64);
+	while idx + 8 <= len {
+		h ^= xx_round(0, fetch64(s, idx));
+		h = h.rotate_left(27).wrapping_mul(P1).wrapping_add(P4);
+		idx += 8;
+	}
+	if idx + 4 <= len {
+		h ^= fetch32(s, idx).wrapping_mul(P1);
+		h = h.rotate_left(23).wrapping_mul(P2).wrapping_add(P3);
+		idx += 4;
+	}
+	for &byte in &s[idx..] {
+		h ^= (byte as u64).wrapping_mul(P5);
+		h = h.rotate_left(11).wrapping_mul(P1);
+	}
+	h ^= h >> 33;
+	h = h.wrapping_mul(P2);
+	h ^= h >> 29;
+	h = h.wrapping_mul(P3);
+	h ^ (h >> 32)
+}
+
+/// `MurmurHash3_x64_128`, returns both halves.
+pub fn murmur_hash3_x64_128(s: &[u8], seed: u64) -> (u64, u64) {
+	const C1: u64 = 0x87c37b91114253d5;
+	const C2: u64 = 0x4cf5ad432745937f;
+	let mix_k1 = |k1: u64| k1.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2);
+	let mix_k2 = |k2: u64| k2.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1);
+
+	let (mut h1, mut h2) = (seed, seed);
+	let blocks = s.len() / 16;
+	for idx in (0..blocks * 16).step_by(16) {
+		h1 ^= mix_k1(fetch64(s, idx));
+		h1 = h1.rotate_left(27).wrapping_add(h2).wrapping_mul(5).wrapping_add(0x52dce729);
+		h2 ^= mix_k2(fetch64(s, idx + 8));
+		h2 = h2.rotate_left(31).wrapping_add(h1).wrapping_mul(5).wrapping_add(0x38495ab5);
+	}
+
+	let tail = &s[blocks * 16..];
+	let word = |bytes: &[u8]| bytes.iter().rev().fold(0u64, |word, &byte| (word << 8) | byte as u64);
+	if tail.len() > 8 {
+		h2 ^= mix_k2(word(&tail[8..]));
+	}
+	if !tail.is_empty() {
+		h1 ^= mix_k1(word(&tail[..tail.len().min(8)]));
+	}
+
+	h1 ^= s.len() as u64;
+	h2 ^= s.len() as u64;
+	h1 = h1.wrapping_add(h2);
+	h2 = h2.wrapping_add(h1);
+	h1 = int_hash64(h1);
+	h2 = int_hash64(h2);
+	h1 = h1.wrapping_add(h2);
+	h2 = h2.wrapping_add(h1);
+	(h1, h2)
+}
+
+/// `murmurHash3_64` of server.
+pub fn murmur_hash3_64(s: &[u8]) -> u64 {
+	let (h1, h2) = murmur_hash3_x64_128(s, 0);
+	h1 ^ h2
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_hash_bytes() {
+		assert_eq!(0x9ae16a3b2f90404f, city_hash64(b""));
+		assert_eq!(0xef46db3751d8e999, xx_hash64(b""));
+		assert_eq!(0x44bc2cf5ad770999, xx_hash64(b"abc"));
+		assert_eq!(17691043854468224118, xx_hash64(b"Hello, world!"));
+		assert_eq!((0xcbd8a7b341bd9b02, 0x5b1e906a48ae1d19), murmur_hash3_x64_128(b"hello", 0));
+		assert_eq!(
+			(0xe34bbc7bbc071b6c, 0x7a433ca9c49a9347),
+			murmur_hash3_x64_128(b"The quick brown fox jumps over the lazy dog", 0)
+		);
+		assert_eq!(0, murmur_hash3_64(b""));
+
+		// every length branch of CityHash, the values are of CityHash64 in city.cc of CityHash v1.0.2
+		// that ClickHouse bundles as contrib/cityhash102, built from the copy in the
+		// clickhouse-rs-cityhash-sys crate.
+		let data = (0..300).map(|i| (i as u8).wrapping_mul(31)).collect::<Vec<_>>();
+		let expected = [
+			(1, 0x085f654e398e757c),
+			(3, 0x6309227b2eb365e6),
+			(4, 0x70abafcce49706e2),
+			(8, 0x3730aa4ebf50916d),
+			(9, 0x549e8ce66e000003),
+			(16, 0x2ce96162bb72f455),
+			(17, 0xc25ded72aac18bc6),
+			(32, 0xaf9cc6c1912b899e),
+			(33, 0xa73ec0f30f1e4d72),
+			(64, 0xfc9e7aafa2b18c10),
+			(65, 0xdfa8b86aeec901b6),
+			(128, 0x6dafea74800696a0),
+			(300, 0x66da622299353076),
+		];
+		for (len, hash) in expected {
+			assert_eq!(hash, city_hash64(&data[..len]), "{}", len);
+		}
+
+		// inputs longer than a stripe of xxHash64 and a block of MurmurHash3, the values are of the
+		// xxhash-rust and murmur3 crates, ports of the xxHash and MurmurHash3 that ClickHouse links.
+		let expected = [
+			(32, 0xba7bafd4734262dd, 0xb8afe8676571b38d),
+			(33, 0x791cbe857e7fa007, 0xc4d7fbfe8a608398),
+			(65, 0xf514ecccaeda9b5f, 0x801213dfc95e6e8e),
+			(300, 0xa72b9833eeec30be, 0x70112b2ce351e0a5),
+		];
+		for (len, xx, murmur) in expected {
+			assert_eq!((xx, murmur), (xx_hash64(&data[..len]), murmur_hash3_64(&data[..len])), "{}", len);
+		}
+		assert_eq!((0x294242b26570044b, 0x5953699e8621e4ee), murmur_hash3_x64_128(&data, 0));
+	}
+
+	#[test]
+	fn test_hash_values() {
+		use crate::metadata::Tz;
+		use crate::values::decimal::Decimal;
+
+		// SELECT cityHash64(array('e','x','a'), 'mple', 10, toDateTime('2019-06-15 23:00:00',
+		// 'Europe/Moscow'))
+		let array = Value::Array(vec!["e".into(), "x".into(), "a".into()]);
+		let tz = "Europe/Moscow".parse::<Tz>().unwrap();
+		let datetime = Value::DateTime(1560628800, Some(tz.clone()));
+		let args = [
+			(&array, &DataType::Array(DataType::String.into())),
+			(&"mple".into(), &DataType::String),
+			(&Value::UInt8(10), &DataType::UInt8),
+			(&datetime, &DataType::DateTime(Some(tz))),
+		];
+		assert_eq!(12072650598913549138, HashFunction::CityHash64.hash_values(&args).unwrap());
+		// FixedString is hashed by its bytes like String.
+		let fixed_string = DataType::FixedString(4);
+		let mut fixed_args = args;
+		fixed_args[1].1 = &fixed_string;
+		assert_eq!(12072650598913549138, HashFunction::CityHash64.hash_values(&fixed_args).unwrap());
+
+		let tuple = Value::Tuple(vec!["mple".into(), Value::UInt8(10)]);
+		let tuple_type = DataType::Tuple(vec![("".to_owned(), DataType::String), ("".to_owned(), DataType::UInt8)]);
+		for function in [HashFunction::CityHash64, HashFunction::XxHash64, HashFunction::MurmurHash3_64] {
+			let flat = function.hash_values(&args[1..3]).unwrap();
+			assert_eq!(flat, function.hash_values(&[(&tuple, &tuple_type)]).unwrap());
+			let low_cardinality = DataType::LowCardinality(DataType::String.into());
+			assert_eq!(
+				function.hash_bytes(b"mple"),
+				function.hash_values(&[(&"mple".into(), &low_cardinality)]).unwrap()
+			);
+			assert_eq!(Some(function), HashFunction::from_name(function.name()));
+		}
+		assert_eq!(xx_hash64(&[10]), HashFunction::XxHash64.hash_values(&args[2..3]).unwrap());
+		assert_eq!(EMPTY_ARGUMENTS, HashFunction::CityHash64.hash_values(&[]).unwrap());
+
+		// values of 8 bytes or shorter are hashed by their bytes unless they're native numbers or dates.
+		let cases = [
+			(
+				Value::Decimal(Decimal::I32(12345i32.to_le_bytes()), 2),
+				DataType::Decimal32(2),
+				12345i32.to_le_bytes().to_vec(),
+			),
+			(
+				Value::Decimal(Decimal::I64((-12345i64).to_le_bytes()), 4),
+				DataType::Decimal64(4),
+				(-12345i64).to_le_bytes().to_vec(),
+			),
+			(
+				Value::DateTime64(1560628800123, 3, None),
+				DataType::DateTime64(3, None),
+				1560628800123i64.to_le_bytes().to_vec(),
+			),
+		];
+		for (value, data_type, bytes) in &cases {
+			assert_eq!(city_hash64(bytes), HashFunction::CityHash64.hash_values(&[(value, data_type)]).unwrap());
+		}
+		assert_eq!(
+			int_hash64_impl(1560628800),
+			HashFunction::CityHash64
+				.hash_values(&[(&Value::DateTime(1560628800, None), &DataType::DateTime(None))])
+				.unwrap()
+		);
+		assert!(HashFunction::CityHash64
+			.hash_values(&[(&Value::Null, &DataType::Nullable(DataType::Int8.into()))])
+			.is_err());
+	}
+}
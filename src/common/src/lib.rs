@@ -1,8 +1,11 @@
 #![allow(dead_code)]
 
 mod error;
+pub mod hash;
 pub mod metadata;
+pub mod rowbinary;
 mod serde;
+pub mod sql;
 pub mod values;
 
 pub use error::Error;
@@ -6,6 +6,7 @@ use crate::metadata::data_type::DataType;
 pub type RowSpec = Vec<ColumnSpec>;
 
 /// Column specification for data.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ColumnSpec {
 	col_name: String,
 	col_type: DataType,
@@ -20,6 +21,18 @@ impl ColumnSpec {
 	pub fn is_nullable(&self) -> bool {
 		self.col_type.is_nullable()
 	}
+
+	pub fn col_name(&self) -> &str {
+		&self.col_name
+	}
+
+	pub fn col_type(&self) -> &DataType {
+		&self.col_type
+	}
+
+	pub fn original_type(&self) -> &str {
+		&self.original_type
+	}
 }
 
 #[cfg(test)]
@@ -1,3 +1,5 @@
+use std::fmt::Display;
+use std::fmt::Formatter;
 use std::str::FromStr;
 
 use crate::error::Error;
@@ -7,6 +9,7 @@ use super::type_util::parse_agg_func;
 use super::type_util::parse_enum;
 use super::type_util::parse_map;
 use super::type_util::parse_tuple;
+use super::type_util::parse_variant;
 
 /// data types.
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
@@ -50,12 +53,39 @@ pub enum DataType {
 	Array(Box<DataType>),
 	Map(Box<DataType>, Box<DataType>),
 	Nullable(Box<DataType>),
+	/// type of `NULL` literal, e.g. `Nullable(Nothing)`.
+	Nothing,
+	/// one of the types, types are in the order of server, they are sorted by name.
+	Variant(Vec<DataType>),
+	/// value of any type, and max types stored as separate subcolumns.
+	Dynamic(Option<u8>),
 }
 
 impl DataType {
 	pub fn is_nullable(&self) -> bool {
 		matches!(self, DataType::Nullable(_))
 	}
+
+	/// set timezone of DateTime and DateTime64 without timezone, e.g. to the timezone of server.
+	pub fn with_timezone(&self, tz: &Tz) -> DataType {
+		let with_tz = |data_type: &DataType| Box::new(data_type.with_timezone(tz));
+		match self {
+			DataType::DateTime(None) => DataType::DateTime(Some(tz.clone())),
+			DataType::DateTime64(precision, None) => DataType::DateTime64(*precision, Some(tz.clone())),
+			DataType::LowCardinality(inner) => DataType::LowCardinality(with_tz(inner)),
+			DataType::Nullable(inner) => DataType::Nullable(with_tz(inner)),
+			DataType::Array(inner) => DataType::Array(with_tz(inner)),
+			DataType::Map(key, value) => DataType::Map(with_tz(key), with_tz(value)),
+			DataType::Tuple(fields) => {
+				DataType::Tuple(fields.iter().map(|(name, field)| (name.clone(), field.with_timezone(tz))).collect())
+			}
+			DataType::Variant(types) => DataType::Variant(types.iter().map(|t| t.with_timezone(tz)).collect()),
+			DataType::SimpleAggregateFunction(func, types) => {
+				DataType::SimpleAggregateFunction(func.clone(), types.iter().map(|t| t.with_timezone(tz)).collect())
+			}
+			data_type => data_type.clone(),
+		}
+	}
 }
 
 impl FromStr for DataType {
@@ -97,7 +127,7 @@ impl FromStr for DataType {
 			// dateTime.
 			"DateTime" => DataType::DateTime(None),
 			s if s.starts_with("DateTime(") => DataType::DateTime(match s[9..(s.len() - 1)].trim() {
-				tz if tz.is_empty() => None,
+				"" => None,
 				tz => Some(tz.replace('\'', "").parse()?),
 			}),
 			s if s.starts_with("DateTime64") => {
@@ -125,7 +155,15 @@ impl FromStr for DataType {
 				let pair = parse_map(&s[4..(s.len() - 1)])?;
 				DataType::Map(Box::new(pair.0), Box::new(pair.1))
 			}
-			"Json" => DataType::Json,
+			"Json" | "JSON" => DataType::Json,
+			"Nothing" => DataType::Nothing,
+			"Dynamic" => DataType::Dynamic(None),
+			s if s.starts_with("Dynamic(") => {
+				let max_types =
+					s[8..(s.len() - 1)].trim().trim_start_matches("max_types").trim().trim_start_matches('=');
+				DataType::Dynamic(Some(max_types.trim().parse()?))
+			}
+			s if s.starts_with("Variant(") => DataType::Variant(parse_variant(&s[8..(s.len() - 1)])?),
 			s if s.starts_with("Tuple") => DataType::Tuple(parse_tuple(&s[6..(s.len() - 1)])?),
 			s if s.starts_with("Enum8") => DataType::Enum8(parse_enum(&s[6..(s.len() - 1)])?),
 			s if s.starts_with("Enum16") => DataType::Enum16(parse_enum(&s[7..(s.len() - 1)])?),
@@ -142,6 +180,99 @@ impl FromStr for DataType {
 	}
 }
 
+/// format data type like ClickHouse, e.g. `Array(Nullable(DateTime64(3, 'UTC')))`.
+impl Display for DataType {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			DataType::Bool => f.write_str("Bool"),
+			DataType::Int8 => f.write_str("Int8"),
+			DataType::Int16 => f.write_str("Int16"),
+			DataType::Int32 => f.write_str("Int32"),
+			DataType::Int64 => f.write_str("Int64"),
+			DataType::Int128 => f.write_str("Int128"),
+			DataType::Int256 => f.write_str("Int256"),
+			DataType::UInt8 => f.write_str("UInt8"),
+			DataType::UInt16 => f.write_str("UInt16"),
+			DataType::UInt32 => f.write_str("UInt32"),
+			DataType::UInt64 => f.write_str("UInt64"),
+			DataType::UInt128 => f.write_str("UInt128"),
+			DataType::UInt256 => f.write_str("UInt256"),
+			DataType::Float32 => f.write_str("Float32"),
+			DataType::Float64 => f.write_str("Float64"),
+			DataType::String => f.write_str("String"),
+			DataType::FixedString(size) => write!(f, "FixedString({})", size),
+			DataType::Ipv4 => f.write_str("IPv4"),
+			DataType::Ipv6 => f.write_str("IPv6"),
+			DataType::Uuid => f.write_str("UUID"),
+			DataType::Date => f.write_str("Date"),
+			DataType::Date32 => f.write_str("Date32"),
+			DataType::DateTime(None) => f.write_str("DateTime"),
+			DataType::DateTime(Some(tz)) => write!(f, "DateTime('{}')", tz),
+			DataType::DateTime64(precision, None) => write!(f, "DateTime64({})", precision),
+			DataType::DateTime64(precision, Some(tz)) => write!(f, "DateTime64({}, '{}')", precision, tz),
+			DataType::Decimal(precision, scale) => write!(f, "Decimal({}, {})", precision, scale),
+			DataType::Decimal32(scale) => write!(f, "Decimal32({})", scale),
+			DataType::Decimal64(scale) => write!(f, "Decimal64({})", scale),
+			DataType::Decimal128(scale) => write!(f, "Decimal128({})", scale),
+			DataType::Decimal256(scale) => write!(f, "Decimal256({})", scale),
+			DataType::Enum8(variants) => fmt_enum(f, "Enum8", variants),
+			DataType::Enum16(variants) => fmt_enum(f, "Enum16", variants),
+			DataType::LowCardinality(inner) => write!(f, "LowCardinality({})", inner),
+			DataType::AggregateFunction(func, types) => fmt_agg_func(f, "AggregateFunction", func, types),
+			DataType::SimpleAggregateFunction(func, types) => fmt_agg_func(f, "SimpleAggregateFunction", func, types),
+			DataType::Json => f.write_str("JSON"),
+			DataType::Tuple(fields) => {
+				f.write_str("Tuple(")?;
+				for (idx, (name, data_type)) in fields.iter().enumerate() {
+					if idx > 0 {
+						f.write_str(", ")?;
+					}
+					if !name.is_empty() {
+						write!(f, "{} ", name)?;
+					}
+					write!(f, "{}", data_type)?;
+				}
+				f.write_str(")")
+			}
+			DataType::Array(inner) => write!(f, "Array({})", inner),
+			DataType::Map(key, value) => write!(f, "Map({}, {})", key, value),
+			DataType::Nullable(inner) => write!(f, "Nullable({})", inner),
+			DataType::Nothing => f.write_str("Nothing"),
+			DataType::Variant(types) => {
+				f.write_str("Variant(")?;
+				for (idx, data_type) in types.iter().enumerate() {
+					if idx > 0 {
+						f.write_str(", ")?;
+					}
+					write!(f, "{}", data_type)?;
+				}
+				f.write_str(")")
+			}
+			DataType::Dynamic(None) => f.write_str("Dynamic"),
+			DataType::Dynamic(Some(max_types)) => write!(f, "Dynamic(max_types={})", max_types),
+		}
+	}
+}
+
+fn fmt_enum<T: Display>(f: &mut Formatter<'_>, name: &str, variants: &[(String, T)]) -> std::fmt::Result {
+	write!(f, "{}(", name)?;
+	for (idx, (variant, num)) in variants.iter().enumerate() {
+		if idx > 0 {
+			f.write_str(", ")?;
+		}
+		write!(f, "'{}' = {}", variant.replace('\\', "\\\\").replace('\'', "\\'"), num)?;
+	}
+	f.write_str(")")
+}
+
+fn fmt_agg_func(f: &mut Formatter<'_>, name: &str, func: &AggFunc, types: &[DataType]) -> std::fmt::Result {
+	write!(f, "{}({}", name, func)?;
+	for data_type in types {
+		write!(f, ", {}", data_type)?;
+	}
+	f.write_str(")")
+}
+
 /// Aggregate function type.
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum AggFunc {
@@ -186,10 +317,61 @@ impl FromStr for AggFunc {
 	}
 }
 
+impl Display for AggFunc {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		f.write_str(match self {
+			AggFunc::Any => "any",
+			AggFunc::AnyLast => "anyLast",
+			AggFunc::Min => "min",
+			AggFunc::Max => "max",
+			AggFunc::Sum => "sum",
+			AggFunc::SumWithOverflow => "sumWithOverflow",
+			AggFunc::GroupBitAnd => "groupBitAnd",
+			AggFunc::GroupBitOr => "groupBitOr",
+			AggFunc::GroupBitXor => "groupBitXor",
+			AggFunc::GroupArrayArray => "groupArrayArray",
+			AggFunc::GroupUniqArrayArray => "groupUniqArrayArray",
+			AggFunc::SumMap => "sumMap",
+			AggFunc::MinMap => "minMap",
+			AggFunc::MaxMap => "maxMap",
+			AggFunc::FuncName(name) => name,
+		})
+	}
+}
+
 /// timezone of DateTime.
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct Tz(chrono_tz::Tz);
 
+impl Tz {
+	pub fn new(tz: chrono_tz::Tz) -> Self {
+		Tz(tz)
+	}
+
+	pub fn inner(&self) -> chrono_tz::Tz {
+		self.0
+	}
+}
+
+impl Display for Tz {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		f.write_str(self.0.name())
+	}
+}
+
+impl serde::Serialize for Tz {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+		serializer.serialize_str(self.0.name())
+	}
+}
+
+impl<'de> serde::Deserialize<'de> for Tz {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+		let name = String::deserialize(deserializer)?;
+		name.parse().map_err(serde::de::Error::custom)
+	}
+}
+
 /// convert string to timezone.
 impl FromStr for Tz {
 	type Err = crate::error::Error;
@@ -272,4 +454,37 @@ mod tests {
 			DataType::SimpleAggregateFunction(AggFunc::Sum, vec![DataType::Float64])
 		);
 	}
+
+	#[test]
+	fn test_display() {
+		for s in [
+			"FixedString(16)",
+			"Decimal(9, 3)",
+			"DateTime64(3, 'Asia/Istanbul')",
+			"Array(Nullable(DateTime('UTC')))",
+			"Map(LowCardinality(String), Array(Decimal64(18)))",
+			"Tuple(Array(String), s Map(String, Int64))",
+			"Enum8('hello' = 1, 'world' = 2)",
+			"SimpleAggregateFunction(sum, Float64)",
+			"Nullable(Nothing)",
+			"Variant(Array(String), UInt64)",
+			"Dynamic",
+			"Dynamic(max_types=8)",
+		] {
+			assert_eq!(s, s.parse::<DataType>().unwrap().to_string());
+		}
+
+		let variant = "Variant(UInt64, String, Array(UInt8), Date)".parse::<DataType>().unwrap();
+		assert_eq!("Variant(Array(UInt8), Date, String, UInt64)", variant.to_string());
+	}
+
+	#[test]
+	fn test_with_timezone() {
+		let tz = "Asia/Shanghai".parse().unwrap();
+		let data_type = "Tuple(a Nullable(DateTime), b Array(DateTime64(3)), c DateTime('UTC'))".parse::<DataType>();
+		assert_eq!(
+			"Tuple(a Nullable(DateTime('Asia/Shanghai')), b Array(DateTime64(3, 'Asia/Shanghai')), c DateTime('UTC'))",
+			data_type.unwrap().with_timezone(&tz).to_string()
+		);
+	}
 }
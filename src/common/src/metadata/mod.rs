@@ -1,8 +1,21 @@
 mod column;
 mod data_type;
+mod row;
+mod schema;
+mod type_codec;
 mod type_util;
 
+pub use column::ColumnSpec;
+pub use column::RowSpec;
+pub use data_type::AggFunc;
 pub use data_type::DataType;
+pub use data_type::Tz;
+pub use row::Row;
+pub use schema::check_schema;
+pub use schema::ColumnType;
+pub use schema::RowSchema;
+pub use schema::SchemaColumn;
+pub use schema::Strictness;
 
 pub type Metadata = Vec<(String, DataType)>;
 
@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::metadata::DataType;
+use crate::metadata::Metadata;
+use crate::serde::value::RowDeserializer;
+use crate::values::convert::FromValue;
+use crate::values::value::Value;
+
+/// A row of dynamic values, columns can be accessed by name or index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+	metadata: Arc<Metadata>,
+	values: Vec<Value>,
+}
+
+impl Row {
+	/// create row of values of columns, the number of values must match metadata.
+	pub fn new(metadata: Arc<Metadata>, values: Vec<Value>) -> Result<Row> {
+		if metadata.len() != values.len() {
+			return Err(Error::ConvertError(format!("{} values for {} columns", values.len(), metadata.len())));
+		}
+		Ok(Row { metadata, values })
+	}
+
+	pub fn metadata(&self) -> &Metadata {
+		&self.metadata
+	}
+
+	pub fn values(&self) -> &[Value] {
+		&self.values
+	}
+
+	pub fn into_values(self) -> Vec<Value> {
+		self.values
+	}
+
+	pub fn len(&self) -> usize {
+		self.values.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.values.is_empty()
+	}
+
+	/// index of column, return none if column not exists.
+	pub fn index_of(&self, name: &str) -> Option<usize> {
+		self.metadata.iter().position(|(col_name, _)| col_name == name)
+	}
+
+	/// get value by column name, return none if column not exists or type can't be converted.
+	pub fn get<T: FromValue>(&self, name: &str) -> Option<T> {
+		self.try_get(name).ok()
+	}
+
+	/// get value by column index, return none if index out of bounds or type can't be converted.
+	pub fn get_by_index<T: FromValue>(&self, index: usize) -> Option<T> {
+		self.try_get_by_index(index).ok()
+	}
+
+	pub fn try_get<T: FromValue>(&self, name: &str) -> Result<T> {
+		let index = self.index_of(name).ok_or_else(|| Error::ColumnNotFound(name.to_owned()))?;
+		self.try_get_by_index(index)
+	}
+
+	pub fn try_get_by_index<T: FromValue>(&self, index: usize) -> Result<T> {
+		let value = self.values.get(index).ok_or_else(|| Error::ColumnNotFound(format!("index {}", index)))?;
+		T::from_value(value).map_err(|err| match err {
+			Error::ConvertError(msg) => Error::ConvertError(format!("column `{}`: {}", self.metadata[index].0, msg)),
+			err => err,
+		})
+	}
+
+	/// iterate over column name, type and value.
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &DataType, &Value)> {
+		self.metadata.iter().zip(self.values.iter()).map(|((name, data_type), value)| (name.as_str(), data_type, value))
+	}
+
+	/// deserialize row to struct by column names, or to tuple and sequence by column order.
+	pub fn deserialize_into<T: DeserializeOwned>(&self) -> Result<T> {
+		T::deserialize(RowDeserializer(self))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use bytes::Bytes;
+	use serde::Deserialize;
+
+	use crate::metadata::DataType;
+	use crate::metadata::Row;
+	use crate::serde::buf::PartBuf;
+	use crate::serde::de::deserialize_row;
+	use crate::values::value::Value;
+
+	#[derive(Deserialize, Debug, PartialEq)]
+	struct Event {
+		id: u64,
+		name: Option<String>,
+		tags: Vec<String>,
+		day: String,
+	}
+
+	#[test]
+	fn test_row() {
+		let metadata = Arc::new(vec![
+			("id".to_owned(), DataType::UInt64),
+			("name".to_owned(), DataType::Nullable(DataType::String.into())),
+			("tags".to_owned(), DataType::Array(DataType::LowCardinality(DataType::String.into()).into())),
+			("day".to_owned(), DataType::Date),
+			("extra".to_owned(), DataType::Int8),
+		]);
+		let mut buf = PartBuf::new();
+		buf.push_back(Bytes::from_static(&[7, 0, 0, 0, 0, 0, 0, 0]));
+		buf.push_back(Bytes::from_static(&[0, 2, b'h', b'i']));
+		buf.push_back(Bytes::from_static(&[2, 1, b'a', 1, b'b']));
+		buf.push_back(Bytes::from_static(&[1, 0, 0xff]));
+		let row = deserialize_row(&mut buf, &metadata).unwrap();
+
+		assert_eq!(Some(7u64), row.get("id"));
+		assert_eq!(Some(7i32), row.get_by_index(0));
+		assert_eq!(Some(Some("hi".to_owned())), row.get::<Option<String>>("name"));
+		assert_eq!(None, row.get::<u8>("missing"));
+		assert!(row.try_get::<u8>("extra").unwrap_err().to_string().contains("column `extra`"));
+		assert_eq!(
+			vec![("day", &DataType::Date, &Value::Date(1))],
+			row.iter().filter(|(name, ..)| *name == "day").collect::<Vec<_>>()
+		);
+		assert_eq!(
+			Event {
+				id: 7,
+				name: Some("hi".to_owned()),
+				tags: vec!["a".to_owned(), "b".to_owned()],
+				day: "1970-01-02".to_owned()
+			},
+			row.deserialize_into::<Event>().unwrap()
+		);
+		assert_eq!(
+			(7u64, Some("hi".to_owned()), vec!["a".to_owned(), "b".to_owned()], 1u16, -1i8),
+			row.deserialize_into::<(u64, Option<String>, Vec<String>, u16, i8)>().unwrap()
+		);
+
+		let values = row.values()[..4].to_vec();
+		assert_eq!(
+			"Convert value error: 4 values for 5 columns",
+			Row::new(metadata.clone(), values).unwrap_err().to_string()
+		);
+		assert_eq!(row, Row::new(metadata, row.values().to_vec()).unwrap());
+	}
+}
@@ -0,0 +1,399 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::metadata::DataType;
+use crate::metadata::Metadata;
+use crate::sql::quote_identifier;
+
+/// Expected column of a rust type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaColumn {
+	pub name: String,
+	pub data_type: DataType,
+	/// column may be absent, default value is used.
+	pub default: bool,
+}
+
+/// Columns of a rust type, usually implemented by `#[derive(Row)]`.
+pub trait RowSchema {
+	fn columns() -> Vec<SchemaColumn>;
+
+	/// column names used by SELECT and INSERT.
+	fn column_names() -> Vec<String> {
+		Self::columns().into_iter().map(|column| column.name).collect()
+	}
+
+	/// quoted column list like `` `id`,`name` ``.
+	fn column_list() -> String {
+		Self::columns().iter().map(|column| quote_identifier(&column.name)).collect::<Vec<_>>().join(",")
+	}
+
+	/// check columns sent by server, all incompatible columns are reported at once.
+	fn check(metadata: &Metadata, strictness: Strictness) -> Result<()> {
+		check_schema(&Self::columns(), metadata, strictness)
+	}
+}
+
+/// Strictness of schema check, each level allows what the previous levels allow.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Strictness {
+	/// types must be the same, timezone is ignored if not expected and unknown columns are
+	/// rejected.
+	#[default]
+	Exact,
+	/// server type can be narrower, e.g. `Int32` for `Int64` or `LowCardinality(String)` for
+	/// `String`.
+	AllowWidening,
+	/// server type can be `Nullable(T)` for `T`, decoding fails if NULL is actually sent.
+	AllowNullable,
+}
+
+/// check columns sent by server against expected columns, unnamed columns of scalars and tuples
+/// are matched by position.
+pub fn check_schema(columns: &[SchemaColumn], metadata: &Metadata, strictness: Strictness) -> Result<()> {
+	let mut errors = Vec::new();
+	if columns.iter().all(|column| column.name.is_empty()) {
+		if columns.len() != metadata.len() {
+			errors.push(format!("expected {} columns, server sent {}", columns.len(), metadata.len()));
+		}
+		for (idx, (column, (name, actual))) in columns.iter().zip(metadata.iter()).enumerate() {
+			if !is_compatible(&column.data_type, actual, strictness) {
+				errors
+					.push(format!("column {} `{}`: expected {}, server sent {}", idx, name, column.data_type, actual));
+			}
+		}
+		return match errors.is_empty() {
+			true => Ok(()),
+			false => Err(Error::SchemaMismatch(errors)),
+		};
+	}
+
+	for column in columns {
+		match metadata.iter().find(|(name, _)| *name == column.name) {
+			Some((_, actual)) if !is_compatible(&column.data_type, actual, strictness) => {
+				errors.push(format!("column `{}`: expected {}, server sent {}", column.name, column.data_type, actual))
+			}
+			None if !column.default => errors.push(format!("column `{}`: not sent by server", column.name)),
+			_ => (),
+		}
+	}
+
+	if strictness == Strictness::Exact {
+		for (name, actual) in metadata.iter().filter(|(name, _)| columns.iter().all(|c| c.name != *name)) {
+			errors.push(format!("column `{}`: unexpected column of type {}", name, actual));
+		}
+	}
+
+	match errors.is_empty() {
+		true => Ok(()),
+		false => Err(Error::SchemaMismatch(errors)),
+	}
+}
+
+fn is_compatible(expected: &DataType, actual: &DataType, strictness: Strictness) -> bool {
+	let widening = strictness >= Strictness::AllowWidening;
+	match (expected, actual) {
+		_ if expected == actual => true,
+		(DataType::DateTime(None), DataType::DateTime(_)) => true,
+		(DataType::DateTime64(p1, None), DataType::DateTime64(p2, _)) => p1 == p2,
+		(DataType::Nullable(e), DataType::Nullable(a))
+		| (DataType::LowCardinality(e), DataType::LowCardinality(a))
+		| (DataType::Array(e), DataType::Array(a)) => is_compatible(e, a, strictness),
+		(DataType::Map(ek, ev), DataType::Map(ak, av)) => {
+			is_compatible(ek, ak, strictness) && is_compatible(ev, av, strictness)
+		}
+		(DataType::Tuple(e), DataType::Tuple(a)) => {
+			e.len() == a.len() && e.iter().zip(a).all(|((_, e), (_, a))| is_compatible(e, a, strictness))
+		}
+		(e, DataType::Nullable(a)) if strictness >= Strictness::AllowNullable => is_compatible(e, a, strictness),
+		(e, DataType::LowCardinality(a)) if widening => is_compatible(e, a, strictness),
+		(DataType::LowCardinality(e), a) if widening => is_compatible(e, a, strictness),
+		(DataType::Float64, DataType::Float32) | (DataType::Date32, DataType::Date) => widening,
+		(e, a) => match (decimal_of(e), decimal_of(a), int_of(e), int_of(a)) {
+			(Some((ep, es)), Some((ap, as_)), ..) => es == as_ && (ep == ap || (widening && ep > ap)),
+			(.., Some((e_signed, e_bits)), Some((a_signed, a_bits))) => {
+				widening && (a_bits < e_bits && (e_signed || !a_signed) || a_bits == e_bits && e_signed == a_signed)
+			}
+			_ => false,
+		},
+	}
+}
+
+/// precision and scale of decimal.
+fn decimal_of(data_type: &DataType) -> Option<(u8, u8)> {
+	match *data_type {
+		DataType::Decimal(precision, scale) => Some((precision, scale)),
+		DataType::Decimal32(scale) => Some((9, scale)),
+		DataType::Decimal64(scale) => Some((18, scale)),
+		DataType::Decimal128(scale) => Some((38, scale)),
+		DataType::Decimal256(scale) => Some((76, scale)),
+		_ => None,
+	}
+}
+
+/// signedness and bits of integer.
+fn int_of(data_type: &DataType) -> Option<(bool, u16)> {
+	match data_type {
+		DataType::Int8 => Some((true, 8)),
+		DataType::Int16 => Some((true, 16)),
+		DataType::Int32 => Some((true, 32)),
+		DataType::Int64 => Some((true, 64)),
+		DataType::Int128 => Some((true, 128)),
+		DataType::Int256 => Some((true, 256)),
+		DataType::UInt8 => Some((false, 8)),
+		DataType::UInt16 => Some((false, 16)),
+		DataType::UInt32 => Some((false, 32)),
+		DataType::UInt64 => Some((false, 64)),
+		DataType::UInt128 => Some((false, 128)),
+		DataType::UInt256 => Some((false, 256)),
+		_ => None,
+	}
+}
+
+/// ClickHouse type of a rust type.
+pub trait ColumnType {
+	fn data_type() -> DataType;
+}
+
+/// column of a scalar or tuple row, e.g. `fetch_all::<(String, u64)>()`.
+fn unnamed<T: ColumnType>() -> SchemaColumn {
+	SchemaColumn { name: String::new(), data_type: T::data_type(), default: false }
+}
+
+macro_rules! impl_column_type {
+	($($ty: ty => $data_type: expr),* $(,)?) => {
+		$(
+			impl ColumnType for $ty {
+				fn data_type() -> DataType {
+					$data_type
+				}
+			}
+
+			impl RowSchema for $ty {
+				fn columns() -> Vec<SchemaColumn> {
+					vec![unnamed::<Self>()]
+				}
+			}
+		)*
+	};
+}
+
+impl_column_type!(
+	bool => DataType::Bool,
+	i8 => DataType::Int8,
+	i16 => DataType::Int16,
+	i32 => DataType::Int32,
+	i64 => DataType::Int64,
+	i128 => DataType::Int128,
+	u8 => DataType::UInt8,
+	u16 => DataType::UInt16,
+	u32 => DataType::UInt32,
+	u64 => DataType::UInt64,
+	u128 => DataType::UInt128,
+	f32 => DataType::Float32,
+	f64 => DataType::Float64,
+	String => DataType::String,
+	Ipv4Addr => DataType::Ipv4,
+	Ipv6Addr => DataType::Ipv6,
+	chrono::NaiveDate => DataType::Date,
+	chrono::DateTime<chrono::Utc> => DataType::DateTime(None),
+	crate::values::ip::IpV4 => DataType::Ipv4,
+	crate::values::ip::IpV6 => DataType::Ipv6,
+);
+
+#[cfg(feature = "bigint")]
+impl_column_type!(
+	crate::values::bigint::i256 => DataType::Int256,
+	crate::values::bigint::u256 => DataType::UInt256,
+);
+
+#[cfg(feature = "uuid")]
+impl_column_type!(
+	uuid::Uuid => DataType::Uuid,
+	crate::values::uuid::Uuid => DataType::Uuid,
+);
+
+impl<const N: usize> ColumnType for [u8; N] {
+	fn data_type() -> DataType {
+		DataType::FixedString(N)
+	}
+}
+
+impl<const N: usize> RowSchema for [u8; N] {
+	fn columns() -> Vec<SchemaColumn> {
+		vec![unnamed::<Self>()]
+	}
+}
+
+impl<T: ColumnType> ColumnType for Option<T> {
+	fn data_type() -> DataType {
+		DataType::Nullable(T::data_type().into())
+	}
+}
+
+impl<T: ColumnType> RowSchema for Option<T> {
+	fn columns() -> Vec<SchemaColumn> {
+		vec![unnamed::<Self>()]
+	}
+}
+
+impl<T: ColumnType> ColumnType for Vec<T> {
+	fn data_type() -> DataType {
+		DataType::Array(T::data_type().into())
+	}
+}
+
+impl<T: ColumnType> RowSchema for Vec<T> {
+	fn columns() -> Vec<SchemaColumn> {
+		vec![unnamed::<Self>()]
+	}
+}
+
+impl<K: ColumnType, V: ColumnType> ColumnType for HashMap<K, V> {
+	fn data_type() -> DataType {
+		DataType::Map(K::data_type().into(), V::data_type().into())
+	}
+}
+
+impl<K: ColumnType, V: ColumnType> RowSchema for HashMap<K, V> {
+	fn columns() -> Vec<SchemaColumn> {
+		vec![unnamed::<Self>()]
+	}
+}
+
+impl<K: ColumnType, V: ColumnType> ColumnType for BTreeMap<K, V> {
+	fn data_type() -> DataType {
+		DataType::Map(K::data_type().into(), V::data_type().into())
+	}
+}
+
+impl<K: ColumnType, V: ColumnType> RowSchema for BTreeMap<K, V> {
+	fn columns() -> Vec<SchemaColumn> {
+		vec![unnamed::<Self>()]
+	}
+}
+
+macro_rules! impl_column_type_tuple {
+	($($name: ident),+) => {
+		impl<$($name: ColumnType),+> ColumnType for ($($name,)+) {
+			fn data_type() -> DataType {
+				DataType::Tuple(vec![$(("".to_owned(), $name::data_type())),+])
+			}
+		}
+
+		/// columns of tuple row are its fields rather than a `Tuple` column.
+		impl<$($name: ColumnType),+> RowSchema for ($($name,)+) {
+			fn columns() -> Vec<SchemaColumn> {
+				vec![$(unnamed::<$name>()),+]
+			}
+		}
+	};
+}
+
+impl_column_type_tuple!(A);
+impl_column_type_tuple!(A, B);
+impl_column_type_tuple!(A, B, C);
+impl_column_type_tuple!(A, B, C, D);
+impl_column_type_tuple!(A, B, C, D, E);
+impl_column_type_tuple!(A, B, C, D, E, F);
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeMap;
+
+	use rickhouse_derive::Row;
+
+	use super::RowSchema;
+	use super::SchemaColumn;
+	use super::Strictness;
+	use crate::metadata::DataType;
+
+	#[derive(Row)]
+	#[row(crate = "crate")]
+	struct Base {
+		#[row(rename = "ts", type = "DateTime64(3)")]
+		timestamp: i64,
+		#[row(default)]
+		version: u32,
+	}
+
+	#[derive(Row)]
+	#[row(crate = "crate")]
+	struct Event {
+		id: u64,
+		#[row(type = "LowCardinality(String)")]
+		kind: String,
+		tags: Vec<Option<String>>,
+		attrs: BTreeMap<String, (u8, f64)>,
+		#[row(skip)]
+		cache: Vec<u8>,
+		#[row(flatten)]
+		base: Base,
+	}
+
+	#[test]
+	fn test_derive_row() {
+		let column =
+			|name: &str, data_type: DataType, default: bool| SchemaColumn { name: name.to_owned(), data_type, default };
+		assert_eq!(
+			vec![
+				column("id", DataType::UInt64, false),
+				column("kind", DataType::LowCardinality(DataType::String.into()), false),
+				column("tags", DataType::Array(DataType::Nullable(DataType::String.into()).into()), false),
+				column(
+					"attrs",
+					DataType::Map(
+						DataType::String.into(),
+						DataType::Tuple(vec![("".to_owned(), DataType::UInt8), ("".to_owned(), DataType::Float64)])
+							.into()
+					),
+					false
+				),
+				column("ts", DataType::DateTime64(3, None), false),
+				column("version", DataType::UInt32, true),
+			],
+			Event::columns()
+		);
+		assert_eq!("`id`,`kind`,`tags`,`attrs`,`ts`,`version`", Event::column_list());
+	}
+
+	#[test]
+	fn test_check() {
+		let metadata = |types: &[(&str, &str)]| {
+			types.iter().map(|(name, ty)| (name.to_string(), ty.parse::<DataType>().unwrap())).collect::<Vec<_>>()
+		};
+
+		let exact = metadata(&[("ts", "DateTime64(3, 'UTC')"), ("version", "UInt32")]);
+		assert!(Base::check(&exact, Strictness::Exact).is_ok());
+		assert!(Base::check(&metadata(&[("ts", "DateTime64(3)")]), Strictness::Exact).is_ok());
+
+		let err = Base::check(&metadata(&[("ts", "DateTime"), ("extra", "String")]), Strictness::Exact).unwrap_err();
+		assert_eq!(
+			"Schema mismatch: column `ts`: expected DateTime64(3), server sent DateTime; column `extra`: unexpected \
+			 column of type String",
+			err.to_string()
+		);
+
+		let narrow = metadata(&[("ts", "DateTime64(3)"), ("version", "LowCardinality(Nullable(UInt16))")]);
+		assert!(Base::check(&narrow, Strictness::Exact).is_err());
+		assert!(Base::check(&narrow, Strictness::AllowWidening).is_err());
+		assert!(Base::check(&narrow, Strictness::AllowNullable).is_ok());
+		assert!(Base::check(&metadata(&[("ts", "DateTime64(3)"), ("version", "Int32")]), Strictness::AllowNullable)
+			.is_err());
+
+		// columns of tuples and scalars are matched by position.
+		let status = metadata(&[("status", "LowCardinality(String)"), ("exception", "String")]);
+		assert!(<(String, String)>::check(&status, Strictness::AllowWidening).is_ok());
+		let err = <(String, u64, u8)>::check(&status, Strictness::AllowWidening).unwrap_err();
+		assert_eq!(
+			"Schema mismatch: expected 3 columns, server sent 2; column 1 `exception`: expected UInt64, server sent \
+			 String",
+			err.to_string()
+		);
+		assert!(u8::check(&metadata(&[("1", "UInt8")]), Strictness::Exact).is_ok());
+	}
+}
@@ -0,0 +1,389 @@
+//! Binary encoding of data types, it's used by headers with
+//! `output_format_binary_encode_types_in_binary_format` and by values of `Dynamic`.
+
+use bytes::Buf;
+use bytes::BufMut;
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::metadata::AggFunc;
+use crate::metadata::DataType;
+use crate::serde::buf::BufExp;
+
+const NOTHING: u8 = 0x00;
+const UINT8: u8 = 0x01;
+const UINT16: u8 = 0x02;
+const UINT32: u8 = 0x03;
+const UINT64: u8 = 0x04;
+const UINT128: u8 = 0x05;
+const UINT256: u8 = 0x06;
+const INT8: u8 = 0x07;
+const INT16: u8 = 0x08;
+const INT32: u8 = 0x09;
+const INT64: u8 = 0x0A;
+const INT128: u8 = 0x0B;
+const INT256: u8 = 0x0C;
+const FLOAT32: u8 = 0x0D;
+const FLOAT64: u8 = 0x0E;
+const DATE: u8 = 0x0F;
+const DATE32: u8 = 0x10;
+const DATETIME: u8 = 0x11;
+const DATETIME_WITH_TZ: u8 = 0x12;
+const DATETIME64: u8 = 0x13;
+const DATETIME64_WITH_TZ: u8 = 0x14;
+const STRING: u8 = 0x15;
+const FIXED_STRING: u8 = 0x16;
+const ENUM8: u8 = 0x17;
+const ENUM16: u8 = 0x18;
+const DECIMAL32: u8 = 0x19;
+const DECIMAL64: u8 = 0x1A;
+const DECIMAL128: u8 = 0x1B;
+const DECIMAL256: u8 = 0x1C;
+const UUID: u8 = 0x1D;
+const ARRAY: u8 = 0x1E;
+const TUPLE: u8 = 0x1F;
+const NAMED_TUPLE: u8 = 0x20;
+const SET: u8 = 0x21;
+const INTERVAL: u8 = 0x22;
+const NULLABLE: u8 = 0x23;
+const FUNCTION: u8 = 0x24;
+const AGGREGATE_FUNCTION: u8 = 0x25;
+const LOW_CARDINALITY: u8 = 0x26;
+const MAP: u8 = 0x27;
+const IPV4: u8 = 0x28;
+const IPV6: u8 = 0x29;
+const VARIANT: u8 = 0x2A;
+const DYNAMIC: u8 = 0x2B;
+const CUSTOM: u8 = 0x2C;
+const BOOL: u8 = 0x2D;
+const SIMPLE_AGGREGATE_FUNCTION: u8 = 0x2E;
+const NESTED: u8 = 0x2F;
+const JSON: u8 = 0x30;
+
+/// default `max_types` of `Dynamic`.
+const DEFAULT_DYNAMIC_MAX_TYPES: u8 = 32;
+/// default `max_dynamic_paths` of `JSON`.
+const DEFAULT_JSON_MAX_DYNAMIC_PATHS: i64 = 1024;
+
+impl DataType {
+	/// read binary encoded data type.
+	pub fn read_binary<B: Buf>(buf: &mut B) -> Result<DataType> {
+		let unsupported = |name: &str| Err(Error::ParseTypeError(format!("binary encoded {} is unsupported", name)));
+		let id = buf.read_arr::<1>()?[0];
+		Ok(match id {
+			NOTHING => DataType::Nothing,
+			UINT8 => DataType::UInt8,
+			UINT16 => DataType::UInt16,
+			UINT32 => DataType::UInt32,
+			UINT64 => DataType::UInt64,
+			UINT128 => DataType::UInt128,
+			UINT256 => DataType::UInt256,
+			INT8 => DataType::Int8,
+			INT16 => DataType::Int16,
+			INT32 => DataType::Int32,
+			INT64 => DataType::Int64,
+			INT128 => DataType::Int128,
+			INT256 => DataType::Int256,
+			FLOAT32 => DataType::Float32,
+			FLOAT64 => DataType::Float64,
+			DATE => DataType::Date,
+			DATE32 => DataType::Date32,
+			DATETIME => DataType::DateTime(None),
+			DATETIME_WITH_TZ => DataType::DateTime(Some(buf.read_utf8_string()?.parse()?)),
+			DATETIME64 => DataType::DateTime64(buf.read_arr::<1>()?[0], None),
+			DATETIME64_WITH_TZ => {
+				let precision = buf.read_arr::<1>()?[0];
+				DataType::DateTime64(precision, Some(buf.read_utf8_string()?.parse()?))
+			}
+			STRING => DataType::String,
+			FIXED_STRING => DataType::FixedString(buf.read_size()?),
+			ENUM8 => DataType::Enum8(read_enum(buf, |buf| Ok(i8::from_le_bytes(buf.read_arr()?)))?),
+			ENUM16 => DataType::Enum16(read_enum(buf, |buf| Ok(i16::from_le_bytes(buf.read_arr()?)))?),
+			DECIMAL32 | DECIMAL64 | DECIMAL128 | DECIMAL256 => {
+				let [precision, scale] = buf.read_arr::<2>()?;
+				DataType::Decimal(precision, scale)
+			}
+			UUID => DataType::Uuid,
+			ARRAY => DataType::Array(Box::new(DataType::read_binary(buf)?)),
+			TUPLE => {
+				let len = buf.read_size()?;
+				DataType::Tuple(
+					(0..len).map(|_| Ok((String::new(), DataType::read_binary(buf)?))).collect::<Result<_>>()?,
+				)
+			}
+			NAMED_TUPLE => {
+				let len = buf.read_size()?;
+				DataType::Tuple(
+					(0..len)
+						.map(|_| Ok((buf.read_utf8_string()?, DataType::read_binary(buf)?)))
+						.collect::<Result<_>>()?,
+				)
+			}
+			NULLABLE => DataType::Nullable(Box::new(DataType::read_binary(buf)?)),
+			AGGREGATE_FUNCTION => {
+				let _version = buf.read_size()?;
+				let (func, types) = read_agg_func(buf)?;
+				DataType::AggregateFunction(func, types)
+			}
+			SIMPLE_AGGREGATE_FUNCTION => {
+				let (func, types) = read_agg_func(buf)?;
+				DataType::SimpleAggregateFunction(func, types)
+			}
+			LOW_CARDINALITY => DataType::LowCardinality(Box::new(DataType::read_binary(buf)?)),
+			MAP => DataType::Map(Box::new(DataType::read_binary(buf)?), Box::new(DataType::read_binary(buf)?)),
+			IPV4 => DataType::Ipv4,
+			IPV6 => DataType::Ipv6,
+			VARIANT => {
+				let len = buf.read_size()?;
+				DataType::Variant((0..len).map(|_| DataType::read_binary(buf)).collect::<Result<_>>()?)
+			}
+			DYNAMIC => match buf.read_arr::<1>()?[0] {
+				DEFAULT_DYNAMIC_MAX_TYPES => DataType::Dynamic(None),
+				max_types => DataType::Dynamic(Some(max_types)),
+			},
+			BOOL => DataType::Bool,
+			JSON => {
+				// typed paths and skipped paths are not kept, JSON is read as string.
+				let _version = buf.read_arr::<1>()?;
+				let _max_dynamic_paths = buf.read_u64_leb128()?;
+				let _max_dynamic_types = buf.read_arr::<1>()?;
+				for _ in 0..buf.read_size()? {
+					buf.read_utf8_string()?;
+					DataType::read_binary(buf)?;
+				}
+				for _ in 0..buf.read_size()? {
+					buf.read_utf8_string()?;
+				}
+				for _ in 0..buf.read_size()? {
+					buf.read_utf8_string()?;
+				}
+				DataType::Json
+			}
+			SET => unsupported("Set")?,
+			INTERVAL => unsupported("Interval")?,
+			FUNCTION => unsupported("Function")?,
+			CUSTOM => unsupported(&format!("custom type {}", buf.read_utf8_string()?))?,
+			NESTED => unsupported("Nested")?,
+			id => unsupported(&format!("type id 0x{:02X}", id))?,
+		})
+	}
+
+	/// write binary encoded data type.
+	pub fn write_binary<B: BufMut>(&self, buf: &mut B) {
+		match self {
+			DataType::Nothing => buf.put_u8(NOTHING),
+			DataType::Bool => buf.put_u8(BOOL),
+			DataType::Int8 => buf.put_u8(INT8),
+			DataType::Int16 => buf.put_u8(INT16),
+			DataType::Int32 => buf.put_u8(INT32),
+			DataType::Int64 => buf.put_u8(INT64),
+			DataType::Int128 => buf.put_u8(INT128),
+			DataType::Int256 => buf.put_u8(INT256),
+			DataType::UInt8 => buf.put_u8(UINT8),
+			DataType::UInt16 => buf.put_u8(UINT16),
+			DataType::UInt32 => buf.put_u8(UINT32),
+			DataType::UInt64 => buf.put_u8(UINT64),
+			DataType::UInt128 => buf.put_u8(UINT128),
+			DataType::UInt256 => buf.put_u8(UINT256),
+			DataType::Float32 => buf.put_u8(FLOAT32),
+			DataType::Float64 => buf.put_u8(FLOAT64),
+			DataType::String => buf.put_u8(STRING),
+			DataType::FixedString(size) => {
+				buf.put_u8(FIXED_STRING);
+				put_size(buf, *size);
+			}
+			DataType::Ipv4 => buf.put_u8(IPV4),
+			DataType::Ipv6 => buf.put_u8(IPV6),
+			DataType::Uuid => buf.put_u8(UUID),
+			DataType::Date => buf.put_u8(DATE),
+			DataType::Date32 => buf.put_u8(DATE32),
+			DataType::DateTime(None) => buf.put_u8(DATETIME),
+			DataType::DateTime(Some(tz)) => {
+				buf.put_u8(DATETIME_WITH_TZ);
+				put_str(buf, &tz.to_string());
+			}
+			DataType::DateTime64(precision, None) => {
+				buf.put_u8(DATETIME64);
+				buf.put_u8(*precision);
+			}
+			DataType::DateTime64(precision, Some(tz)) => {
+				buf.put_u8(DATETIME64_WITH_TZ);
+				buf.put_u8(*precision);
+				put_str(buf, &tz.to_string());
+			}
+			DataType::Decimal(precision, scale) => {
+				buf.put_u8(match precision {
+					0..=9 => DECIMAL32,
+					10..=18 => DECIMAL64,
+					19..=38 => DECIMAL128,
+					_ => DECIMAL256,
+				});
+				buf.put_u8(*precision);
+				buf.put_u8(*scale);
+			}
+			DataType::Decimal32(scale) => put_decimal(buf, DECIMAL32, 9, *scale),
+			DataType::Decimal64(scale) => put_decimal(buf, DECIMAL64, 18, *scale),
+			DataType::Decimal128(scale) => put_decimal(buf, DECIMAL128, 38, *scale),
+			DataType::Decimal256(scale) => put_decimal(buf, DECIMAL256, 76, *scale),
+			DataType::Enum8(variants) => {
+				buf.put_u8(ENUM8);
+				put_size(buf, variants.len());
+				for (name, num) in variants {
+					put_str(buf, name);
+					buf.put_i8(*num);
+				}
+			}
+			DataType::Enum16(variants) => {
+				buf.put_u8(ENUM16);
+				put_size(buf, variants.len());
+				for (name, num) in variants {
+					put_str(buf, name);
+					buf.put_i16_le(*num);
+				}
+			}
+			DataType::LowCardinality(inner) => {
+				buf.put_u8(LOW_CARDINALITY);
+				inner.write_binary(buf);
+			}
+			DataType::AggregateFunction(func, types) => {
+				buf.put_u8(AGGREGATE_FUNCTION);
+				put_size(buf, 0);
+				put_agg_func(buf, func, types);
+			}
+			DataType::SimpleAggregateFunction(func, types) => {
+				buf.put_u8(SIMPLE_AGGREGATE_FUNCTION);
+				put_agg_func(buf, func, types);
+			}
+			DataType::Json => {
+				buf.put_u8(JSON);
+				buf.put_u8(0);
+				// max_dynamic_paths is zigzag encoded.
+				put_size(buf, (DEFAULT_JSON_MAX_DYNAMIC_PATHS << 1) as usize);
+				buf.put_u8(DEFAULT_DYNAMIC_MAX_TYPES);
+				put_size(buf, 0);
+				put_size(buf, 0);
+				put_size(buf, 0);
+			}
+			DataType::Tuple(fields) if fields.iter().all(|(name, _)| !name.is_empty()) && !fields.is_empty() => {
+				buf.put_u8(NAMED_TUPLE);
+				put_size(buf, fields.len());
+				for (name, data_type) in fields {
+					put_str(buf, name);
+					data_type.write_binary(buf);
+				}
+			}
+			DataType::Tuple(fields) => {
+				buf.put_u8(TUPLE);
+				put_size(buf, fields.len());
+				fields.iter().for_each(|(_, data_type)| data_type.write_binary(buf));
+			}
+			DataType::Array(inner) => {
+				buf.put_u8(ARRAY);
+				inner.write_binary(buf);
+			}
+			DataType::Map(key, value) => {
+				buf.put_u8(MAP);
+				key.write_binary(buf);
+				value.write_binary(buf);
+			}
+			DataType::Nullable(inner) => {
+				buf.put_u8(NULLABLE);
+				inner.write_binary(buf);
+			}
+			DataType::Variant(types) => {
+				buf.put_u8(VARIANT);
+				put_size(buf, types.len());
+				types.iter().for_each(|data_type| data_type.write_binary(buf));
+			}
+			DataType::Dynamic(max_types) => {
+				buf.put_u8(DYNAMIC);
+				buf.put_u8(max_types.unwrap_or(DEFAULT_DYNAMIC_MAX_TYPES));
+			}
+		}
+	}
+}
+
+fn read_enum<B: Buf, T>(buf: &mut B, read_num: impl Fn(&mut B) -> Result<T>) -> Result<Vec<(String, T)>> {
+	let len = buf.read_size()?;
+	(0..len).map(|_| Ok((buf.read_utf8_string()?, read_num(buf)?))).collect()
+}
+
+/// read `name, parameters, argument types` of aggregate function, parameters are unsupported.
+fn read_agg_func<B: Buf>(buf: &mut B) -> Result<(AggFunc, Vec<DataType>)> {
+	let name = buf.read_utf8_string()?;
+	if buf.read_size()? > 0 {
+		return Err(Error::ParseTypeError(format!("parameters of aggregate function {} are unsupported", name)));
+	}
+	let len = buf.read_size()?;
+	Ok((name.parse()?, (0..len).map(|_| DataType::read_binary(buf)).collect::<Result<_>>()?))
+}
+
+fn put_agg_func<B: BufMut>(buf: &mut B, func: &AggFunc, types: &[DataType]) {
+	put_str(buf, &func.to_string());
+	put_size(buf, 0);
+	put_size(buf, types.len());
+	types.iter().for_each(|data_type| data_type.write_binary(buf));
+}
+
+fn put_decimal<B: BufMut>(buf: &mut B, id: u8, precision: u8, scale: u8) {
+	buf.put_u8(id);
+	buf.put_u8(precision);
+	buf.put_u8(scale);
+}
+
+fn put_size<B: BufMut>(buf: &mut B, size: usize) {
+	crate::serde::ser::put_size(buf, size)
+}
+
+fn put_str<B: BufMut>(buf: &mut B, s: &str) {
+	put_size(buf, s.len());
+	buf.put_slice(s.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::metadata::DataType;
+
+	fn round_trip(data_type: &DataType) -> DataType {
+		let mut buf = Vec::new();
+		data_type.write_binary(&mut buf);
+		let mut slice = &buf[..];
+		let decoded = DataType::read_binary(&mut slice).unwrap();
+		assert!(slice.is_empty(), "{}", data_type);
+		decoded
+	}
+
+	#[test]
+	fn test_binary_type() {
+		for s in [
+			"Bool",
+			"Int256",
+			"FixedString(16)",
+			"Decimal(40, 3)",
+			"DateTime64(3, 'Asia/Istanbul')",
+			"Array(Nullable(DateTime('UTC')))",
+			"Map(LowCardinality(String), Array(Decimal(18, 2)))",
+			"Tuple(a Array(String), b Map(String, Int64))",
+			"Tuple(String, UInt8)",
+			"Enum8('hello' = 1, 'world' = 2)",
+			"Enum16('a' = -1000)",
+			"AggregateFunction(uniq, UInt64)",
+			"SimpleAggregateFunction(sum, Float64)",
+			"Nullable(Nothing)",
+			"Variant(Array(String), IPv6, UInt64, UUID)",
+			"Dynamic",
+			"Dynamic(max_types=8)",
+			"JSON",
+		] {
+			let data_type = s.parse::<DataType>().unwrap();
+			assert_eq!(data_type, round_trip(&data_type));
+		}
+
+		assert_eq!(DataType::Decimal(9, 2), round_trip(&DataType::Decimal32(2)));
+
+		let mut buf = Vec::new();
+		"Array(Nullable(DateTime64(3, 'UTC')))".parse::<DataType>().unwrap().write_binary(&mut buf);
+		assert_eq!(vec![0x1E, 0x23, 0x14, 3, 3, b'U', b'T', b'C'], buf);
+		assert!(DataType::read_binary(&mut &[0x22u8, 0][..]).is_err());
+	}
+}
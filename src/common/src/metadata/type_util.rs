@@ -40,6 +40,14 @@ pub(crate) fn parse_tuple(inner: &str) -> Result<Vec<(String, DataType)>> {
 	})
 }
 
+/// parse variant like Variant(`UInt64, Array(String)`), types are sorted by name like server does,
+/// so discriminators are indexes of the sorted types.
+pub(crate) fn parse_variant(inner: &str) -> Result<Vec<DataType>> {
+	let mut types = split_to_fields(inner).iter().map(|field| field.parse()).collect::<Result<Vec<DataType>>>()?;
+	types.sort_by_cached_key(DataType::to_string);
+	Ok(types)
+}
+
 /// parse str: 'hello'=1,world=2
 pub(crate) fn parse_enum<T: FromStr<Err = ParseIntError>>(inner: &str) -> Result<Vec<(String, T)>> {
 	let vec =
@@ -92,11 +100,7 @@ fn is_balanced_brackets(s: &str) -> bool {
 	for c in s.chars() {
 		match c {
 			'(' => stack.push(c),
-			')' => {
-				if stack.pop().is_none() {
-					return false;
-				}
-			}
+			')' if stack.pop().is_none() => return false,
 			_ => (),
 		}
 	}
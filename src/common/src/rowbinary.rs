@@ -0,0 +1,175 @@
+//! Encoding and decoding of the RowBinary family of formats.
+
+use std::sync::Arc;
+
+use bytes::Buf;
+use bytes::BufMut;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::metadata::DataType;
+use crate::metadata::Metadata;
+use crate::metadata::Row;
+use crate::metadata::RowSchema;
+use crate::metadata::SchemaColumn;
+use crate::metadata::Strictness;
+use crate::serde::de;
+use crate::serde::ser;
+use crate::values::value::Value;
+use crate::Result;
+
+/// Options of RowBinary formats which depend on settings of the query.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FormatOptions {
+	/// types of header are encoded in binary, `output_format_binary_encode_types_in_binary_format`.
+	pub binary_types: bool,
+}
+
+/// read header of `RowBinaryWithNamesAndTypes`.
+pub fn read_header<B: Buf>(buf: &mut B) -> Result<Metadata> {
+	de::deserialize_header(buf)
+}
+
+/// read header of `RowBinaryWithNamesAndTypes` encoded with the options.
+pub fn read_header_with<B: Buf>(buf: &mut B, options: &FormatOptions) -> Result<Metadata> {
+	match options.binary_types {
+		true => de::deserialize_header_binary(buf),
+		false => de::deserialize_header(buf),
+	}
+}
+
+/// read header and check it against the schema of `T`.
+pub fn read_header_checked<T: RowSchema, B: Buf>(buf: &mut B, strictness: Strictness) -> Result<Metadata> {
+	de::deserialize_header_checked::<T, B>(buf, strictness)
+}
+
+/// read a row as dynamic values.
+pub fn read_row<B: Buf>(buf: &mut B, metadata: &Arc<Metadata>) -> Result<Row> {
+	de::deserialize_row(buf, metadata)
+}
+
+/// read a row into `T` by server types.
+pub fn read_row_into<T: DeserializeOwned, B: Buf>(buf: &mut B, metadata: &Arc<Metadata>) -> Result<T> {
+	de::deserialize_row_into(buf, metadata)
+}
+
+/// write header of `RowBinaryWithNamesAndTypes`.
+pub fn write_header<B: BufMut>(buf: &mut B, columns: &[SchemaColumn]) {
+	write_header_with(buf, columns, &FormatOptions::default())
+}
+
+/// write header of `RowBinaryWithNamesAndTypes` encoded with the options, binary encoded types
+/// are read by server with `input_format_binary_decode_types_in_binary_format`.
+pub fn write_header_with<B: BufMut>(buf: &mut B, columns: &[SchemaColumn], options: &FormatOptions) {
+	write_size(buf, columns.len());
+	for column in columns {
+		write_size(buf, column.name.len());
+		buf.put_slice(column.name.as_bytes());
+	}
+	for column in columns {
+		match options.binary_types {
+			true => column.data_type.write_binary(buf),
+			false => {
+				let data_type = column.data_type.to_string();
+				write_size(buf, data_type.len());
+				buf.put_slice(data_type.as_bytes());
+			}
+		}
+	}
+}
+
+/// write a row of struct, map or tuple, fields are encoded by column types in order.
+pub fn write_row<T: Serialize + ?Sized, B: BufMut>(buf: &mut B, row: &T, columns: &[SchemaColumn]) -> Result<()> {
+	ser::serialize_row(buf, row, columns)
+}
+
+/// write a value of the data type.
+pub fn write_value<B: BufMut>(buf: &mut B, value: &Value, data_type: &DataType) -> Result<()> {
+	ser::serialize_value(buf, value, data_type)
+}
+
+fn write_size<B: BufMut>(buf: &mut B, size: usize) {
+	ser::put_size(buf, size)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use serde::Deserialize;
+	use serde::Serialize;
+
+	use super::*;
+	use crate::metadata::RowSchema;
+
+	#[derive(Debug, PartialEq, Serialize, Deserialize, rickhouse_derive::Row)]
+	#[row(crate = "crate")]
+	struct Metric {
+		name: String,
+		#[row(type = "DateTime('UTC')")]
+		ts: String,
+		value: Option<f64>,
+	}
+
+	#[test]
+	fn test_round_trip() {
+		let columns = Metric::columns();
+		let metric = Metric { name: "cpu".to_owned(), ts: "2024-01-02 03:04:05".to_owned(), value: Some(0.5) };
+		let mut buf = Vec::new();
+		write_header(&mut buf, &columns);
+		write_row(&mut buf, &metric, &columns).unwrap();
+
+		let mut buf = buf.as_slice();
+		let metadata = Arc::new(read_header_checked::<Metric, _>(&mut buf, Strictness::Exact).unwrap());
+		let row = read_row(&mut buf, &metadata).unwrap();
+		assert_eq!("2024-01-02 03:04:05", row.get::<String>("ts").unwrap());
+		assert_eq!(Some(0.5), row.get::<Option<f64>>("value").unwrap());
+		assert!(!buf.has_remaining());
+	}
+
+	#[derive(Debug, PartialEq, Serialize, Deserialize, rickhouse_derive::Row)]
+	#[row(crate = "crate")]
+	struct Event {
+		id: u64,
+		#[row(type = "Variant(String, UInt64)")]
+		tag: String,
+		#[row(type = "Dynamic")]
+		extra: Option<f64>,
+	}
+
+	#[test]
+	fn test_binary_types() {
+		let columns = Event::columns();
+		let options = FormatOptions { binary_types: true };
+		let events =
+			[Event { id: 1, tag: "a".to_owned(), extra: Some(0.5) }, Event { id: 2, tag: "b".to_owned(), extra: None }];
+		let mut buf = Vec::new();
+		write_header_with(&mut buf, &columns, &options);
+		for event in &events {
+			write_row(&mut buf, event, &columns).unwrap();
+		}
+
+		let mut buf = buf.as_slice();
+		let metadata = Arc::new(read_header_with(&mut buf, &options).unwrap());
+		assert_eq!(columns.iter().map(|c| (c.name.clone(), c.data_type.clone())).collect::<Metadata>(), *metadata);
+		assert_eq!(events[0], read_row_into::<Event, _>(&mut buf, &metadata).unwrap());
+		assert_eq!(Value::Null, read_row(&mut buf, &metadata).unwrap().values()[2]);
+		assert!(!buf.has_remaining());
+
+		let dynamic = DataType::Dynamic(None);
+		let value = Value::Array(vec![Value::Null, Value::from(1u64)]);
+		let mut buf = Vec::new();
+		write_value(&mut buf, &value, &dynamic).unwrap();
+		assert_eq!(&[0x1E, 0x23, 0x04, 2, 1, 0][..], &buf[..6]);
+		assert_eq!(value, de::deserialize_value(&mut buf.as_slice(), &dynamic).unwrap());
+
+		// discriminators are indexes of types sorted by name, whatever the declaration order is.
+		let variant = "Variant(String, Date, Array(UInt8))".parse::<DataType>().unwrap();
+		for (value, discriminator) in [(Value::from("2024-01-31"), 2), (Value::Date(19753), 1), (Value::Null, 255)] {
+			let mut buf = Vec::new();
+			write_value(&mut buf, &value, &variant).unwrap();
+			assert_eq!(discriminator, buf[0]);
+			assert_eq!(value, de::deserialize_value(&mut buf.as_slice(), &variant).unwrap());
+		}
+	}
+}
@@ -64,15 +64,16 @@ impl<T: Buf> Buf for PartBuf<T> {
 pub(crate) trait BufExp: Buf {
 	fn ensure_size(&self, size: usize) -> Result<()>;
 	fn read_size(&mut self) -> Result<usize>;
+	fn read_arr<const N: usize>(&mut self) -> Result<[u8; N]>;
 	fn read_vec(&mut self, size: usize) -> Result<Vec<u8>>;
 	fn read_u64_leb128(&mut self) -> Result<u64>;
 	fn read_utf8_string(&mut self) -> Result<String>;
 }
 
-impl<T: Buf> BufExp for PartBuf<T> {
+impl<T: Buf + ?Sized> BufExp for T {
 	#[inline]
 	fn ensure_size(&self, size: usize) -> Result<()> {
-		match size >= self.remaining() {
+		match self.remaining() >= size {
 			true => Ok(()),
 			false => Err(crate::Error::NotEnoughData),
 		}
@@ -80,7 +81,15 @@ impl<T: Buf> BufExp for PartBuf<T> {
 
 	#[inline]
 	fn read_size(&mut self) -> Result<usize> {
-		 Ok(self.read_u64_leb128()?.try_into()?)
+		Ok(self.read_u64_leb128()?.try_into()?)
+	}
+
+	#[inline]
+	fn read_arr<const N: usize>(&mut self) -> Result<[u8; N]> {
+		self.ensure_size(N)?;
+		let mut arr = [0u8; N];
+		self.copy_to_slice(&mut arr[..]);
+		Ok(arr)
 	}
 
 	#[inline]
@@ -93,7 +102,8 @@ impl<T: Buf> BufExp for PartBuf<T> {
 
 	#[inline]
 	fn read_u64_leb128(&mut self) -> Result<u64> {
-		let mut arr = [0u8; 8];
+		// u64 takes 10 bytes at most in leb128.
+		let mut arr = [0u8; 10];
 
 		let mut idx = 0;
 		loop {
@@ -101,7 +111,7 @@ impl<T: Buf> BufExp for PartBuf<T> {
 			let byte = self.get_u8();
 			arr[idx] = byte;
 			idx += 1;
-			if byte & 0x80 == 0 {
+			if byte & 0x80 == 0 || idx == arr.len() {
 				break;
 			}
 		}
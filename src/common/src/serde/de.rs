@@ -1,14 +1,22 @@
+use std::collections::HashMap;
 use std::mem;
-use std::u8;
+use std::sync::Arc;
 
-use serde::de::IntoDeserializer;
+use serde::de::DeserializeOwned;
+use serde::de::DeserializeSeed;
+use serde::de::MapAccess;
+use serde::de::SeqAccess;
 use serde::Deserialize;
 use serde::Deserializer;
 
 use crate::metadata::DataType;
 use crate::metadata::Metadata;
 use crate::metadata::MetadataRef;
+use crate::metadata::Row;
+use crate::metadata::RowSchema;
+use crate::metadata::Strictness;
 use crate::serde::buf::BufExp;
+use crate::values::value::Value;
 use crate::Error;
 
 pub(crate) fn deserialize_header<B: BufExp>(buf: &mut B) -> crate::Result<Metadata> {
@@ -18,6 +26,24 @@ pub(crate) fn deserialize_header<B: BufExp>(buf: &mut B) -> crate::Result<Metada
 	Ok(names.into_iter().zip(types).collect())
 }
 
+/// deserialize header whose types are binary encoded.
+pub(crate) fn deserialize_header_binary<B: BufExp>(buf: &mut B) -> crate::Result<Metadata> {
+	let len = buf.read_u64_leb128()?;
+	let names = (0..len).map(|_| buf.read_utf8_string()).collect::<Result<Vec<_>, _>>()?;
+	let types = (0..len).map(|_| DataType::read_binary(buf)).collect::<Result<Vec<_>, _>>()?;
+	Ok(names.into_iter().zip(types).collect())
+}
+
+/// deserialize header and check it against the schema of `T` before decoding rows.
+pub(crate) fn deserialize_header_checked<T: RowSchema, B: BufExp>(
+	buf: &mut B,
+	strictness: Strictness,
+) -> crate::Result<Metadata> {
+	let metadata = deserialize_header(buf)?;
+	T::check(&metadata, strictness)?;
+	Ok(metadata)
+}
+
 pub(crate) fn deserialize_from<'de, T, B: BufExp>(data: B, metadata: &Metadata) -> crate::Result<T>
 where
 	T: Deserialize<'de>,
@@ -26,6 +52,140 @@ where
 	T::deserialize(&mut deserializer)
 }
 
+/// deserialize a row to dynamic values according to metadata.
+pub(crate) fn deserialize_row<B: BufExp>(buf: &mut B, metadata: &Arc<Metadata>) -> crate::Result<Row> {
+	let values = metadata.iter().map(|(_, data_type)| deserialize_value(buf, data_type)).collect::<Result<_, _>>()?;
+	Row::new(metadata.clone(), values)
+}
+
+/// deserialize a row to `T` by server types, so checked widening and nullable columns are read
+/// correctly.
+pub(crate) fn deserialize_row_into<T: DeserializeOwned, B: BufExp>(
+	buf: &mut B,
+	metadata: &Arc<Metadata>,
+) -> crate::Result<T> {
+	deserialize_row(buf, metadata)?.deserialize_into()
+}
+
+/// deserialize a value of the data type.
+pub(crate) fn deserialize_value<B: BufExp>(buf: &mut B, data_type: &DataType) -> crate::Result<Value> {
+	macro_rules! read_num {
+		($ty: ty, $variant: ident) => {
+			Value::$variant(<$ty>::from_le_bytes(buf.read_arr()?))
+		};
+	}
+
+	Ok(match data_type {
+		DataType::Bool => Value::Bool(buf.read_arr::<1>()?[0] != 0),
+		DataType::Int8 => read_num!(i8, Int8),
+		DataType::Int16 => read_num!(i16, Int16),
+		DataType::Int32 => read_num!(i32, Int32),
+		DataType::Int64 => read_num!(i64, Int64),
+		DataType::Int128 => read_num!(i128, Int128),
+		#[cfg(feature = "bigint")]
+		DataType::Int256 => Value::Int256(crate::values::bigint::i256(buf.read_arr()?)),
+		DataType::UInt8 => read_num!(u8, UInt8),
+		DataType::UInt16 => read_num!(u16, UInt16),
+		DataType::UInt32 => read_num!(u32, UInt32),
+		DataType::UInt64 => read_num!(u64, UInt64),
+		DataType::UInt128 => read_num!(u128, UInt128),
+		#[cfg(feature = "bigint")]
+		DataType::UInt256 => Value::UInt256(crate::values::bigint::u256(buf.read_arr()?)),
+		DataType::Float32 => read_num!(f32, Float32),
+		DataType::Float64 => read_num!(f64, Float64),
+		DataType::String => {
+			let size = buf.read_size()?;
+			Value::String(buf.read_vec(size)?)
+		}
+		DataType::FixedString(size) => Value::String(buf.read_vec(*size)?),
+		DataType::Ipv4 => Value::Ipv4(crate::values::ip::IpV4(buf.read_arr()?)),
+		DataType::Ipv6 => Value::Ipv6(crate::values::ip::IpV6(buf.read_arr()?)),
+		#[cfg(feature = "uuid")]
+		DataType::Uuid => Value::Uuid(crate::values::uuid::Uuid(buf.read_arr()?)),
+		DataType::Date => read_num!(u16, Date),
+		DataType::Date32 => read_num!(i32, Date32),
+		DataType::DateTime(tz) => Value::DateTime(u32::from_le_bytes(buf.read_arr()?), tz.clone()),
+		DataType::DateTime64(precision, tz) => {
+			Value::DateTime64(i64::from_le_bytes(buf.read_arr()?), *precision, tz.clone())
+		}
+		#[cfg(feature = "decimal")]
+		DataType::Decimal(precision, scale) => {
+			use crate::values::decimal::Decimal;
+			let decimal = match precision {
+				0..=9 => Decimal::I32(buf.read_arr()?),
+				10..=18 => Decimal::I64(buf.read_arr()?),
+				19..=38 => Decimal::I128(buf.read_arr()?),
+				_ => Decimal::I256(buf.read_arr()?),
+			};
+			Value::Decimal(decimal, *scale)
+		}
+		#[cfg(feature = "decimal")]
+		DataType::Decimal32(scale) => Value::Decimal(buf.read_arr::<4>()?.into(), *scale),
+		#[cfg(feature = "decimal")]
+		DataType::Decimal64(scale) => Value::Decimal(buf.read_arr::<8>()?.into(), *scale),
+		#[cfg(feature = "decimal")]
+		DataType::Decimal128(scale) => Value::Decimal(buf.read_arr::<16>()?.into(), *scale),
+		#[cfg(feature = "decimal")]
+		DataType::Decimal256(scale) => Value::Decimal(buf.read_arr::<32>()?.into(), *scale),
+		DataType::Enum8(variants) => {
+			let num = i8::from_le_bytes(buf.read_arr()?);
+			Value::Enum8(enum_name(variants, num), num)
+		}
+		DataType::Enum16(variants) => {
+			let num = i16::from_le_bytes(buf.read_arr()?);
+			Value::Enum16(enum_name(variants, num), num)
+		}
+		DataType::LowCardinality(inner) => deserialize_value(buf, inner)?,
+		DataType::SimpleAggregateFunction(_, types) if types.len() == 1 => deserialize_value(buf, &types[0])?,
+		#[cfg(feature = "json")]
+		DataType::Json => {
+			let size = buf.read_size()?;
+			Value::Json(crate::values::json::Json(buf.read_vec(size)?))
+		}
+		DataType::Tuple(fields) => {
+			Value::Tuple(fields.iter().map(|(_, field)| deserialize_value(buf, field)).collect::<Result<_, _>>()?)
+		}
+		DataType::Array(inner) => {
+			let size = buf.read_size()?;
+			Value::Array((0..size).map(|_| deserialize_value(buf, inner)).collect::<Result<_, _>>()?)
+		}
+		DataType::Map(key, value) => {
+			let size = buf.read_size()?;
+			let mut map = HashMap::with_capacity(size);
+			for _ in 0..size {
+				map.insert(deserialize_value(buf, key)?, deserialize_value(buf, value)?);
+			}
+			Value::Map(map)
+		}
+		DataType::Nullable(inner) => match buf.read_arr::<1>()?[0] {
+			0 => deserialize_value(buf, inner)?,
+			1 => Value::Null,
+			v => Err(Error::EncodingError(format!("invalid option symbol: {}", v)))?,
+		},
+		DataType::Nothing => Value::Null,
+		DataType::Variant(types) => match buf.read_arr::<1>()?[0] {
+			NULL_DISCRIMINATOR => Value::Null,
+			discriminator => match types.get(discriminator as usize) {
+				Some(data_type) => deserialize_value(buf, data_type)?,
+				None => Err(Error::EncodingError(format!("invalid variant discriminator: {}", discriminator)))?,
+			},
+		},
+		// value of dynamic is prefixed by its binary encoded type, `Nothing` for NULL.
+		DataType::Dynamic(_) => {
+			let data_type = DataType::read_binary(buf)?;
+			deserialize_value(buf, &data_type)?
+		}
+		data_type => Err(Error::EncodingError(format!("can't deserialize type {:?} to value", data_type)))?,
+	})
+}
+
+/// discriminator of NULL in `Variant`.
+pub(crate) const NULL_DISCRIMINATOR: u8 = 255;
+
+fn enum_name<T: PartialEq + ToString>(variants: &[(String, T)], num: T) -> String {
+	variants.iter().find(|(_, v)| *v == num).map(|(name, _)| name.clone()).unwrap_or_else(|| num.to_string())
+}
+
 pub(crate) struct RowBinaryDeserializer<'a, T> {
 	metadata: MetadataRef<'a>,
 	data: T,
@@ -41,10 +201,7 @@ impl<T: BufExp> RowBinaryDeserializer<'_, T> {
 	}
 
 	fn read_arr<const N: usize>(&mut self) -> crate::Result<[u8; N]> {
-		self.ensure_size(N)?;
-		let mut arr = [0u8; N];
-		self.data.copy_to_slice(&mut arr[..]);
-		Ok(arr)
+		self.data.read_arr()
 	}
 
 	fn read_vec(&mut self, size: usize) -> crate::Result<Vec<u8>> {
@@ -60,6 +217,44 @@ impl<T: BufExp> RowBinaryDeserializer<'_, T> {
 	}
 }
 
+/// elements of a sequence, or entries of a map whose `len` is the number of entries.
+struct Access<'a, 'b, T> {
+	de: &'a mut RowBinaryDeserializer<'b, T>,
+	len: usize,
+}
+
+impl<'de, T: BufExp> SeqAccess<'de> for Access<'_, '_, T> {
+	type Error = crate::Error;
+
+	fn next_element_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<Option<S::Value>, Self::Error> {
+		if self.len == 0 {
+			return Ok(None);
+		}
+		self.len -= 1;
+		seed.deserialize(&mut *self.de).map(Some)
+	}
+
+	fn size_hint(&self) -> Option<usize> {
+		Some(self.len)
+	}
+}
+
+impl<'de, T: BufExp> MapAccess<'de> for Access<'_, '_, T> {
+	type Error = crate::Error;
+
+	fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error> {
+		self.next_element_seed(seed)
+	}
+
+	fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value, Self::Error> {
+		seed.deserialize(&mut *self.de)
+	}
+
+	fn size_hint(&self) -> Option<usize> {
+		Some(self.len)
+	}
+}
+
 macro_rules! impl_deserialize_num {
 	($ty: ty, $de_fn: ident, $visitor_fn: ident, $reader_fn: ident) => {
 		fn $de_fn<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -168,70 +363,78 @@ impl<'de, T: BufExp> Deserializer<'de> for &mut RowBinaryDeserializer<'_, T> {
 		visitor.visit_unit()
 	}
 
-	fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+	fn deserialize_unit_struct<V>(self, _: &'static str, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		visitor.visit_unit()
 	}
 
-	fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+	fn deserialize_newtype_struct<V>(self, _: &'static str, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		visitor.visit_newtype_struct(self)
 	}
 
+	/// `Array` is prefixed by its length.
 	fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		let len = self.read_size()?;
+		visitor.visit_seq(Access { de: self, len })
 	}
 
+	/// elements of `Tuple` and bytes of `FixedString` aren't prefixed by length.
 	fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		visitor.visit_seq(Access { de: self, len })
 	}
 
-	fn deserialize_tuple_struct<V>(self, name: &'static str, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+	fn deserialize_tuple_struct<V>(self, _: &'static str, len: usize, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		self.deserialize_tuple(len, visitor)
 	}
 
+	/// `Map` is prefixed by the number of its entries.
 	fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		let len = self.read_size()?;
+		visitor.visit_map(Access { de: self, len })
 	}
 
+	/// fields of a row are in the order of columns.
 	fn deserialize_struct<V>(
 		self,
-		name: &'static str,
+		_: &'static str,
 		fields: &'static [&'static str],
 		visitor: V,
 	) -> Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		self.deserialize_tuple(fields.len(), visitor)
 	}
 
+	/// `Enum8` and `Enum16` can't be told apart without column types, rows with enums are read by
+	/// [`deserialize_row_into`].
 	fn deserialize_enum<V>(
 		self,
 		name: &'static str,
 		variants: &'static [&'static str],
-		visitor: V,
+		_: V,
 	) -> Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		visitor.visit_enum("".into_deserializer())
+		Err(Error::SerdeError(format!("enum {} of variants {:?} needs column types", name, variants)))
 	}
 
 	fn deserialize_identifier<V>(self, _: V) -> Result<V::Value, Self::Error>
@@ -252,3 +455,48 @@ impl<'de, T: BufExp> Deserializer<'de> for &mut RowBinaryDeserializer<'_, T> {
 		false
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeMap;
+
+	use serde::Deserialize;
+
+	use super::deserialize_from;
+	use crate::metadata::Metadata;
+
+	#[derive(Debug, PartialEq, Deserialize)]
+	struct Id(u32);
+
+	#[derive(Debug, PartialEq, Deserialize)]
+	enum Status {
+		New,
+	}
+
+	#[derive(Debug, PartialEq, Deserialize)]
+	struct Event {
+		id: Id,
+		name: Option<String>,
+		tags: Vec<String>,
+		attrs: BTreeMap<String, u8>,
+		code: [u8; 2],
+		point: (i16, f64),
+	}
+
+	#[test]
+	fn test_deserialize_from() {
+		let mut data = vec![7, 0, 0, 0, 0, 1, b'a', 2, 1, b'x', 2, b'y', b'z', 1, 1, b'k', 5, 1, 2, 0xff, 0xff];
+		data.extend(0.5f64.to_le_bytes());
+		let event = Event {
+			id: Id(7),
+			name: Some("a".to_owned()),
+			tags: vec!["x".to_owned(), "yz".to_owned()],
+			attrs: BTreeMap::from([("k".to_owned(), 5)]),
+			code: [1, 2],
+			point: (-1, 0.5),
+		};
+		assert_eq!(event, deserialize_from::<Event, _>(&data[..], &Metadata::new()).unwrap());
+		assert!(deserialize_from::<Event, _>(&data[..data.len() - 1], &Metadata::new()).is_err());
+		assert!(deserialize_from::<Status, _>(&[0u8][..], &Metadata::new()).is_err());
+	}
+}
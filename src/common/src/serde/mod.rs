@@ -1,2 +1,4 @@
-mod buf;
-mod de;
\ No newline at end of file
+pub(crate) mod buf;
+pub(crate) mod de;
+pub(crate) mod ser;
+pub(crate) mod value;
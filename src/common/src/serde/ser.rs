@@ -0,0 +1,1076 @@
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+
+use bytes::BufMut;
+use chrono::DateTime;
+use chrono::NaiveDate;
+use chrono::NaiveDateTime;
+use chrono::TimeZone;
+use serde::ser;
+use serde::ser::Impossible;
+use serde::Serialize;
+use serde::Serializer;
+
+use crate::metadata::DataType;
+use crate::metadata::SchemaColumn;
+use crate::metadata::Tz;
+use crate::serde::de::NULL_DISCRIMINATOR;
+use crate::values::value::Value;
+use crate::Error;
+use crate::Result;
+
+/// serialize a row in RowBinary format, fields are matched with columns in order and encoded by
+/// column types.
+pub(crate) fn serialize_row<T: Serialize + ?Sized, B: BufMut>(
+	buf: &mut B,
+	row: &T,
+	columns: &[SchemaColumn],
+) -> Result<()> {
+	row.serialize(RowSerializer { buf, columns })
+}
+
+/// serialize a value of the data type in RowBinary format.
+pub(crate) fn serialize_value<B: BufMut>(buf: &mut B, value: &Value, data_type: &DataType) -> Result<()> {
+	let mut serializer = TypedSerializer { buf, data_type };
+	let data_type = match (value, data_type) {
+		(Value::Null, DataType::Nullable(_)) => {
+			serializer.buf.put_u8(1);
+			return Ok(());
+		}
+		(Value::Null, DataType::Nothing) => return Ok(()),
+		(value, DataType::Variant(types)) => return serialize_variant(serializer.buf, value, types),
+		(Value::Null, DataType::Dynamic(_)) => {
+			DataType::Nothing.write_binary(serializer.buf);
+			return Ok(());
+		}
+		(value, DataType::Dynamic(_)) => {
+			let data_type = value.data_type();
+			data_type.write_binary(serializer.buf);
+			return serialize_value(serializer.buf, value, &data_type);
+		}
+		_ => serializer.prepare()?,
+	};
+
+	macro_rules! write {
+		($fn: ident, $v: expr) => {
+			$fn(serializer.buf, data_type, $v)
+		};
+	}
+
+	match value {
+		Value::Null => Err(mismatch(data_type, "NULL")),
+		Value::Bool(v) => write!(write_int, *v as i128),
+		Value::Int8(v) => write!(write_int, *v as i128),
+		Value::Int16(v) => write!(write_int, *v as i128),
+		Value::Int32(v) => write!(write_int, *v as i128),
+		Value::Int64(v) => write!(write_int, *v as i128),
+		Value::Int128(v) => write!(write_int, *v),
+		#[cfg(feature = "bigint")]
+		Value::Int256(v) => write!(write_bytes, &v.0),
+		Value::UInt8(v) => write!(write_int, *v as i128),
+		Value::UInt16(v) => write!(write_int, *v as i128),
+		Value::UInt32(v) => write!(write_int, *v as i128),
+		Value::UInt64(v) => write!(write_int, *v as i128),
+		Value::UInt128(v) => write!(write_u128, *v),
+		#[cfg(feature = "bigint")]
+		Value::UInt256(v) => write!(write_bytes, &v.0),
+		Value::Float32(v) => write!(write_float, *v as f64),
+		Value::Float64(v) => write!(write_float, *v),
+		Value::String(v) => write!(write_bytes, v),
+		Value::Ipv4(v) => write!(write_bytes, &v.0),
+		Value::Ipv6(v) => write!(write_bytes, &v.0),
+		#[cfg(feature = "uuid")]
+		Value::Uuid(v) => write!(write_bytes, &v.0),
+		Value::Date(v) => write!(write_int, *v as i128),
+		Value::Date32(v) => write!(write_int, *v as i128),
+		Value::DateTime(v, _) => write!(write_int, *v as i128),
+		Value::DateTime64(v, ..) => write!(write_int, *v as i128),
+		#[cfg(feature = "decimal")]
+		Value::Decimal(v, scale) => write!(write_str, &v.to_string_with_scale(*scale)),
+		Value::Enum8(name, _) | Value::Enum16(name, _) => write!(write_str, name),
+		#[cfg(feature = "json")]
+		Value::Json(v) => write!(write_bytes, &v.0),
+		Value::Tuple(values) | Value::Array(values) => match data_type {
+			DataType::Array(inner) => {
+				put_size(serializer.buf, values.len());
+				values.iter().try_for_each(|value| serialize_value(serializer.buf, value, inner))
+			}
+			DataType::Tuple(fields) if fields.len() == values.len() => values
+				.iter()
+				.zip(fields)
+				.try_for_each(|(value, (_, field))| serialize_value(serializer.buf, value, field)),
+			data_type => Err(mismatch(data_type, "sequence")),
+		},
+		Value::Map(map) => match data_type {
+			DataType::Map(key_type, value_type) => {
+				put_size(serializer.buf, map.len());
+				map.iter().try_for_each(|(key, value)| {
+					serialize_value(serializer.buf, key, key_type)?;
+					serialize_value(serializer.buf, value, value_type)
+				})
+			}
+			data_type => Err(mismatch(data_type, "map")),
+		},
+	}
+}
+
+/// write discriminator and value of variant, the variant of the same type is preferred, otherwise
+/// the first variant the value can be converted to.
+fn serialize_variant<B: BufMut>(buf: &mut B, value: &Value, types: &[DataType]) -> Result<()> {
+	if let Value::Null = value {
+		buf.put_u8(NULL_DISCRIMINATOR);
+		return Ok(());
+	}
+
+	let natural = value.data_type();
+	let exact = types.iter().position(|data_type| *data_type == natural);
+	let mut data = Vec::new();
+	for idx in exact.into_iter().chain(0..types.len()) {
+		data.clear();
+		if serialize_value(&mut data, value, &types[idx]).is_ok() {
+			buf.put_u8(idx as u8);
+			buf.put_slice(&data);
+			return Ok(());
+		}
+	}
+	Err(mismatch(&DataType::Variant(types.to_vec()), value))
+}
+
+/// whether type of value is decided at runtime, scalars are converted to `Value` to find the type.
+fn is_dynamic(data_type: &DataType) -> bool {
+	matches!(data_type, DataType::Variant(_) | DataType::Dynamic(_))
+}
+
+fn mismatch(data_type: &DataType, value: impl std::fmt::Display) -> Error {
+	Error::SerdeError(format!("can't serialize {} as {}", value, data_type))
+}
+
+pub(crate) fn put_size<B: BufMut>(buf: &mut B, size: usize) {
+	let mut value = size as u64;
+	loop {
+		let byte = (value & 0x7f) as u8;
+		value >>= 7;
+		if value == 0 {
+			buf.put_u8(byte);
+			break;
+		}
+		buf.put_u8(byte | 0x80);
+	}
+}
+
+/// write integer, it is also the raw value of date, datetime, enum and decimal.
+fn write_int<B: BufMut>(buf: &mut B, data_type: &DataType, v: i128) -> Result<()> {
+	match data_type {
+		DataType::Bool if v == 0 || v == 1 => buf.put_u8(v as u8),
+		DataType::Int8 | DataType::Enum8(_) => buf.put_i8(v.try_into()?),
+		DataType::Int16 | DataType::Enum16(_) => buf.put_i16_le(v.try_into()?),
+		DataType::Int32 | DataType::Date32 => buf.put_i32_le(v.try_into()?),
+		DataType::Int64 | DataType::DateTime64(..) => buf.put_i64_le(v.try_into()?),
+		DataType::Int128 => buf.put_i128_le(v),
+		DataType::UInt8 => buf.put_u8(v.try_into()?),
+		DataType::UInt16 | DataType::Date => buf.put_u16_le(v.try_into()?),
+		DataType::UInt32 | DataType::DateTime(_) => buf.put_u32_le(v.try_into()?),
+		DataType::UInt64 => buf.put_u64_le(v.try_into()?),
+		DataType::UInt128 => buf.put_u128_le(v.try_into()?),
+		DataType::Int256 => put_i256(buf, v),
+		DataType::UInt256 => put_i256(buf, u128::try_from(v)? as i128),
+		DataType::Float32 => buf.put_f32_le(v as f32),
+		DataType::Float64 => buf.put_f64_le(v as f64),
+		data_type if decimal_of(data_type).is_some() => {
+			let (precision, scale) = decimal_of(data_type).unwrap();
+			let scaled = v.checked_mul(10i128.pow(scale as u32));
+			write_decimal(buf, precision, scaled.ok_or_else(|| mismatch(data_type, v))?)?
+		}
+		data_type => return Err(mismatch(data_type, v)),
+	}
+	Ok(())
+}
+
+fn write_u128<B: BufMut>(buf: &mut B, data_type: &DataType, v: u128) -> Result<()> {
+	match data_type {
+		DataType::UInt128 => buf.put_u128_le(v),
+		DataType::UInt256 => {
+			buf.put_u128_le(v);
+			buf.put_u128_le(0);
+		}
+		data_type => write_int(buf, data_type, v.try_into()?)?,
+	}
+	Ok(())
+}
+
+fn put_i256<B: BufMut>(buf: &mut B, v: i128) {
+	buf.put_i128_le(v);
+	buf.put_i128_le(if v < 0 { -1 } else { 0 });
+}
+
+fn write_float<B: BufMut>(buf: &mut B, data_type: &DataType, v: f64) -> Result<()> {
+	match data_type {
+		DataType::Float32 => buf.put_f32_le(v as f32),
+		DataType::Float64 => buf.put_f64_le(v),
+		data_type if decimal_of(data_type).is_some() => {
+			let (precision, scale) = decimal_of(data_type).unwrap();
+			write_decimal(buf, precision, (v * 10f64.powi(scale as i32)).round() as i128)?
+		}
+		data_type => return Err(mismatch(data_type, v)),
+	}
+	Ok(())
+}
+
+/// write string, it is also the text form of ip, uuid, date, datetime, decimal and enum.
+fn write_str<B: BufMut>(buf: &mut B, data_type: &DataType, s: &str) -> Result<()> {
+	let invalid = || mismatch(data_type, format!("{:?}", s));
+	match data_type {
+		DataType::Ipv4 => buf.put_u32_le(s.parse::<Ipv4Addr>().map_err(|_| invalid())?.into()),
+		DataType::Ipv6 => buf.put_slice(&s.parse::<Ipv6Addr>().map_err(|_| invalid())?.octets()),
+		#[cfg(feature = "uuid")]
+		DataType::Uuid => {
+			let (high, low) = s.parse::<uuid::Uuid>().map_err(|_| invalid())?.as_u64_pair();
+			buf.put_u64_le(high);
+			buf.put_u64_le(low);
+		}
+		DataType::Date | DataType::Date32 => {
+			let date = NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| invalid())?;
+			write_int(buf, data_type, (date - NaiveDate::default()).num_days() as i128)?
+		}
+		DataType::DateTime(tz) => {
+			let (secs, _) = parse_datetime(s, tz.as_ref()).ok_or_else(invalid)?;
+			write_int(buf, data_type, secs as i128)?
+		}
+		DataType::DateTime64(precision, tz) => {
+			let (secs, nanos) = parse_datetime(s, tz.as_ref()).ok_or_else(invalid)?;
+			let scale = 10i128.pow(*precision as u32);
+			let ticks = secs as i128 * scale + nanos as i128 * scale / 1_000_000_000;
+			write_int(buf, data_type, ticks)?
+		}
+		DataType::Enum8(variants) => {
+			let (_, v) = variants.iter().find(|(name, _)| name == s).ok_or_else(invalid)?;
+			buf.put_i8(*v)
+		}
+		DataType::Enum16(variants) => {
+			let (_, v) = variants.iter().find(|(name, _)| name == s).ok_or_else(invalid)?;
+			buf.put_i16_le(*v)
+		}
+		data_type if decimal_of(data_type).is_some() => {
+			let (precision, scale) = decimal_of(data_type).unwrap();
+			write_decimal(buf, precision, parse_decimal(s, scale).ok_or_else(invalid)?)?
+		}
+		data_type => write_bytes(buf, data_type, s.as_bytes())?,
+	}
+	Ok(())
+}
+
+/// write bytes of string, or raw bytes of fixed size types.
+fn write_bytes<B: BufMut>(buf: &mut B, data_type: &DataType, bytes: &[u8]) -> Result<()> {
+	match data_type {
+		DataType::String | DataType::Json => {
+			put_size(buf, bytes.len());
+			buf.put_slice(bytes);
+		}
+		DataType::Array(inner) if **inner == DataType::UInt8 => {
+			put_size(buf, bytes.len());
+			buf.put_slice(bytes);
+		}
+		DataType::FixedString(size) if bytes.len() <= *size => {
+			buf.put_slice(bytes);
+			buf.put_bytes(0, size - bytes.len());
+		}
+		DataType::Ipv4 if bytes.len() == 4 => buf.put_slice(bytes),
+		DataType::Ipv6 | DataType::Uuid if bytes.len() == 16 => buf.put_slice(bytes),
+		DataType::Int256 | DataType::UInt256 if bytes.len() == 32 => buf.put_slice(bytes),
+		data_type => return Err(mismatch(data_type, format!("{} bytes", bytes.len()))),
+	}
+	Ok(())
+}
+
+fn write_decimal<B: BufMut>(buf: &mut B, precision: u8, v: i128) -> Result<()> {
+	match precision {
+		0..=9 => buf.put_i32_le(v.try_into()?),
+		10..=18 => buf.put_i64_le(v.try_into()?),
+		19..=38 => buf.put_i128_le(v),
+		_ => put_i256(buf, v),
+	}
+	Ok(())
+}
+
+/// precision and scale of decimal.
+fn decimal_of(data_type: &DataType) -> Option<(u8, u8)> {
+	match *data_type {
+		DataType::Decimal(precision, scale) => Some((precision, scale)),
+		DataType::Decimal32(scale) => Some((9, scale)),
+		DataType::Decimal64(scale) => Some((18, scale)),
+		DataType::Decimal128(scale) => Some((38, scale)),
+		DataType::Decimal256(scale) => Some((76, scale)),
+		_ => None,
+	}
+}
+
+/// parse decimal like `-12.34` to integer scaled by `scale`.
+fn parse_decimal(s: &str, scale: u8) -> Option<i128> {
+	let (negative, s) = match s.strip_prefix('-') {
+		Some(s) => (true, s),
+		None => (false, s.strip_prefix('+').unwrap_or(s)),
+	};
+	let (int_part, frac_part) = s.split_once('.').unwrap_or((s, ""));
+	if frac_part.len() > scale as usize || !(int_part.chars().chain(frac_part.chars())).all(|c| c.is_ascii_digit()) {
+		return None;
+	}
+
+	let digits = format!("{}{:0<width$}", int_part, frac_part, width = scale as usize);
+	let num = if digits.is_empty() { 0 } else { digits.parse::<i128>().ok()? };
+	Some(if negative { -num } else { num })
+}
+
+/// parse RFC 3339 datetime, or `%Y-%m-%d %H:%M:%S%.f` in timezone, return seconds and nanoseconds.
+fn parse_datetime(s: &str, tz: Option<&Tz>) -> Option<(i64, u32)> {
+	let datetime = match DateTime::parse_from_rfc3339(s) {
+		Ok(datetime) => datetime.to_utc(),
+		Err(_) => {
+			let naive = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+				.or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f"))
+				.ok()?;
+			match tz {
+				Some(tz) => tz.inner().from_local_datetime(&naive).single()?.to_utc(),
+				None => naive.and_utc(),
+			}
+		}
+	};
+	Some((datetime.timestamp(), datetime.timestamp_subsec_nanos()))
+}
+
+/// serializer of a row, it may be a struct, a map or a tuple.
+struct RowSerializer<'a, B> {
+	buf: &'a mut B,
+	columns: &'a [SchemaColumn],
+}
+
+struct RowCompound<'a, B> {
+	buf: &'a mut B,
+	columns: &'a [SchemaColumn],
+	idx: usize,
+	key: Option<String>,
+}
+
+impl<B: BufMut> RowCompound<'_, B> {
+	fn serialize_column<T: Serialize + ?Sized>(&mut self, name: Option<&str>, value: &T) -> Result<()> {
+		let column = self
+			.columns
+			.get(self.idx)
+			.ok_or_else(|| Error::SerdeError(format!("field `{}` has no column", name.unwrap_or_default())))?;
+		if let Some(name) = name.filter(|name| *name != column.name) {
+			return Err(Error::SerdeError(format!("field `{}` doesn't match column `{}`", name, column.name)));
+		}
+
+		self.idx += 1;
+		value.serialize(TypedSerializer { buf: self.buf, data_type: &column.data_type })
+	}
+
+	fn end(self) -> Result<()> {
+		match self.columns.get(self.idx) {
+			Some(column) => Err(Error::SerdeError(format!("no field of column `{}`", column.name))),
+			None => Ok(()),
+		}
+	}
+}
+
+macro_rules! unsupported_row {
+	($($fn: ident($($arg: ty),*)),*) => {
+		$(
+			fn $fn(self, $(_: $arg),*) -> Result<Self::Ok> {
+				Err(Error::SerdeError("row must be a struct, a map or a tuple".to_owned()))
+			}
+		)*
+	};
+}
+
+impl<'a, B: BufMut> Serializer for RowSerializer<'a, B> {
+	type Ok = ();
+	type Error = Error;
+	type SerializeSeq = RowCompound<'a, B>;
+	type SerializeTuple = RowCompound<'a, B>;
+	type SerializeTupleStruct = RowCompound<'a, B>;
+	type SerializeTupleVariant = Impossible<(), Error>;
+	type SerializeMap = RowCompound<'a, B>;
+	type SerializeStruct = RowCompound<'a, B>;
+	type SerializeStructVariant = Impossible<(), Error>;
+
+	unsupported_row!(
+		serialize_bool(bool),
+		serialize_i8(i8),
+		serialize_i16(i16),
+		serialize_i32(i32),
+		serialize_i64(i64),
+		serialize_u8(u8),
+		serialize_u16(u16),
+		serialize_u32(u32),
+		serialize_u64(u64),
+		serialize_f32(f32),
+		serialize_f64(f64),
+		serialize_char(char),
+		serialize_str(&str),
+		serialize_bytes(&[u8]),
+		serialize_none(),
+		serialize_unit(),
+		serialize_unit_struct(&'static str),
+		serialize_unit_variant(&'static str, u32, &'static str)
+	);
+
+	fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+		value.serialize(self)
+	}
+
+	fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _: &'static str, value: &T) -> Result<()> {
+		value.serialize(self)
+	}
+
+	fn serialize_newtype_variant<T: Serialize + ?Sized>(
+		self,
+		_: &'static str,
+		_: u32,
+		_: &'static str,
+		_: &T,
+	) -> Result<()> {
+		Err(Error::SerdeError("row must be a struct, a map or a tuple".to_owned()))
+	}
+
+	fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq> {
+		Ok(RowCompound { buf: self.buf, columns: self.columns, idx: 0, key: None })
+	}
+
+	fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+		self.serialize_seq(Some(len))
+	}
+
+	fn serialize_tuple_struct(self, _: &'static str, len: usize) -> Result<Self::SerializeTupleStruct> {
+		self.serialize_seq(Some(len))
+	}
+
+	fn serialize_tuple_variant(
+		self,
+		_: &'static str,
+		_: u32,
+		_: &'static str,
+		_: usize,
+	) -> Result<Self::SerializeTupleVariant> {
+		Err(Error::SerdeError("row must be a struct, a map or a tuple".to_owned()))
+	}
+
+	fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
+		self.serialize_seq(len)
+	}
+
+	fn serialize_struct(self, _: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+		self.serialize_seq(Some(len))
+	}
+
+	fn serialize_struct_variant(
+		self,
+		_: &'static str,
+		_: u32,
+		_: &'static str,
+		_: usize,
+	) -> Result<Self::SerializeStructVariant> {
+		Err(Error::SerdeError("row must be a struct, a map or a tuple".to_owned()))
+	}
+}
+
+impl<B: BufMut> ser::SerializeSeq for RowCompound<'_, B> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+		self.serialize_column(None, value)
+	}
+
+	fn end(self) -> Result<()> {
+		RowCompound::end(self)
+	}
+}
+
+impl<B: BufMut> ser::SerializeTuple for RowCompound<'_, B> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+		self.serialize_column(None, value)
+	}
+
+	fn end(self) -> Result<()> {
+		RowCompound::end(self)
+	}
+}
+
+impl<B: BufMut> ser::SerializeTupleStruct for RowCompound<'_, B> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+		self.serialize_column(None, value)
+	}
+
+	fn end(self) -> Result<()> {
+		RowCompound::end(self)
+	}
+}
+
+impl<B: BufMut> ser::SerializeStruct for RowCompound<'_, B> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()> {
+		self.serialize_column(Some(key), value)
+	}
+
+	fn end(self) -> Result<()> {
+		RowCompound::end(self)
+	}
+}
+
+/// map is used by struct with flattened fields.
+impl<B: BufMut> ser::SerializeMap for RowCompound<'_, B> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+		self.key = Some(key.serialize(KeySerializer)?);
+		Ok(())
+	}
+
+	fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+		let key = self.key.take();
+		self.serialize_column(key.as_deref(), value)
+	}
+
+	fn end(self) -> Result<()> {
+		RowCompound::end(self)
+	}
+}
+
+/// serializer of map key of a row, only string is supported.
+struct KeySerializer;
+
+macro_rules! unsupported_key {
+	($($fn: ident($($arg: ty),*)),*) => {
+		$(
+			fn $fn(self, $(_: $arg),*) -> Result<Self::Ok> {
+				Err(Error::SerdeError("field name must be a string".to_owned()))
+			}
+		)*
+	};
+}
+
+impl Serializer for KeySerializer {
+	type Ok = String;
+	type Error = Error;
+	type SerializeSeq = Impossible<String, Error>;
+	type SerializeTuple = Impossible<String, Error>;
+	type SerializeTupleStruct = Impossible<String, Error>;
+	type SerializeTupleVariant = Impossible<String, Error>;
+	type SerializeMap = Impossible<String, Error>;
+	type SerializeStruct = Impossible<String, Error>;
+	type SerializeStructVariant = Impossible<String, Error>;
+
+	unsupported_key!(
+		serialize_bool(bool),
+		serialize_i8(i8),
+		serialize_i16(i16),
+		serialize_i32(i32),
+		serialize_i64(i64),
+		serialize_u8(u8),
+		serialize_u16(u16),
+		serialize_u32(u32),
+		serialize_u64(u64),
+		serialize_f32(f32),
+		serialize_f64(f64),
+		serialize_char(char),
+		serialize_bytes(&[u8]),
+		serialize_none(),
+		serialize_unit(),
+		serialize_unit_struct(&'static str),
+		serialize_unit_variant(&'static str, u32, &'static str)
+	);
+
+	fn serialize_str(self, v: &str) -> Result<String> {
+		Ok(v.to_owned())
+	}
+
+	fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String> {
+		value.serialize(self)
+	}
+
+	fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _: &'static str, value: &T) -> Result<String> {
+		value.serialize(self)
+	}
+
+	fn serialize_newtype_variant<T: Serialize + ?Sized>(
+		self,
+		_: &'static str,
+		_: u32,
+		_: &'static str,
+		_: &T,
+	) -> Result<String> {
+		Err(Error::SerdeError("field name must be a string".to_owned()))
+	}
+
+	fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq> {
+		Err(Error::SerdeError("field name must be a string".to_owned()))
+	}
+
+	fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple> {
+		Err(Error::SerdeError("field name must be a string".to_owned()))
+	}
+
+	fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeTupleStruct> {
+		Err(Error::SerdeError("field name must be a string".to_owned()))
+	}
+
+	fn serialize_tuple_variant(
+		self,
+		_: &'static str,
+		_: u32,
+		_: &'static str,
+		_: usize,
+	) -> Result<Self::SerializeTupleVariant> {
+		Err(Error::SerdeError("field name must be a string".to_owned()))
+	}
+
+	fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap> {
+		Err(Error::SerdeError("field name must be a string".to_owned()))
+	}
+
+	fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeStruct> {
+		Err(Error::SerdeError("field name must be a string".to_owned()))
+	}
+
+	fn serialize_struct_variant(
+		self,
+		_: &'static str,
+		_: u32,
+		_: &'static str,
+		_: usize,
+	) -> Result<Self::SerializeStructVariant> {
+		Err(Error::SerdeError("field name must be a string".to_owned()))
+	}
+}
+
+/// serializer driven by ClickHouse data type, so rust values are converted to the column type,
+/// e.g. RFC 3339 string to DateTime or unit variant to Enum8.
+struct TypedSerializer<'a, B> {
+	buf: &'a mut B,
+	data_type: &'a DataType,
+}
+
+impl<'a, B: BufMut> TypedSerializer<'a, B> {
+	/// write not-null flag of nullable, return the type to encode value.
+	fn prepare(&mut self) -> Result<&'a DataType> {
+		let mut data_type = self.data_type;
+		loop {
+			data_type = match data_type {
+				DataType::Nullable(inner) => {
+					self.buf.put_u8(0);
+					inner
+				}
+				DataType::LowCardinality(inner) => inner,
+				DataType::SimpleAggregateFunction(_, types) if types.len() == 1 => &types[0],
+				data_type => return Ok(data_type),
+			};
+		}
+	}
+
+	fn compound(mut self, len: Option<usize>) -> Result<Compound<'a, B>> {
+		let data_type = self.prepare()?;
+		let kind = match data_type {
+			DataType::Array(inner) => {
+				put_size(self.buf, len.ok_or_else(|| Error::SerdeError("length of array is unknown".to_owned()))?);
+				CompoundKind::Array(inner)
+			}
+			DataType::Tuple(fields) => CompoundKind::Tuple(fields),
+			DataType::Map(key, value) => {
+				put_size(self.buf, len.ok_or_else(|| Error::SerdeError("length of map is unknown".to_owned()))?);
+				CompoundKind::Map(key, value)
+			}
+			DataType::FixedString(_)
+			| DataType::Ipv4
+			| DataType::Ipv6
+			| DataType::Uuid
+			| DataType::Int256
+			| DataType::UInt256 => CompoundKind::Bytes(data_type, Vec::new()),
+			data_type => return Err(mismatch(data_type, "sequence")),
+		};
+		Ok(Compound { buf: self.buf, kind, idx: 0 })
+	}
+}
+
+macro_rules! impl_serialize_num {
+	($fn: ident, $ty: ty, $write_fn: ident, $as_ty: ty) => {
+		fn $fn(mut self, v: $ty) -> Result<()> {
+			let data_type = self.prepare()?;
+			if is_dynamic(data_type) {
+				return serialize_value(self.buf, &Value::from(v), data_type);
+			}
+			$write_fn(self.buf, data_type, v as $as_ty)
+		}
+	};
+}
+
+impl<'a, B: BufMut> Serializer for TypedSerializer<'a, B> {
+	type Ok = ();
+	type Error = Error;
+	type SerializeSeq = Compound<'a, B>;
+	type SerializeTuple = Compound<'a, B>;
+	type SerializeTupleStruct = Compound<'a, B>;
+	type SerializeTupleVariant = Impossible<(), Error>;
+	type SerializeMap = Compound<'a, B>;
+	type SerializeStruct = Compound<'a, B>;
+	type SerializeStructVariant = Impossible<(), Error>;
+
+	impl_serialize_num!(serialize_bool, bool, write_int, i128);
+	impl_serialize_num!(serialize_i8, i8, write_int, i128);
+	impl_serialize_num!(serialize_i16, i16, write_int, i128);
+	impl_serialize_num!(serialize_i32, i32, write_int, i128);
+	impl_serialize_num!(serialize_i64, i64, write_int, i128);
+	impl_serialize_num!(serialize_i128, i128, write_int, i128);
+	impl_serialize_num!(serialize_u8, u8, write_int, i128);
+	impl_serialize_num!(serialize_u16, u16, write_int, i128);
+	impl_serialize_num!(serialize_u32, u32, write_int, i128);
+	impl_serialize_num!(serialize_u64, u64, write_int, i128);
+	impl_serialize_num!(serialize_u128, u128, write_u128, u128);
+	impl_serialize_num!(serialize_f32, f32, write_float, f64);
+	impl_serialize_num!(serialize_f64, f64, write_float, f64);
+
+	fn serialize_char(self, v: char) -> Result<()> {
+		self.serialize_str(v.encode_utf8(&mut [0; 4]))
+	}
+
+	fn serialize_str(mut self, v: &str) -> Result<()> {
+		let data_type = self.prepare()?;
+		if is_dynamic(data_type) {
+			return serialize_value(self.buf, &Value::from(v), data_type);
+		}
+		write_str(self.buf, data_type, v)
+	}
+
+	fn serialize_bytes(mut self, v: &[u8]) -> Result<()> {
+		let data_type = self.prepare()?;
+		if is_dynamic(data_type) {
+			return serialize_value(self.buf, &Value::String(v.to_vec()), data_type);
+		}
+		write_bytes(self.buf, data_type, v)
+	}
+
+	fn serialize_none(self) -> Result<()> {
+		// wrappers of `prepare`, e.g. `LowCardinality(Nullable(String))`.
+		let mut data_type = self.data_type;
+		while let DataType::LowCardinality(inner) = data_type {
+			data_type = inner;
+		}
+		if let DataType::SimpleAggregateFunction(_, types) = data_type {
+			if types.len() == 1 {
+				data_type = &types[0];
+			}
+		}
+		match data_type {
+			DataType::Nullable(_) => {
+				self.buf.put_u8(1);
+				Ok(())
+			}
+			data_type if is_dynamic(data_type) => serialize_value(self.buf, &Value::Null, data_type),
+			data_type => Err(mismatch(data_type, "NULL")),
+		}
+	}
+
+	fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+		value.serialize(self)
+	}
+
+	fn serialize_unit(self) -> Result<()> {
+		self.serialize_none()
+	}
+
+	fn serialize_unit_struct(self, _: &'static str) -> Result<()> {
+		self.serialize_none()
+	}
+
+	fn serialize_unit_variant(self, _: &'static str, _: u32, variant: &'static str) -> Result<()> {
+		self.serialize_str(variant)
+	}
+
+	fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _: &'static str, value: &T) -> Result<()> {
+		value.serialize(self)
+	}
+
+	fn serialize_newtype_variant<T: Serialize + ?Sized>(
+		self,
+		_: &'static str,
+		_: u32,
+		variant: &'static str,
+		_: &T,
+	) -> Result<()> {
+		Err(mismatch(self.data_type, format!("variant {}", variant)))
+	}
+
+	fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+		self.compound(len)
+	}
+
+	fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+		self.compound(Some(len))
+	}
+
+	fn serialize_tuple_struct(self, _: &'static str, len: usize) -> Result<Self::SerializeTupleStruct> {
+		self.compound(Some(len))
+	}
+
+	fn serialize_tuple_variant(
+		self,
+		_: &'static str,
+		_: u32,
+		variant: &'static str,
+		_: usize,
+	) -> Result<Self::SerializeTupleVariant> {
+		Err(mismatch(self.data_type, format!("variant {}", variant)))
+	}
+
+	fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
+		self.compound(len)
+	}
+
+	fn serialize_struct(self, _: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+		self.compound(Some(len))
+	}
+
+	fn serialize_struct_variant(
+		self,
+		_: &'static str,
+		_: u32,
+		variant: &'static str,
+		_: usize,
+	) -> Result<Self::SerializeStructVariant> {
+		Err(mismatch(self.data_type, format!("variant {}", variant)))
+	}
+
+	fn is_human_readable(&self) -> bool {
+		// ip, uuid and datetime are serialized as text, and parsed by column type.
+		true
+	}
+}
+
+enum CompoundKind<'a> {
+	Array(&'a DataType),
+	Tuple(&'a [(String, DataType)]),
+	Map(&'a DataType, &'a DataType),
+	/// fixed size type is serialized as a sequence of bytes, e.g. `[u8; 16]` for FixedString(16).
+	Bytes(&'a DataType, Vec<u8>),
+}
+
+struct Compound<'a, B> {
+	buf: &'a mut B,
+	kind: CompoundKind<'a>,
+	idx: usize,
+}
+
+impl<B: BufMut> Compound<'_, B> {
+	fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+		let data_type = match &mut self.kind {
+			CompoundKind::Array(inner) => *inner,
+			CompoundKind::Tuple(fields) => {
+				&fields.get(self.idx).ok_or_else(|| Error::SerdeError("too many tuple elements".to_owned()))?.1
+			}
+			// key and value of map are serialized alternately.
+			CompoundKind::Map(key, value) => match self.idx % 2 {
+				0 => *key,
+				_ => *value,
+			},
+			CompoundKind::Bytes(_, bytes) => {
+				let mut byte = Vec::with_capacity(1);
+				value.serialize(TypedSerializer { buf: &mut byte, data_type: &DataType::UInt8 })?;
+				bytes.extend(byte);
+				return Ok(());
+			}
+		};
+		self.idx += 1;
+		value.serialize(TypedSerializer { buf: self.buf, data_type })
+	}
+
+	fn finish(self) -> Result<()> {
+		match self.kind {
+			CompoundKind::Tuple(fields) if fields.len() != self.idx => {
+				Err(Error::SerdeError(format!("expect {} tuple elements, got {}", fields.len(), self.idx)))
+			}
+			CompoundKind::Bytes(data_type, bytes) => write_bytes(self.buf, data_type, &bytes),
+			_ => Ok(()),
+		}
+	}
+}
+
+impl<B: BufMut> ser::SerializeSeq for Compound<'_, B> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+		self.element(value)
+	}
+
+	fn end(self) -> Result<()> {
+		self.finish()
+	}
+}
+
+impl<B: BufMut> ser::SerializeTuple for Compound<'_, B> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+		self.element(value)
+	}
+
+	fn end(self) -> Result<()> {
+		self.finish()
+	}
+}
+
+impl<B: BufMut> ser::SerializeTupleStruct for Compound<'_, B> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+		self.element(value)
+	}
+
+	fn end(self) -> Result<()> {
+		self.finish()
+	}
+}
+
+impl<B: BufMut> ser::SerializeMap for Compound<'_, B> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+		self.element(key)
+	}
+
+	fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+		self.element(value)
+	}
+
+	fn end(self) -> Result<()> {
+		self.finish()
+	}
+}
+
+/// struct is serialized as tuple.
+impl<B: BufMut> ser::SerializeStruct for Compound<'_, B> {
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, _: &'static str, value: &T) -> Result<()> {
+		self.element(value)
+	}
+
+	fn end(self) -> Result<()> {
+		self.finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeMap;
+
+	use serde::Serialize;
+
+	use super::serialize_row;
+	use super::serialize_value;
+	use super::TypedSerializer;
+	use crate::metadata::DataType;
+	use crate::metadata::SchemaColumn;
+	use crate::values::value::Value;
+
+	#[derive(Serialize)]
+	enum Status {
+		#[serde(rename = "new")]
+		New,
+	}
+
+	#[derive(Serialize)]
+	struct Event {
+		id: u32,
+		name: Option<&'static str>,
+		status: Status,
+		ts: &'static str,
+		price: f64,
+		tags: Vec<&'static str>,
+		attrs: BTreeMap<&'static str, u8>,
+		code: [u8; 2],
+	}
+
+	#[test]
+	fn test_serialize_row() {
+		let column = |name: &str, data_type: &str| SchemaColumn {
+			name: name.to_owned(),
+			data_type: data_type.parse().unwrap(),
+			default: false,
+		};
+		let columns = vec![
+			column("id", "UInt64"),
+			column("name", "Nullable(String)"),
+			column("status", "Enum8('new' = 1)"),
+			column("ts", "DateTime64(3, 'Asia/Shanghai')"),
+			column("price", "Decimal(9, 2)"),
+			column("tags", "Array(LowCardinality(String))"),
+			column("attrs", "Map(String, UInt8)"),
+			column("code", "FixedString(3)"),
+		];
+		let event = Event {
+			id: 1,
+			name: None,
+			status: Status::New,
+			ts: "1970-01-01 08:00:01.5",
+			price: 1.5,
+			tags: vec!["a"],
+			attrs: BTreeMap::from([("k", 2)]),
+			code: [b'x', b'y'],
+		};
+
+		let mut buf = Vec::new();
+		serialize_row(&mut buf, &event, &columns).unwrap();
+		assert_eq!(
+			vec![
+				1, 0, 0, 0, 0, 0, 0, 0, // id
+				1, // name
+				1, // status
+				0xdc, 0x05, 0, 0, 0, 0, 0, 0, // ts
+				150, 0, 0, 0, // price
+				1, 1, b'a', // tags
+				1, 1, b'k', 2, // attrs
+				b'x', b'y', 0, // code
+			],
+			buf
+		);
+
+		let err = serialize_row(&mut Vec::new(), &event, &columns[1..]).unwrap_err();
+		assert_eq!("Serde deSer error: field `id` doesn't match column `name`", err.to_string());
+	}
+
+	#[test]
+	fn test_serialize_value() {
+		let data_type = "Tuple(Nullable(Int32), String)".parse::<DataType>().unwrap();
+		let mut buf = Vec::new();
+		serialize_value(&mut buf, &Value::Tuple(vec![Value::Null, Value::String(b"a".to_vec())]), &data_type).unwrap();
+		assert_eq!(vec![1, 1, b'a'], buf);
+		assert!(serialize_value(&mut buf, &Value::Int64(i64::MAX), &DataType::Int32).is_err());
+	}
+
+	#[test]
+	fn test_serialize_none() {
+		for data_type in ["LowCardinality(Nullable(String))", "SimpleAggregateFunction(any, Nullable(String))"] {
+			let data_type = data_type.parse::<DataType>().unwrap();
+			let mut buf = Vec::new();
+			None::<&str>.serialize(TypedSerializer { buf: &mut buf, data_type: &data_type }).unwrap();
+			Some("a").serialize(TypedSerializer { buf: &mut buf, data_type: &data_type }).unwrap();
+			assert_eq!(vec![1, 0, 1, b'a'], buf);
+		}
+		assert!(None::<&str>
+			.serialize(TypedSerializer { buf: &mut Vec::new(), data_type: &DataType::String })
+			.is_err());
+	}
+}
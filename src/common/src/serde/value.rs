@@ -0,0 +1,196 @@
+use serde::de::value::MapDeserializer;
+use serde::de::value::SeqDeserializer;
+use serde::de::IntoDeserializer;
+use serde::de::Visitor;
+use serde::forward_to_deserialize_any;
+use serde::Deserializer;
+
+use crate::metadata::Row;
+use crate::values::value::Value;
+use crate::Error;
+
+/// deserialize rust type from a dynamic row, struct fields are matched by column name.
+pub(crate) struct RowDeserializer<'a>(pub &'a Row);
+
+impl<'de> Deserializer<'de> for RowDeserializer<'_> {
+	type Error = Error;
+
+	fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		let mut map = MapDeserializer::new(self.0.iter().map(|(name, _, value)| (name, ValueDeserializer(value))));
+		let value = visitor.visit_map(&mut map)?;
+		map.end()?;
+		Ok(value)
+	}
+
+	fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		visit_values(self.0.values(), visitor)
+	}
+
+	fn deserialize_tuple<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, Self::Error> {
+		visit_values(self.0.values(), visitor)
+	}
+
+	fn deserialize_tuple_struct<V: Visitor<'de>>(
+		self,
+		_: &'static str,
+		_: usize,
+		visitor: V,
+	) -> Result<V::Value, Self::Error> {
+		visit_values(self.0.values(), visitor)
+	}
+
+	fn deserialize_struct<V: Visitor<'de>>(
+		self,
+		_: &'static str,
+		fields: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, Self::Error> {
+		// unknown columns are skipped.
+		let iter = self.0.iter().filter(|(name, ..)| fields.contains(name));
+		visitor.visit_map(MapDeserializer::new(iter.map(|(name, _, value)| (name, ValueDeserializer(value)))))
+	}
+
+	forward_to_deserialize_any! {
+		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+		bytes byte_buf option unit unit_struct newtype_struct map enum identifier ignored_any
+	}
+}
+
+fn visit_values<'de, V: Visitor<'de>>(values: &[Value], visitor: V) -> Result<V::Value, Error> {
+	let mut seq = SeqDeserializer::new(values.iter().map(ValueDeserializer));
+	let value = visitor.visit_seq(&mut seq)?;
+	seq.end()?;
+	Ok(value)
+}
+
+/// deserialize rust type from a dynamic value.
+pub(crate) struct ValueDeserializer<'a>(pub &'a Value);
+
+impl<'de> IntoDeserializer<'de, Error> for ValueDeserializer<'_> {
+	type Deserializer = Self;
+
+	fn into_deserializer(self) -> Self::Deserializer {
+		self
+	}
+}
+
+macro_rules! impl_deserialize_num {
+	($de_fn: ident, $visitor_fn: ident, $ty: ty) => {
+		fn $de_fn<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+			// numeric form of date, datetime and enum.
+			match *self.0 {
+				Value::Date(v) => visitor.$visitor_fn(<$ty>::try_from(v as i128)?),
+				Value::Date32(v) => visitor.$visitor_fn(<$ty>::try_from(v as i128)?),
+				Value::DateTime(v, _) => visitor.$visitor_fn(<$ty>::try_from(v as i128)?),
+				Value::DateTime64(v, ..) => visitor.$visitor_fn(<$ty>::try_from(v as i128)?),
+				Value::Enum8(_, v) => visitor.$visitor_fn(<$ty>::try_from(v as i128)?),
+				Value::Enum16(_, v) => visitor.$visitor_fn(<$ty>::try_from(v as i128)?),
+				_ => self.deserialize_any(visitor),
+			}
+		}
+	};
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer<'_> {
+	type Error = Error;
+
+	fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		match self.0 {
+			Value::Null => visitor.visit_unit(),
+			Value::Bool(v) => visitor.visit_bool(*v),
+			Value::Int8(v) => visitor.visit_i8(*v),
+			Value::Int16(v) => visitor.visit_i16(*v),
+			Value::Int32(v) => visitor.visit_i32(*v),
+			Value::Int64(v) => visitor.visit_i64(*v),
+			Value::Int128(v) => visitor.visit_i128(*v),
+			Value::UInt8(v) => visitor.visit_u8(*v),
+			Value::UInt16(v) => visitor.visit_u16(*v),
+			Value::UInt32(v) => visitor.visit_u32(*v),
+			Value::UInt64(v) => visitor.visit_u64(*v),
+			Value::UInt128(v) => visitor.visit_u128(*v),
+			Value::Float32(v) => visitor.visit_f32(*v),
+			Value::Float64(v) => visitor.visit_f64(*v),
+			Value::String(v) => match core::str::from_utf8(v) {
+				Ok(s) => visitor.visit_str(s),
+				Err(_) => visitor.visit_bytes(v),
+			},
+			Value::Tuple(values) | Value::Array(values) => visit_values(values, visitor),
+			Value::Map(map) => {
+				let mut map =
+					MapDeserializer::new(map.iter().map(|(k, v)| (ValueDeserializer(k), ValueDeserializer(v))));
+				let value = visitor.visit_map(&mut map)?;
+				map.end()?;
+				Ok(value)
+			}
+			// dates, decimals, ips, uuids and so on are visited by text format.
+			v => visitor.visit_string(v.to_string()),
+		}
+	}
+
+	impl_deserialize_num!(deserialize_i8, visit_i8, i8);
+	impl_deserialize_num!(deserialize_i16, visit_i16, i16);
+	impl_deserialize_num!(deserialize_i32, visit_i32, i32);
+	impl_deserialize_num!(deserialize_i64, visit_i64, i64);
+	impl_deserialize_num!(deserialize_u8, visit_u8, u8);
+	impl_deserialize_num!(deserialize_u16, visit_u16, u16);
+	impl_deserialize_num!(deserialize_u32, visit_u32, u32);
+	impl_deserialize_num!(deserialize_u64, visit_u64, u64);
+
+	fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		match self.0 {
+			Value::String(v) => visitor.visit_bytes(v),
+			_ => self.deserialize_any(visitor),
+		}
+	}
+
+	fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		self.deserialize_bytes(visitor)
+	}
+
+	fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		match self.0 {
+			Value::Null => visitor.visit_none(),
+			_ => visitor.visit_some(self),
+		}
+	}
+
+	/// bytes of fixed strings, big integers and uuids are visited as arrays, e.g. `[u8; 32]` of
+	/// `i256`.
+	fn deserialize_tuple<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, Self::Error> {
+		let bytes = match self.0 {
+			Value::String(v) => v.as_slice(),
+			#[cfg(feature = "bigint")]
+			Value::Int256(v) => v.0.as_slice(),
+			#[cfg(feature = "bigint")]
+			Value::UInt256(v) => v.0.as_slice(),
+			#[cfg(feature = "uuid")]
+			Value::Uuid(v) => v.0.as_slice(),
+			_ => return self.deserialize_any(visitor),
+		};
+		let mut seq = SeqDeserializer::<_, Error>::new(bytes.iter().copied());
+		let value = visitor.visit_seq(&mut seq)?;
+		seq.end()?;
+		Ok(value)
+	}
+
+	fn deserialize_newtype_struct<V: Visitor<'de>>(self, _: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+		visitor.visit_newtype_struct(self)
+	}
+
+	fn deserialize_enum<V: Visitor<'de>>(
+		self,
+		_: &'static str,
+		_: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, Self::Error> {
+		match self.0 {
+			Value::Enum8(name, _) | Value::Enum16(name, _) => visitor.visit_enum(name.as_str().into_deserializer()),
+			Value::String(v) => visitor.visit_enum(core::str::from_utf8(v)?.into_deserializer()),
+			v => Err(Error::ConvertError(format!("can't convert {:?} to enum", v))),
+		}
+	}
+
+	forward_to_deserialize_any! {
+		bool i128 u128 f32 f64 char str string unit unit_struct seq tuple_struct map struct identifier ignored_any
+	}
+}
@@ -0,0 +1,352 @@
+//! Rendering of SQL literals and identifiers, for the places parameters can't be used, e.g. table
+//! names or `IN` lists of DDL.
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Write;
+
+use crate::error::Result;
+use crate::metadata::DataType;
+use crate::values::text::format_date;
+use crate::values::text::format_datetime;
+use crate::values::text::format_datetime64;
+use crate::values::text::format_float;
+use crate::values::text::write_quoted;
+use crate::values::value::Value;
+
+/// quote identifier by backticks, e.g. `` `my col` ``.
+pub fn quote_identifier(name: &str) -> String {
+	let mut quoted = String::with_capacity(name.len() + 2);
+	quoted.push('`');
+	for c in name.chars() {
+		match c {
+			'`' => quoted.push_str("\\`"),
+			'\\' => quoted.push_str("\\\\"),
+			c => quoted.push(c),
+		}
+	}
+	quoted.push('`');
+	quoted
+}
+
+/// quote table name, `db.table` is quoted as `` `db`.`table` ``.
+pub fn quote_table(table: &str) -> String {
+	match table.split_once('.') {
+		Some((database, name)) => format!("{}.{}", quote_identifier(database), quote_identifier(name)),
+		None => quote_identifier(table),
+	}
+}
+
+/// quote string by `'`, e.g. `'it\'s'`.
+pub fn quote_string(s: &str) -> String {
+	let mut quoted = String::with_capacity(s.len() + 2);
+	let _ = write_quoted(&mut quoted, s);
+	quoted
+}
+
+/// quote bytes by `'`, invalid utf-8 bytes are escaped like `\xFF`.
+fn quote_bytes(bytes: &[u8]) -> String {
+	if let Ok(s) = std::str::from_utf8(bytes) {
+		return quote_string(s);
+	}
+
+	let mut quoted = String::with_capacity(bytes.len() + 2);
+	quoted.push('\'');
+	let mut rest = bytes;
+	while !rest.is_empty() {
+		let (valid, invalid) = match std::str::from_utf8(rest) {
+			Ok(valid) => (valid, 0),
+			Err(e) => {
+				let valid = std::str::from_utf8(&rest[..e.valid_up_to()]).unwrap_or_default();
+				(valid, e.error_len().unwrap_or(rest.len() - e.valid_up_to()))
+			}
+		};
+		let valid_quoted = quote_string(valid);
+		quoted.push_str(&valid_quoted[1..valid_quoted.len() - 1]);
+		rest = &rest[valid.len()..];
+		for b in &rest[..invalid] {
+			let _ = write!(quoted, "\\x{:02X}", b);
+		}
+		rest = &rest[invalid..];
+	}
+	quoted.push('\'');
+	quoted
+}
+
+/// render value as SQL literal of its own type, e.g. `['a',NULL]`, `toDate('2024-01-31')`.
+///
+/// Types which have no literal syntax are constructed by functions, so the literal keeps its type
+/// in arrays and comparisons.
+pub fn literal(value: &Value) -> String {
+	let mut out = String::new();
+	write_literal(&mut out, value);
+	out
+}
+
+/// render value as SQL literal casted to the data type, e.g. `CAST([1,2] AS Array(UInt32))`.
+///
+/// Value is checked against the data type as query parameters do.
+pub fn typed_literal(value: &Value, data_type: &DataType) -> Result<String> {
+	value.to_param(data_type)?;
+	Ok(format!("CAST({} AS {})", literal(value), data_type))
+}
+
+fn write_literal(out: &mut String, value: &Value) {
+	let with_tz = |text: String, tz: Option<String>| match tz {
+		Some(tz) => format!("{}, {}", quote_string(&text), quote_string(&tz)),
+		None => quote_string(&text),
+	};
+	match value {
+		Value::Null => out.push_str("NULL"),
+		Value::Bool(v) => out.push_str(if *v { "true" } else { "false" }),
+		Value::Float32(v) => out.push_str(&format_float(*v)),
+		Value::Float64(v) => out.push_str(&format_float(*v)),
+		Value::String(v) => out.push_str(&quote_bytes(v)),
+		Value::Ipv4(v) => {
+			let _ = write!(out, "toIPv4({})", quote_string(&v.to_string()));
+		}
+		Value::Ipv6(v) => {
+			let _ = write!(out, "toIPv6({})", quote_string(&v.to_string()));
+		}
+		#[cfg(feature = "uuid")]
+		Value::Uuid(v) => {
+			let _ = write!(out, "toUUID({})", quote_string(&v.to_string()));
+		}
+		Value::Date(v) => {
+			let _ = write!(out, "toDate({})", quote_string(&format_date(*v as i32)));
+		}
+		Value::Date32(v) => {
+			let _ = write!(out, "toDate32({})", quote_string(&format_date(*v)));
+		}
+		Value::DateTime(v, tz) => {
+			let text = format_datetime(*v as i64, tz.as_ref());
+			let _ = write!(out, "toDateTime({})", with_tz(text, tz.as_ref().map(|tz| tz.to_string())));
+		}
+		Value::DateTime64(v, precision, tz) => {
+			let text = format_datetime64(*v, *precision, tz.as_ref());
+			let tz = tz.as_ref().map(|tz| tz.to_string());
+			let _ = write!(out, "toDateTime64({}, {}", quote_string(&text), precision);
+			if let Some(tz) = tz {
+				let _ = write!(out, ", {}", quote_string(&tz));
+			}
+			out.push(')');
+		}
+		#[cfg(feature = "decimal")]
+		Value::Decimal(v, scale) => {
+			use crate::values::decimal::Decimal;
+
+			let func = match v {
+				Decimal::I32(_) => "toDecimal32",
+				Decimal::I64(_) => "toDecimal64",
+				Decimal::I128(_) => "toDecimal128",
+				Decimal::I256(_) => "toDecimal256",
+			};
+			let _ = write!(out, "{}({}, {})", func, quote_string(&value.to_string()), scale);
+		}
+		Value::Enum8(name, _) | Value::Enum16(name, _) => out.push_str(&quote_string(name)),
+		#[cfg(feature = "json")]
+		Value::Json(v) => out.push_str(&quote_bytes(&v.0)),
+		Value::Tuple(values) => {
+			// `(1)` is not a tuple but an expression in parentheses.
+			out.push_str(if values.len() < 2 { "tuple(" } else { "(" });
+			write_separated(out, values.iter(), write_literal);
+			out.push(')');
+		}
+		Value::Array(values) => {
+			out.push('[');
+			write_separated(out, values.iter(), write_literal);
+			out.push(']');
+		}
+		Value::Map(map) => {
+			out.push_str("map(");
+			write_separated(out, map.iter(), |out, (key, value)| {
+				write_literal(out, key);
+				out.push_str(", ");
+				write_literal(out, value);
+			});
+			out.push(')');
+		}
+		value => {
+			let _ = write!(out, "{}", value);
+		}
+	}
+}
+
+fn write_separated<I, F>(out: &mut String, iter: I, mut write_fn: F)
+where
+	I: Iterator,
+	F: FnMut(&mut String, I::Item),
+{
+	for (idx, item) in iter.enumerate() {
+		if idx > 0 {
+			out.push_str(", ");
+		}
+		write_fn(out, item);
+	}
+}
+
+/// SQL built from trusted fragments, identifiers and literals.
+///
+/// Only `&'static str` is accepted as raw SQL, so values from runtime are always escaped:
+///
+/// ```
+/// use rickhouse_common::sql::Sql;
+///
+/// let sql = Sql::new("SELECT * FROM ").identifier("my table").push(" WHERE id IN ").literals([1, 2]);
+/// assert_eq!("SELECT * FROM `my table` WHERE id IN (1, 2)", sql.as_str());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sql {
+	sql: String,
+}
+
+impl Sql {
+	pub fn new(sql: &'static str) -> Sql {
+		Sql { sql: sql.to_owned() }
+	}
+
+	/// append trusted SQL fragment.
+	pub fn push(mut self, sql: &'static str) -> Self {
+		self.sql.push_str(sql);
+		self
+	}
+
+	/// append another SQL built safely.
+	pub fn push_sql(mut self, sql: &Sql) -> Self {
+		self.sql.push_str(&sql.sql);
+		self
+	}
+
+	/// append quoted identifier, e.g. table or column name.
+	pub fn identifier(mut self, name: &str) -> Self {
+		self.sql.push_str(&quote_identifier(name));
+		self
+	}
+
+	/// append qualified name like `` `db`.`table` ``.
+	pub fn qualified(mut self, database: &str, name: &str) -> Self {
+		self.sql.push_str(&quote_identifier(database));
+		self.sql.push('.');
+		self.sql.push_str(&quote_identifier(name));
+		self
+	}
+
+	/// append value as literal.
+	pub fn literal(mut self, value: impl Into<Value>) -> Self {
+		write_literal(&mut self.sql, &value.into());
+		self
+	}
+
+	/// append value as literal casted to the data type.
+	pub fn typed_literal(mut self, value: impl Into<Value>, data_type: &DataType) -> Result<Self> {
+		self.sql.push_str(&typed_literal(&value.into(), data_type)?);
+		Ok(self)
+	}
+
+	/// append values as list in parentheses for `IN`, e.g. `(1, 2, 3)`.
+	pub fn literals<I>(mut self, values: I) -> Self
+	where
+		I: IntoIterator,
+		I::Item: Into<Value>,
+	{
+		self.sql.push('(');
+		write_separated(&mut self.sql, values.into_iter(), |out, value| write_literal(out, &value.into()));
+		self.sql.push(')');
+		self
+	}
+
+	/// append identifiers separated by comma, e.g. column list.
+	pub fn identifiers<I>(mut self, names: I) -> Self
+	where
+		I: IntoIterator,
+		I::Item: AsRef<str>,
+	{
+		write_separated(&mut self.sql, names.into_iter(), |out, name| out.push_str(&quote_identifier(name.as_ref())));
+		self
+	}
+
+	pub fn as_str(&self) -> &str {
+		&self.sql
+	}
+
+	pub fn into_string(self) -> String {
+		self.sql
+	}
+}
+
+impl Display for Sql {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		f.write_str(&self.sql)
+	}
+}
+
+impl From<Sql> for String {
+	fn from(sql: Sql) -> Self {
+		sql.sql
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+
+	use chrono::DateTime;
+	use chrono::NaiveDate;
+	use chrono::Utc;
+
+	use super::*;
+
+	#[test]
+	fn test_literal() {
+		let lit = |value: Value| literal(&value);
+		assert_eq!("'it\\'s\\t\\\\'", lit("it's\t\\".into()));
+		assert_eq!("'a\\xFFb'", lit(Value::String(vec![b'a', 0xff, b'b'])));
+		assert_eq!("'\\xFF\\xE4\\xB8'", lit(Value::String(vec![0xff, 0xe4, 0xb8])));
+		assert_eq!("[1, NULL, -inf]", lit(vec![Some(1.0), None, Some(f64::NEG_INFINITY)].into()));
+		assert_eq!("tuple('a')", lit(("a",).into()));
+		assert_eq!("(1, true)", lit((1, true).into()));
+		assert_eq!("map('k', [1])", lit(HashMap::from([("k", vec![1])]).into()));
+		assert_eq!("toDate32('1900-01-01')", lit(NaiveDate::from_ymd_opt(1900, 1, 1).unwrap().into()));
+		assert_eq!("toDateTime('2024-01-31 20:00:00', 'Asia/Shanghai')", {
+			lit(Value::DateTime(1706702400, Some("Asia/Shanghai".parse().unwrap())))
+		});
+		let datetime = DateTime::<Utc>::from_timestamp(1706702400, 50_000_000).unwrap();
+		assert_eq!("toDateTime64('2024-01-31 12:00:00.050000000', 9)", lit(datetime.into()));
+		assert_eq!("toDecimal128('-12.34', 2)", lit(rust_decimal::Decimal::new(-1234, 2).into()));
+		assert_eq!("toIPv4('10.0.0.1')", lit(std::net::Ipv4Addr::new(10, 0, 0, 1).into()));
+		assert_eq!("toIPv6('::1')", lit(std::net::Ipv6Addr::LOCALHOST.into()));
+		assert_eq!(
+			"toUUID('67e55044-10b1-426f-9247-bb680e5fe0c8')",
+			lit("67e55044-10b1-426f-9247-bb680e5fe0c8".parse::<uuid::Uuid>().unwrap().into())
+		);
+
+		let array = "Array(Nullable(String))".parse::<DataType>().unwrap();
+		assert_eq!("CAST(['a', NULL] AS Array(Nullable(String)))", {
+			typed_literal(&vec![Some("a"), None].into(), &array).unwrap()
+		});
+		assert_eq!("CAST('2024-01-31' AS Date)", typed_literal(&"2024-01-31".into(), &DataType::Date).unwrap());
+		assert!(typed_literal(&Value::Null, &DataType::String).is_err());
+	}
+
+	#[test]
+	fn test_sql() {
+		assert_eq!("`a\\`b`", quote_identifier("a`b"));
+		assert_eq!("`db`.`my table`", quote_table("db.my table"));
+
+		let table = "events`; DROP TABLE users; --";
+		let sql = Sql::new("ALTER TABLE ")
+			.qualified("db", table)
+			.push(" DELETE WHERE name IN ")
+			.literals(["a", "b'c"])
+			.push(" AND (")
+			.identifiers(["x", "y"])
+			.push(") = ")
+			.literal((1, 2));
+		assert_eq!(
+			"ALTER TABLE `db`.`events\\`; DROP TABLE users; --` DELETE WHERE name IN ('a', 'b\\'c') AND (`x`, `y`) = (1, 2)",
+			sql.to_string()
+		);
+		let sql = Sql::new("SELECT ").typed_literal(1, &DataType::UInt64).unwrap().push_sql(&Sql::new(" AS x"));
+		assert_eq!("SELECT CAST(1 AS UInt64) AS x", String::from(sql));
+	}
+}
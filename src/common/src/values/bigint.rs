@@ -2,7 +2,7 @@
 
 macro_rules! impl_num_256 {
 	($name: ident, $to_ty: ty, $to_fn: ident) => {
-		#[derive(::serde::Serialize, ::serde::Deserialize, Hash, PartialEq, Eq)]
+		#[derive(::serde::Serialize, ::serde::Deserialize, Debug, Clone, Hash, PartialEq, Eq)]
 		pub struct $name(pub [u8; 32]);
 
 		impl $name {
@@ -16,6 +16,12 @@ macro_rules! impl_num_256 {
 				Self::$to_fn(value.0.as_slice())
 			}
 		}
+
+		impl ::std::fmt::Display for $name {
+			fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+				<$to_ty>::$to_fn(self.0.as_slice()).fmt(f)
+			}
+		}
 	};
 }
 
@@ -0,0 +1,385 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+
+use chrono::DateTime;
+use chrono::NaiveDate;
+use chrono::Utc;
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::values::ip::IpV4;
+use crate::values::ip::IpV6;
+use crate::values::value::Value;
+
+/// convert a [`Value`] to rust type.
+pub trait FromValue: Sized {
+	fn from_value(value: &Value) -> Result<Self>;
+}
+
+fn convert_error<T>(value: &Value) -> Error {
+	Error::ConvertError(format!("can't convert {:?} to {}", value, std::any::type_name::<T>()))
+}
+
+macro_rules! impl_from_value_int {
+	($($ty: ty),*) => {
+		$(
+			impl FromValue for $ty {
+				fn from_value(value: &Value) -> Result<Self> {
+					let converted = match *value {
+						Value::Int8(v) => v.try_into().ok(),
+						Value::Int16(v) => v.try_into().ok(),
+						Value::Int32(v) => v.try_into().ok(),
+						Value::Int64(v) => v.try_into().ok(),
+						Value::Int128(v) => v.try_into().ok(),
+						Value::UInt8(v) => v.try_into().ok(),
+						Value::UInt16(v) => v.try_into().ok(),
+						Value::UInt32(v) => v.try_into().ok(),
+						Value::UInt64(v) => v.try_into().ok(),
+						Value::UInt128(v) => v.try_into().ok(),
+						Value::Enum8(_, v) => v.try_into().ok(),
+						Value::Enum16(_, v) => v.try_into().ok(),
+						Value::Date(v) => v.try_into().ok(),
+						Value::Date32(v) => v.try_into().ok(),
+						Value::DateTime(v, _) => v.try_into().ok(),
+						Value::DateTime64(v, ..) => v.try_into().ok(),
+						_ => None,
+					};
+					converted.ok_or_else(|| convert_error::<Self>(value))
+				}
+			}
+		)*
+	};
+}
+
+impl_from_value_int!(i8, i16, i32, i64, i128, u8, u16, u32, u64, u128);
+
+impl FromValue for bool {
+	fn from_value(value: &Value) -> Result<Self> {
+		match *value {
+			Value::Bool(v) => Ok(v),
+			Value::UInt8(v) if v <= 1 => Ok(v == 1),
+			_ => Err(convert_error::<Self>(value)),
+		}
+	}
+}
+
+impl FromValue for f32 {
+	fn from_value(value: &Value) -> Result<Self> {
+		match *value {
+			Value::Float32(v) => Ok(v),
+			_ => Err(convert_error::<Self>(value)),
+		}
+	}
+}
+
+impl FromValue for f64 {
+	fn from_value(value: &Value) -> Result<Self> {
+		match *value {
+			Value::Float32(v) => Ok(v as f64),
+			Value::Float64(v) => Ok(v),
+			_ => Err(convert_error::<Self>(value)),
+		}
+	}
+}
+
+/// string is read as utf-8, other non-null values are converted to ClickHouse text format.
+impl FromValue for String {
+	fn from_value(value: &Value) -> Result<Self> {
+		match value {
+			Value::Null => Err(convert_error::<Self>(value)),
+			Value::String(v) => Ok(core::str::from_utf8(v)?.to_owned()),
+			v => Ok(v.to_string()),
+		}
+	}
+}
+
+impl FromValue for Value {
+	fn from_value(value: &Value) -> Result<Self> {
+		Ok(value.clone())
+	}
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+	fn from_value(value: &Value) -> Result<Self> {
+		match value {
+			Value::Null => Ok(None),
+			v => Ok(Some(T::from_value(v)?)),
+		}
+	}
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+	fn from_value(value: &Value) -> Result<Self> {
+		match value {
+			Value::Array(values) | Value::Tuple(values) => values.iter().map(T::from_value).collect(),
+			_ => Err(convert_error::<Self>(value)),
+		}
+	}
+}
+
+impl<K: FromValue + Eq + Hash, V: FromValue> FromValue for HashMap<K, V> {
+	fn from_value(value: &Value) -> Result<Self> {
+		match value {
+			Value::Map(map) => map.iter().map(|(k, v)| Ok((K::from_value(k)?, V::from_value(v)?))).collect(),
+			_ => Err(convert_error::<Self>(value)),
+		}
+	}
+}
+
+impl<K: FromValue + Ord, V: FromValue> FromValue for BTreeMap<K, V> {
+	fn from_value(value: &Value) -> Result<Self> {
+		match value {
+			Value::Map(map) => map.iter().map(|(k, v)| Ok((K::from_value(k)?, V::from_value(v)?))).collect(),
+			_ => Err(convert_error::<Self>(value)),
+		}
+	}
+}
+
+impl FromValue for Ipv4Addr {
+	fn from_value(value: &Value) -> Result<Self> {
+		match value {
+			Value::Ipv4(v) => Ok(v.to_addr()),
+			_ => Err(convert_error::<Self>(value)),
+		}
+	}
+}
+
+impl FromValue for Ipv6Addr {
+	fn from_value(value: &Value) -> Result<Self> {
+		match value {
+			Value::Ipv6(v) => Ok(v.to_addr()),
+			_ => Err(convert_error::<Self>(value)),
+		}
+	}
+}
+
+#[cfg(feature = "uuid")]
+impl FromValue for uuid::Uuid {
+	fn from_value(value: &Value) -> Result<Self> {
+		match value {
+			Value::Uuid(v) => Ok(v.to_uuid()),
+			_ => Err(convert_error::<Self>(value)),
+		}
+	}
+}
+
+#[cfg(feature = "decimal")]
+impl FromValue for rust_decimal::Decimal {
+	fn from_value(value: &Value) -> Result<Self> {
+		match value {
+			Value::Decimal(v, scale) => v.try_to_rust_decimal(*scale as u32),
+			_ => Err(convert_error::<Self>(value)),
+		}
+	}
+}
+
+impl FromValue for NaiveDate {
+	fn from_value(value: &Value) -> Result<Self> {
+		let days = match *value {
+			Value::Date(v) => v as i64,
+			Value::Date32(v) => v as i64,
+			_ => return Err(convert_error::<Self>(value)),
+		};
+		NaiveDate::default()
+			.checked_add_signed(chrono::Duration::days(days))
+			.ok_or_else(|| convert_error::<Self>(value))
+	}
+}
+
+impl FromValue for DateTime<Utc> {
+	fn from_value(value: &Value) -> Result<Self> {
+		let datetime = match *value {
+			Value::DateTime(secs, _) => DateTime::from_timestamp(secs as i64, 0),
+			Value::DateTime64(ticks, precision, _) if precision <= 9 => {
+				let scale = 10i64.pow(precision as u32);
+				let nanos = ticks.rem_euclid(scale) * 10i64.pow(9 - precision as u32);
+				DateTime::from_timestamp(ticks.div_euclid(scale), nanos as u32)
+			}
+			_ => None,
+		};
+		datetime.ok_or_else(|| convert_error::<Self>(value))
+	}
+}
+
+macro_rules! impl_value_from {
+	($($ty: ty => $variant: ident),*) => {
+		$(
+			impl From<$ty> for Value {
+				fn from(value: $ty) -> Self {
+					Value::$variant(value)
+				}
+			}
+		)*
+	};
+}
+
+impl_value_from!(
+	bool => Bool,
+	i8 => Int8,
+	i16 => Int16,
+	i32 => Int32,
+	i64 => Int64,
+	i128 => Int128,
+	u8 => UInt8,
+	u16 => UInt16,
+	u32 => UInt32,
+	u64 => UInt64,
+	u128 => UInt128,
+	f32 => Float32,
+	f64 => Float64
+);
+
+impl From<&str> for Value {
+	fn from(value: &str) -> Self {
+		Value::String(value.as_bytes().to_vec())
+	}
+}
+
+impl From<String> for Value {
+	fn from(value: String) -> Self {
+		Value::String(value.into_bytes())
+	}
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+	fn from(value: Option<T>) -> Self {
+		value.map(Into::into).unwrap_or(Value::Null)
+	}
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+	fn from(value: Vec<T>) -> Self {
+		Value::Array(value.into_iter().map(Into::into).collect())
+	}
+}
+
+impl<T: Into<Value> + Clone> From<&[T]> for Value {
+	fn from(value: &[T]) -> Self {
+		Value::Array(value.iter().cloned().map(Into::into).collect())
+	}
+}
+
+impl<K: Into<Value>, V: Into<Value>> From<HashMap<K, V>> for Value {
+	fn from(value: HashMap<K, V>) -> Self {
+		Value::Map(value.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+	}
+}
+
+impl<K: Into<Value>, V: Into<Value>> From<BTreeMap<K, V>> for Value {
+	fn from(value: BTreeMap<K, V>) -> Self {
+		Value::Map(value.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+	}
+}
+
+macro_rules! impl_value_from_tuple {
+	($($name: ident),+) => {
+		impl<$($name: Into<Value>),+> From<($($name,)+)> for Value {
+			#[allow(non_snake_case)]
+			fn from(($($name,)+): ($($name,)+)) -> Self {
+				Value::Tuple(vec![$($name.into()),+])
+			}
+		}
+	};
+}
+
+impl_value_from_tuple!(A);
+impl_value_from_tuple!(A, B);
+impl_value_from_tuple!(A, B, C);
+impl_value_from_tuple!(A, B, C, D);
+impl_value_from_tuple!(A, B, C, D, E);
+impl_value_from_tuple!(A, B, C, D, E, F);
+
+impl From<Ipv4Addr> for Value {
+	fn from(value: Ipv4Addr) -> Self {
+		let mut octets = value.octets();
+		octets.reverse();
+		Value::Ipv4(IpV4(octets))
+	}
+}
+
+impl From<Ipv6Addr> for Value {
+	fn from(value: Ipv6Addr) -> Self {
+		Value::Ipv6(IpV6(value.octets()))
+	}
+}
+
+#[cfg(feature = "uuid")]
+impl From<uuid::Uuid> for Value {
+	fn from(value: uuid::Uuid) -> Self {
+		let (high, low) = value.as_u64_pair();
+		let mut data = [0; 16];
+		data[..8].copy_from_slice(&high.to_le_bytes());
+		data[8..].copy_from_slice(&low.to_le_bytes());
+		Value::Uuid(crate::values::uuid::Uuid(data))
+	}
+}
+
+#[cfg(feature = "decimal")]
+impl From<rust_decimal::Decimal> for Value {
+	fn from(value: rust_decimal::Decimal) -> Self {
+		let decimal = crate::values::decimal::Decimal::I128(value.mantissa().to_le_bytes());
+		Value::Decimal(decimal, value.scale() as u8)
+	}
+}
+
+/// date is converted to `Date32` for its wider range.
+impl From<NaiveDate> for Value {
+	fn from(value: NaiveDate) -> Self {
+		Value::Date32(value.signed_duration_since(NaiveDate::default()).num_days() as i32)
+	}
+}
+
+/// datetime is converted to `DateTime64(9)` to keep nanoseconds.
+impl From<DateTime<Utc>> for Value {
+	fn from(value: DateTime<Utc>) -> Self {
+		let nanos = value.timestamp() * 1_000_000_000 + value.timestamp_subsec_nanos() as i64;
+		Value::DateTime64(nanos, 9, None)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net::Ipv4Addr;
+
+	use chrono::DateTime;
+	use chrono::NaiveDate;
+	use chrono::Utc;
+
+	use super::FromValue;
+	use crate::values::value::Value;
+
+	#[test]
+	fn test_from_value() {
+		assert_eq!(7i64, i64::from_value(&Value::UInt8(7)).unwrap());
+		assert!(u8::from_value(&Value::Int16(-1)).is_err());
+		assert_eq!(None, Option::<u8>::from_value(&Value::Null).unwrap());
+		assert_eq!(
+			vec!["a".to_owned()],
+			Vec::<String>::from_value(&Value::Array(vec![Value::String(b"a".to_vec())])).unwrap()
+		);
+		assert_eq!(NaiveDate::from_ymd_opt(1970, 1, 2).unwrap(), NaiveDate::from_value(&Value::Date(1)).unwrap());
+		assert_eq!(
+			DateTime::<Utc>::from_timestamp(1, 500_000_000).unwrap(),
+			DateTime::<Utc>::from_value(&Value::DateTime64(15, 1, None)).unwrap()
+		);
+	}
+
+	#[test]
+	fn test_into_value() {
+		let ip = Ipv4Addr::new(127, 0, 0, 1);
+		assert_eq!(ip, Ipv4Addr::from_value(&ip.into()).unwrap());
+		let uuid = uuid::Uuid::from_u128(0x0102030405060708090a0b0c0d0e0f10);
+		assert_eq!(uuid, uuid::Uuid::from_value(&uuid.into()).unwrap());
+		let decimal = rust_decimal::Decimal::new(-1234, 2);
+		assert_eq!(decimal, rust_decimal::Decimal::from_value(&decimal.into()).unwrap());
+		let date = NaiveDate::from_ymd_opt(1900, 1, 1).unwrap();
+		assert_eq!(date, NaiveDate::from_value(&date.into()).unwrap());
+		assert_eq!(
+			Value::Tuple(vec![Value::UInt8(1), Value::Array(vec![Value::String(b"a".to_vec()), Value::Null])]),
+			(1u8, vec![Some("a"), None]).into()
+		);
+	}
+}
@@ -3,7 +3,7 @@ use rust_decimal::Decimal as RustDecimal;
 use serde::Deserialize;
 use serde::Serialize;
 
-#[derive(Serialize, Deserialize, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, PartialEq, Eq)]
 pub enum Decimal {
 	I32([u8; 4]),
 	I64([u8; 8]),
@@ -52,6 +52,70 @@ impl Decimal {
 			}
 		}
 	}
+
+	/// format decimal with scale like ClickHouse does, e.g. `-12.340`.
+	pub fn to_string_with_scale(&self, scale: u8) -> String {
+		let num = match *self {
+			Decimal::I32(data) => i32::from_le_bytes(data).to_string(),
+			Decimal::I64(data) => i64::from_le_bytes(data).to_string(),
+			Decimal::I128(data) => i128::from_le_bytes(data).to_string(),
+			Decimal::I256(data) => i256_to_string(data),
+		};
+
+		let scale = scale as usize;
+		if scale == 0 {
+			return num;
+		}
+
+		let (sign, digits) = match num.strip_prefix('-') {
+			Some(digits) => ("-", digits),
+			None => ("", num.as_str()),
+		};
+		let digits = format!("{:0>width$}", digits, width = scale + 1);
+		let (int_part, frac_part) = digits.split_at(digits.len() - scale);
+		format!("{}{}.{}", sign, int_part, frac_part)
+	}
+}
+
+/// convert little endian two's complement 256-bit integer to decimal string.
+fn i256_to_string(data: [u8; 32]) -> String {
+	let negative = data[31] & 0x80 != 0;
+	let mut limbs = [0u64; 4];
+	for (idx, limb) in limbs.iter_mut().enumerate() {
+		*limb = u64::from_le_bytes(data[idx * 8..(idx + 1) * 8].try_into().unwrap());
+	}
+
+	if negative {
+		let mut carry = true;
+		for limb in limbs.iter_mut() {
+			*limb = !*limb;
+			if carry {
+				let (sum, overflow) = limb.overflowing_add(1);
+				*limb = sum;
+				carry = overflow;
+			}
+		}
+	}
+
+	let mut digits = Vec::new();
+	while limbs.iter().any(|limb| *limb != 0) {
+		let mut rem = 0u128;
+		for limb in limbs.iter_mut().rev() {
+			let cur = (rem << 64) | *limb as u128;
+			*limb = (cur / 10) as u64;
+			rem = cur % 10;
+		}
+		digits.push(b'0' + rem as u8);
+	}
+
+	if digits.is_empty() {
+		digits.push(b'0');
+	}
+	if negative {
+		digits.push(b'-');
+	}
+	digits.reverse();
+	String::from_utf8(digits).unwrap()
 }
 
 #[cfg(test)]
@@ -75,4 +139,15 @@ mod tests {
 		let _ = expect.set_scale(2);
 		assert_eq!(Some(expect), decimal256.try_to_rust_decimal(2).ok());
 	}
+
+	#[test]
+	fn test_to_string_with_scale() {
+		assert_eq!("1.05", Decimal::I32(105i32.to_le_bytes()).to_string_with_scale(2));
+		assert_eq!("-0.005", Decimal::I64((-5i64).to_le_bytes()).to_string_with_scale(3));
+		assert_eq!("42", Decimal::I128(42i128.to_le_bytes()).to_string_with_scale(0));
+
+		let mut arr = [0xffu8; 32];
+		arr[0] = 0x85;
+		assert_eq!("-1.23", Decimal::I256(arr).to_string_with_scale(2));
+	}
 }
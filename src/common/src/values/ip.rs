@@ -1,6 +1,6 @@
 macro_rules! impl_ip {
 	($name: ident, $d_ty: ty, $ip_ty: ty) => {
-		#[derive(::serde::Serialize, ::serde::Deserialize, Hash, PartialEq, Eq)]
+		#[derive(::serde::Serialize, ::serde::Deserialize, Debug, Clone, Hash, PartialEq, Eq)]
 		pub struct $name(pub $d_ty);
 
 		impl $name {
@@ -11,9 +11,13 @@ macro_rules! impl_ip {
 
 		impl From<$name> for $ip_ty {
 			fn from(value: $name) -> Self {
-				let mut arr = value.0;
-				arr.reverse();
-				Self::from(arr)
+				value.to_addr()
+			}
+		}
+
+		impl ::std::fmt::Display for $name {
+			fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+				self.to_addr().fmt(f)
 			}
 		}
 
@@ -29,10 +33,32 @@ macro_rules! impl_ip {
 impl_ip!(IpV4, [u8; 4], std::net::Ipv4Addr);
 impl_ip!(IpV6, [u8; 16], std::net::Ipv6Addr);
 
+impl IpV4 {
+	/// IPv4 is sent as little endian UInt32.
+	pub fn to_addr(&self) -> std::net::Ipv4Addr {
+		let mut arr = self.0;
+		arr.reverse();
+		arr.into()
+	}
+}
+
+impl IpV6 {
+	/// IPv6 is sent in network byte order.
+	pub fn to_addr(&self) -> std::net::Ipv6Addr {
+		self.0.into()
+	}
+}
+
 #[cfg(test)]
 mod tests {
+	use std::net::Ipv6Addr;
+	use std::sync::Arc;
+
+	use crate::metadata::DataType;
+	use crate::rowbinary;
 	use crate::values::ip::IpV4;
 	use crate::values::ip::IpV6;
+	use crate::values::value::Value;
 
 	#[test]
 	fn test_ipv4() {
@@ -44,9 +70,35 @@ mod tests {
 	#[test]
 	fn test_ipv6() {
 		let mut arr = [0u8; 16];
-		arr[0] = 1;
+		arr[15] = 1;
 		let ipv6 = IpV6::new(arr);
 		let ip: String = ipv6.into();
 		assert_eq!("::1", &ip);
 	}
+
+	#[test]
+	fn test_wire_bytes() {
+		// `SELECT toIPv4('127.0.0.1'), toIPv6('2001:db8::1') FORMAT RowBinary`
+		let wire = [&[1u8, 0, 0, 127][..], &[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]].concat();
+		let metadata = Arc::new(vec![("v4".to_owned(), DataType::Ipv4), ("v6".to_owned(), DataType::Ipv6)]);
+		let row = rowbinary::read_row(&mut wire.as_slice(), &metadata).unwrap();
+		let [Value::Ipv4(v4), Value::Ipv6(v6)] = row.values() else {
+			panic!("{:?}", row);
+		};
+		assert_eq!(("127.0.0.1".to_owned(), "2001:db8::1".to_owned()), (v4.to_string(), v6.to_string()));
+		assert_eq!("2001:db8::1".parse::<Ipv6Addr>().unwrap(), Ipv6Addr::from(v6.clone()));
+
+		let json = row.values().iter().map(Value::to_json).collect::<Vec<_>>();
+		assert_eq!(r#"["127.0.0.1","2001:db8::1"]"#, serde_json::to_string(&json).unwrap());
+		let mut encoded = Vec::new();
+		for (json, (_, data_type)) in json.iter().zip(metadata.iter()) {
+			let addr = json.as_str().unwrap();
+			let value = match data_type {
+				DataType::Ipv4 => Value::from(addr.parse::<std::net::Ipv4Addr>().unwrap()),
+				_ => Value::from(addr.parse::<Ipv6Addr>().unwrap()),
+			};
+			rowbinary::write_value(&mut encoded, &value, data_type).unwrap();
+		}
+		assert_eq!(wire, encoded);
+	}
 }
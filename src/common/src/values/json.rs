@@ -3,7 +3,7 @@ use serde::Serialize;
 
 use crate::Result;
 
-#[derive(Serialize, Deserialize, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, PartialEq, Eq)]
 pub struct Json(pub Vec<u8>);
 
 impl Json {
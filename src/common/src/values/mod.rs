@@ -1,10 +1,13 @@
 #[cfg(feature = "bigint")]
 pub mod bigint;
+pub mod convert;
 #[cfg(feature = "decimal")]
 pub mod decimal;
 pub mod ip;
 #[cfg(feature = "json")]
 pub mod json;
+mod param;
+pub(crate) mod text;
 #[cfg(feature = "uuid")]
 pub mod uuid;
 pub mod value;
@@ -0,0 +1,195 @@
+use crate::error::Error;
+use crate::error::Result;
+use crate::metadata::DataType;
+use crate::values::text::format_date;
+use crate::values::text::write_quoted;
+use crate::values::value::Value;
+
+impl Value {
+	/// format value as query parameter of the data type, e.g. `param_id=42` for `{id:UInt64}`.
+	///
+	/// Parameters are parsed by server in the escaped text format, so top level strings are
+	/// escaped and nested ones are quoted. Datetimes are sent as unix timestamps, so they don't
+	/// depend on the timezone of server.
+	pub fn to_param(&self, data_type: &DataType) -> Result<String> {
+		let mut text = String::new();
+		write_param(&mut text, self, data_type, false)?;
+		Ok(text)
+	}
+}
+
+fn mismatch(value: &Value, data_type: &DataType) -> Error {
+	Error::ConvertError(format!("can't use {:?} as parameter of {}", value, data_type))
+}
+
+fn write_param(out: &mut String, value: &Value, data_type: &DataType, nested: bool) -> Result<()> {
+	match (value, data_type) {
+		(_, DataType::LowCardinality(inner)) => write_param(out, value, inner, nested)?,
+		(Value::Null, DataType::Nullable(_)) => out.push_str(if nested { "NULL" } else { "\\N" }),
+		(value, DataType::Nullable(inner)) => write_param(out, value, inner, nested)?,
+		(Value::Null, _) => return Err(mismatch(value, data_type)),
+		(Value::Array(values) | Value::Tuple(values), DataType::Array(inner)) => {
+			out.push('[');
+			write_separated(out, values.iter(), |out, value| write_param(out, value, inner, true))?;
+			out.push(']');
+		}
+		(Value::Array(values) | Value::Tuple(values), DataType::Tuple(fields)) if values.len() == fields.len() => {
+			out.push('(');
+			write_separated(out, values.iter().zip(fields), |out, (value, (_, field))| {
+				write_param(out, value, field, true)
+			})?;
+			out.push(')');
+		}
+		(Value::Map(map), DataType::Map(key_type, value_type)) => {
+			out.push('{');
+			write_separated(out, map.iter(), |out, (key, value)| {
+				write_param(out, key, key_type, true)?;
+				out.push(':');
+				write_param(out, value, value_type, true)
+			})?;
+			out.push('}');
+		}
+		(_, DataType::Array(_) | DataType::Tuple(_) | DataType::Map(..)) => return Err(mismatch(value, data_type)),
+		(Value::Array(_) | Value::Tuple(_) | Value::Map(_), _) => return Err(mismatch(value, data_type)),
+		(Value::Bool(v), data_type) if *data_type != DataType::Bool => out.push(if *v { '1' } else { '0' }),
+		(_, DataType::Date | DataType::Date32) => {
+			let text = match *value {
+				Value::Date(days) => format_date(days as i32),
+				Value::Date32(days) => format_date(days),
+				Value::DateTime(secs, _) => format_date((secs / 86400) as i32),
+				Value::DateTime64(ticks, precision, _) => {
+					format_date(ticks.div_euclid(10i64.pow(precision as u32)).div_euclid(86400) as i32)
+				}
+				_ => value.to_string(),
+			};
+			write_text(out, &text, nested);
+		}
+		(_, DataType::DateTime(_)) => {
+			let text = match *value {
+				Value::Date(days) => (days as i64 * 86400).to_string(),
+				Value::Date32(days) => (days as i64 * 86400).to_string(),
+				Value::DateTime(secs, _) => secs.to_string(),
+				Value::DateTime64(ticks, precision, _) => ticks.div_euclid(10i64.pow(precision as u32)).to_string(),
+				_ => value.to_string(),
+			};
+			write_text(out, &text, nested);
+		}
+		(_, DataType::DateTime64(precision, _)) => {
+			let text = match *value {
+				Value::Date(days) => (days as i64 * 86400).to_string(),
+				Value::Date32(days) => (days as i64 * 86400).to_string(),
+				Value::DateTime(secs, _) => secs.to_string(),
+				Value::DateTime64(ticks, from, _) => format_ticks(ticks as i128, from, *precision),
+				_ => value.to_string(),
+			};
+			write_text(out, &text, nested);
+		}
+		(_, data_type) if is_string_like(data_type) => write_text(out, &value.to_string(), nested),
+		(Value::String(_), _) if nested => write_quoted(out, &value.to_string()).map_err(fmt_error)?,
+		(value, _) => write_text(out, &value.to_string(), false),
+	}
+	Ok(())
+}
+
+/// format ticks of `from` precision as unix timestamp of `to` precision, e.g. `1706702400.050`.
+fn format_ticks(ticks: i128, from: u8, to: u8) -> String {
+	let ticks = match to >= from {
+		true => ticks * 10i128.pow((to - from) as u32),
+		false => ticks.div_euclid(10i128.pow((from - to) as u32)),
+	};
+	let scale = 10i128.pow(to as u32);
+	match to {
+		0 => ticks.to_string(),
+		_ => format!("{}.{:0>width$}", ticks.div_euclid(scale), ticks.rem_euclid(scale), width = to as usize),
+	}
+}
+
+fn is_string_like(data_type: &DataType) -> bool {
+	matches!(
+		data_type,
+		DataType::String
+			| DataType::FixedString(_)
+			| DataType::Uuid
+			| DataType::Ipv4
+			| DataType::Ipv6
+			| DataType::Enum8(_)
+			| DataType::Enum16(_)
+			| DataType::Json
+	)
+}
+
+/// write text quoted if nested, otherwise escaped.
+fn write_text(out: &mut String, s: &str, nested: bool) {
+	if nested {
+		let _ = write_quoted(out, s);
+		return;
+	}
+
+	for c in s.chars() {
+		match c {
+			'\\' => out.push_str("\\\\"),
+			'\t' => out.push_str("\\t"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\0' => out.push_str("\\0"),
+			c => out.push(c),
+		}
+	}
+}
+
+fn write_separated<I, F>(out: &mut String, iter: I, mut write_fn: F) -> Result<()>
+where
+	I: Iterator,
+	F: FnMut(&mut String, I::Item) -> Result<()>,
+{
+	for (idx, item) in iter.enumerate() {
+		if idx > 0 {
+			out.push(',');
+		}
+		write_fn(out, item)?;
+	}
+	Ok(())
+}
+
+fn fmt_error(e: std::fmt::Error) -> Error {
+	Error::ConvertError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeMap;
+
+	use chrono::DateTime;
+	use chrono::NaiveDate;
+	use chrono::Utc;
+
+	use crate::metadata::DataType;
+	use crate::values::value::Value;
+
+	fn param(value: impl Into<Value>, data_type: &str) -> String {
+		value.into().to_param(&data_type.parse::<DataType>().unwrap()).unwrap()
+	}
+
+	#[test]
+	fn test_to_param() {
+		assert_eq!("42", param(42, "UInt64"));
+		assert_eq!("1", param(true, "UInt8"));
+		assert_eq!("it's\\ta\\\\b\\n", param("it's\ta\\b\n", "String"));
+		assert_eq!("\\N", param(None::<u8>, "Nullable(UInt8)"));
+		assert_eq!("['it\\'s',NULL]", param(vec![Some("it's"), None], "Array(Nullable(String))"));
+		assert_eq!("(1,'a\\tb')", param((1, "a\tb"), "Tuple(UInt8, String)"));
+		assert_eq!("{'k':[1,2]}", param(BTreeMap::from([("k", vec![1, 2])]), "Map(String, Array(Int32))"));
+		assert_eq!("-12.34", param(rust_decimal::Decimal::new(-1234, 2), "Decimal(9, 2)"));
+		assert_eq!("['127.0.0.1']", param(vec![std::net::Ipv4Addr::LOCALHOST], "Array(IPv4)"));
+		assert_eq!("1900-01-01", param(NaiveDate::from_ymd_opt(1900, 1, 1).unwrap(), "Date32"));
+
+		let datetime = DateTime::<Utc>::from_timestamp(1706702400, 50_000_000).unwrap();
+		assert_eq!("1706702400", param(datetime, "DateTime('Asia/Shanghai')"));
+		assert_eq!("1706702400.050", param(datetime, "DateTime64(3)"));
+		assert_eq!("['1706702400']", param(vec![datetime], "Array(DateTime)"));
+		assert_eq!("2024-01-31 12:00:00", param("2024-01-31 12:00:00", "DateTime"));
+
+		assert!(Value::from(1).to_param(&DataType::Array(Box::new(DataType::UInt8))).is_err());
+		assert!(Value::Null.to_param(&DataType::UInt8).is_err());
+	}
+}
@@ -0,0 +1,100 @@
+use std::fmt::Write;
+
+use chrono::DateTime;
+use chrono::NaiveDate;
+use chrono::TimeZone;
+use chrono::Utc;
+
+use crate::metadata::Tz;
+
+/// days between 0001-01-01 and 1970-01-01.
+const UNIX_EPOCH_DAYS_FROM_CE: i32 = 719_163;
+
+/// format days since 1970-01-01 like `2024-01-31`.
+pub(crate) fn format_date(days: i32) -> String {
+	match NaiveDate::from_num_days_from_ce_opt(UNIX_EPOCH_DAYS_FROM_CE + days) {
+		Some(date) => date.format("%Y-%m-%d").to_string(),
+		None => days.to_string(),
+	}
+}
+
+/// format seconds since epoch like `2024-01-31 12:00:00`, UTC is used if no timezone.
+pub(crate) fn format_datetime(secs: i64, tz: Option<&Tz>) -> String {
+	format_timestamp(secs, 0, tz).unwrap_or_else(|| secs.to_string())
+}
+
+/// format ticks since epoch like `2024-01-31 12:00:00.123`, UTC is used if no timezone.
+pub(crate) fn format_datetime64(ticks: i64, precision: u8, tz: Option<&Tz>) -> String {
+	let scale = 10i64.pow(precision as u32);
+	let (secs, frac) = (ticks.div_euclid(scale), ticks.rem_euclid(scale));
+	let nanos = if precision <= 9 { frac * 10i64.pow(9 - precision as u32) } else { 0 };
+	let Some(mut text) = format_timestamp(secs, nanos as u32, tz) else {
+		return ticks.to_string();
+	};
+
+	if precision > 0 {
+		let _ = write!(text, ".{:0>width$}", frac, width = precision as usize);
+	}
+	text
+}
+
+fn format_timestamp(secs: i64, nanos: u32, tz: Option<&Tz>) -> Option<String> {
+	let datetime = DateTime::<Utc>::from_timestamp(secs, nanos)?;
+	let format = "%Y-%m-%d %H:%M:%S";
+	Some(match tz {
+		Some(tz) => tz.inner().from_utc_datetime(&datetime.naive_utc()).format(format).to_string(),
+		None => datetime.format(format).to_string(),
+	})
+}
+
+/// format float like ClickHouse: `nan`, `inf`, `-inf` or the shortest representation.
+pub(crate) fn format_float<T: Into<f64> + ToString + Copy>(num: T) -> String {
+	let num_f64: f64 = num.into();
+	if num_f64.is_nan() {
+		"nan".to_owned()
+	} else if num_f64.is_infinite() {
+		if num_f64 > 0.0 { "inf" } else { "-inf" }.to_owned()
+	} else {
+		num.to_string()
+	}
+}
+
+/// write string quoted by `'`, special characters are escaped by backslash.
+pub(crate) fn write_quoted<W: Write>(w: &mut W, s: &str) -> std::fmt::Result {
+	w.write_char('\'')?;
+	for c in s.chars() {
+		match c {
+			'\'' => w.write_str("\\'")?,
+			'\\' => w.write_str("\\\\")?,
+			'\n' => w.write_str("\\n")?,
+			'\t' => w.write_str("\\t")?,
+			'\r' => w.write_str("\\r")?,
+			'\0' => w.write_str("\\0")?,
+			'\x08' => w.write_str("\\b")?,
+			'\x0c' => w.write_str("\\f")?,
+			c => w.write_char(c)?,
+		}
+	}
+	w.write_char('\'')
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_format() {
+		assert_eq!("1970-01-01", format_date(0));
+		assert_eq!("1969-12-31", format_date(-1));
+		assert_eq!("2024-01-31 12:00:00", format_datetime(1706702400, None));
+		assert_eq!("2024-01-31 15:00:00", format_datetime(1706702400, Some(&"Europe/Istanbul".parse().unwrap())));
+		assert_eq!("2024-01-31 12:00:00.050", format_datetime64(1706702400050, 3, None));
+		assert_eq!("1969-12-31 23:59:59.9", format_datetime64(-1, 1, None));
+		assert_eq!("-inf", format_float(f64::NEG_INFINITY));
+		assert_eq!("0.1", format_float(0.1f32));
+
+		let mut s = String::new();
+		write_quoted(&mut s, "it's\t\\").unwrap();
+		assert_eq!("'it\\'s\\t\\\\'", s);
+	}
+}
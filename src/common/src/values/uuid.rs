@@ -1,24 +1,49 @@
 use serde::Deserialize;
 use serde::Serialize;
 
-#[derive(Serialize, Deserialize, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, PartialEq, Eq)]
 pub struct Uuid(pub [u8; 16]);
 
 impl Uuid {
 	pub fn new(data: [u8; 16]) -> Uuid {
 		Uuid(data)
 	}
+
+	/// ClickHouse stores uuid as two little endian u64, high half first.
+	pub fn to_uuid(&self) -> uuid::Uuid {
+		let high = u64::from_le_bytes(self.0[..8].try_into().unwrap());
+		let low = u64::from_le_bytes(self.0[8..].try_into().unwrap());
+		uuid::Uuid::from_u64_pair(high, low)
+	}
 }
 
 impl From<Uuid> for uuid::Uuid {
 	fn from(value: Uuid) -> Self {
-		Self::from_bytes_le(value.0)
+		value.to_uuid()
 	}
 }
 
 impl From<Uuid> for String {
 	fn from(value: Uuid) -> Self {
-		let uuid: uuid::Uuid = value.into();
-		uuid.to_string()
+		value.to_uuid().to_string()
+	}
+}
+
+impl std::fmt::Display for Uuid {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		self.to_uuid().fmt(f)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Uuid;
+
+	#[test]
+	fn test_uuid() {
+		let mut arr = [0u8; 16];
+		arr[..8].copy_from_slice(&0x61f0c4045cb311e7u64.to_le_bytes());
+		arr[8..].copy_from_slice(&0x907ba6006ad3dba0u64.to_le_bytes());
+		assert_eq!("61f0c404-5cb3-11e7-907b-a6006ad3dba0", Uuid::new(arr).to_string());
 	}
 }
@@ -1,12 +1,21 @@
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::metadata::DataType;
+use crate::metadata::Tz;
 use crate::values::ip::IpV4;
 use crate::values::ip::IpV6;
+use crate::values::text::format_date;
+use crate::values::text::format_datetime;
+use crate::values::text::format_datetime64;
+use crate::values::text::format_float;
+use crate::values::text::write_quoted;
 use std::collections::HashMap;
+use std::fmt::Display;
+use std::fmt::Formatter;
 use std::hash::Hash;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Value {
 	Null,
 	Bool(bool),
@@ -33,12 +42,15 @@ pub enum Value {
 	Uuid(crate::values::uuid::Uuid),
 	Date(u16),
 	Date32(i32),
-	DateTime(u32),
-	DateTime64(u64),
+	/// seconds since epoch and timezone.
+	DateTime(u32, Option<Tz>),
+	/// ticks since epoch, precision and timezone.
+	DateTime64(i64, u8, Option<Tz>),
+	/// decimal and its scale.
 	#[cfg(feature = "decimal")]
-	Decimal(crate::values::decimal::Decimal),
-	Enum8(Vec<Value>),
-	Enum16(Vec<Value>),
+	Decimal(crate::values::decimal::Decimal, u8),
+	Enum8(String, i8),
+	Enum16(String, i16),
 	#[cfg(feature = "json")]
 	Json(crate::values::json::Json),
 	Tuple(Vec<Value>),
@@ -49,6 +61,8 @@ pub enum Value {
 impl Hash for Value {
 	fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
 		match self {
+			Self::Null => 0.hash(state),
+			Self::Bool(b) => b.hash(state),
 			Self::String(s) => s.hash(state),
 			Self::Int8(i) => i.hash(state),
 			Self::Int16(i) => i.hash(state),
@@ -62,13 +76,15 @@ impl Hash for Value {
 			Self::UInt64(i) => i.hash(state),
 			Self::UInt128(i) => i.hash(state),
 			Self::UInt256(i) => i.hash(state),
+			Self::Ipv4(i) => i.hash(state),
+			Self::Ipv6(i) => i.hash(state),
 			Self::Uuid(i) => i.hash(state),
 			Self::Date(i) => i.hash(state),
 			Self::Date32(i) => i.hash(state),
-			Self::DateTime(i) => i.hash(state),
-			Self::DateTime64(i) => i.hash(state),
-			Self::Enum8(i) => i.hash(state),
-			Self::Enum16(i) => i.hash(state),
+			Self::DateTime(i, _) => i.hash(state),
+			Self::DateTime64(i, ..) => i.hash(state),
+			Self::Enum8(_, i) => i.hash(state),
+			Self::Enum16(_, i) => i.hash(state),
 			_ => unimplemented!("Hash trait unimplemented"),
 		}
 	}
@@ -100,24 +116,290 @@ impl PartialEq for Value {
 			(Self::Uuid(l0), Self::Uuid(r0)) => *l0 == *r0,
 			(Self::Date(l0), Self::Date(r0)) => *l0 == *r0,
 			(Self::Date32(l0), Self::Date32(r0)) => *l0 == *r0,
-			(Self::DateTime(l0), Self::DateTime(r0)) => *l0 == *r0,
-			(Self::DateTime64(l0), Self::DateTime64(r0)) => *l0 == *r0,
-			(Self::Decimal(l0), Self::Decimal(r0)) => *l0 == *r0,
-			(Self::Enum8(l0), Self::Enum8(r0)) => *l0 == *r0,
-			(Self::Enum16(l0), Self::Enum16(r0)) => *l0 == *r0,
+			(Self::DateTime(l0, l1), Self::DateTime(r0, r1)) => *l0 == *r0 && *l1 == *r1,
+			(Self::DateTime64(l0, l1, l2), Self::DateTime64(r0, r1, r2)) => *l0 == *r0 && *l1 == *r1 && *l2 == *r2,
+			#[cfg(feature = "decimal")]
+			(Self::Decimal(l0, l1), Self::Decimal(r0, r1)) => *l0 == *r0 && *l1 == *r1,
+			(Self::Enum8(l0, l1), Self::Enum8(r0, r1)) => *l0 == *r0 && *l1 == *r1,
+			(Self::Enum16(l0, l1), Self::Enum16(r0, r1)) => *l0 == *r0 && *l1 == *r1,
 			(Self::Json(l0), Self::Json(r0)) => *l0 == *r0,
 			(Self::Tuple(l0), Self::Tuple(r0)) => *l0 == *r0,
 			(Self::Array(l0), Self::Array(r0)) => *l0 == *r0,
+			(Self::Map(l0), Self::Map(r0)) => *l0 == *r0,
+			(Self::Null, Self::Null) => true,
 			_ => false,
 		}
 	}
 }
 
+impl Value {
+	/// format value in ClickHouse text format, string like values are quoted if nested.
+	fn fmt_text(&self, f: &mut Formatter<'_>, nested: bool) -> std::fmt::Result {
+		let quote = |f: &mut Formatter<'_>, s: &str| if nested { write_quoted(f, s) } else { f.write_str(s) };
+		match self {
+			Value::Null => f.write_str("NULL"),
+			Value::Bool(v) => v.fmt(f),
+			Value::Int8(v) => v.fmt(f),
+			Value::Int16(v) => v.fmt(f),
+			Value::Int32(v) => v.fmt(f),
+			Value::Int64(v) => v.fmt(f),
+			Value::Int128(v) => v.fmt(f),
+			#[cfg(feature = "bigint")]
+			Value::Int256(v) => v.fmt(f),
+			Value::UInt8(v) => v.fmt(f),
+			Value::UInt16(v) => v.fmt(f),
+			Value::UInt32(v) => v.fmt(f),
+			Value::UInt64(v) => v.fmt(f),
+			Value::UInt128(v) => v.fmt(f),
+			#[cfg(feature = "bigint")]
+			Value::UInt256(v) => v.fmt(f),
+			Value::Float32(v) => f.write_str(&format_float(*v)),
+			Value::Float64(v) => f.write_str(&format_float(*v)),
+			Value::String(v) => quote(f, &String::from_utf8_lossy(v)),
+			Value::Ipv4(v) => quote(f, &v.to_string()),
+			Value::Ipv6(v) => quote(f, &v.to_string()),
+			#[cfg(feature = "uuid")]
+			Value::Uuid(v) => quote(f, &v.to_string()),
+			Value::Date(v) => quote(f, &format_date(*v as i32)),
+			Value::Date32(v) => quote(f, &format_date(*v)),
+			Value::DateTime(v, tz) => quote(f, &format_datetime(*v as i64, tz.as_ref())),
+			Value::DateTime64(v, precision, tz) => quote(f, &format_datetime64(*v, *precision, tz.as_ref())),
+			#[cfg(feature = "decimal")]
+			Value::Decimal(v, scale) => f.write_str(&v.to_string_with_scale(*scale)),
+			Value::Enum8(name, _) => quote(f, name),
+			Value::Enum16(name, _) => quote(f, name),
+			#[cfg(feature = "json")]
+			Value::Json(v) => quote(f, &String::from_utf8_lossy(&v.0)),
+			Value::Tuple(values) => {
+				f.write_str("(")?;
+				fmt_separated(f, values.iter(), |f, value| value.fmt_text(f, true))?;
+				f.write_str(")")
+			}
+			Value::Array(values) => {
+				f.write_str("[")?;
+				fmt_separated(f, values.iter(), |f, value| value.fmt_text(f, true))?;
+				f.write_str("]")
+			}
+			Value::Map(map) => {
+				f.write_str("{")?;
+				fmt_separated(f, map.iter(), |f, (key, value)| {
+					key.fmt_text(f, true)?;
+					f.write_str(":")?;
+					value.fmt_text(f, true)
+				})?;
+				f.write_str("}")
+			}
+		}
+	}
+
+	/// data type of the value, e.g. the type written before value of `Dynamic`.
+	///
+	/// Element type of array and map is the type of the first non-null element, it's nullable if
+	/// any element is NULL.
+	pub fn data_type(&self) -> DataType {
+		match self {
+			Value::Null => DataType::Nullable(Box::new(DataType::Nothing)),
+			Value::Bool(_) => DataType::Bool,
+			Value::Int8(_) => DataType::Int8,
+			Value::Int16(_) => DataType::Int16,
+			Value::Int32(_) => DataType::Int32,
+			Value::Int64(_) => DataType::Int64,
+			Value::Int128(_) => DataType::Int128,
+			#[cfg(feature = "bigint")]
+			Value::Int256(_) => DataType::Int256,
+			Value::UInt8(_) => DataType::UInt8,
+			Value::UInt16(_) => DataType::UInt16,
+			Value::UInt32(_) => DataType::UInt32,
+			Value::UInt64(_) => DataType::UInt64,
+			Value::UInt128(_) => DataType::UInt128,
+			#[cfg(feature = "bigint")]
+			Value::UInt256(_) => DataType::UInt256,
+			Value::Float32(_) => DataType::Float32,
+			Value::Float64(_) => DataType::Float64,
+			Value::String(_) => DataType::String,
+			Value::Ipv4(_) => DataType::Ipv4,
+			Value::Ipv6(_) => DataType::Ipv6,
+			#[cfg(feature = "uuid")]
+			Value::Uuid(_) => DataType::Uuid,
+			Value::Date(_) => DataType::Date,
+			Value::Date32(_) => DataType::Date32,
+			Value::DateTime(_, tz) => DataType::DateTime(tz.clone()),
+			Value::DateTime64(_, precision, tz) => DataType::DateTime64(*precision, tz.clone()),
+			#[cfg(feature = "decimal")]
+			Value::Decimal(v, scale) => {
+				use crate::values::decimal::Decimal;
+
+				let precision = match v {
+					Decimal::I32(_) => 9,
+					Decimal::I64(_) => 18,
+					Decimal::I128(_) => 38,
+					Decimal::I256(_) => 76,
+				};
+				DataType::Decimal(precision, *scale)
+			}
+			Value::Enum8(name, num) => DataType::Enum8(vec![(name.clone(), *num)]),
+			Value::Enum16(name, num) => DataType::Enum16(vec![(name.clone(), *num)]),
+			#[cfg(feature = "json")]
+			Value::Json(_) => DataType::Json,
+			Value::Tuple(values) => {
+				DataType::Tuple(values.iter().map(|value| (String::new(), value.data_type())).collect())
+			}
+			Value::Array(values) => DataType::Array(Box::new(common_type(values.iter()))),
+			Value::Map(map) => DataType::Map(Box::new(common_type(map.keys())), Box::new(common_type(map.values()))),
+		}
+	}
+
+	/// convert value to json like JSONEachRow format of ClickHouse does, 64-bit and larger integers
+	/// are quoted, non-finite floats are null. Decimals of more significant digits than f64 keeps
+	/// are quoted too, unless `arbitrary_precision` feature keeps them exact.
+	#[cfg(feature = "json")]
+	pub fn to_json(&self) -> serde_json::Value {
+		use serde_json::Number;
+		use serde_json::Value as Json;
+
+		let number = |text: String| {
+			let number = match cfg!(feature = "arbitrary_precision") {
+				true => text.parse::<Number>().ok(),
+				// the parser of serde_json may round the last digit.
+				false => text.parse::<f64>().ok().and_then(Number::from_f64),
+			};
+			number.map(Json::Number).unwrap_or(Json::String(text))
+		};
+		let decimal = |text: String| {
+			let digits = text.trim_start_matches(['-', '0', '.']).trim_end_matches(['0', '.']).replace('.', "");
+			match cfg!(feature = "arbitrary_precision") || digits.len() <= MAX_EXACT_DIGITS {
+				true => number(text),
+				false => Json::String(text),
+			}
+		};
+		match self {
+			Value::Null => Json::Null,
+			Value::Bool(v) => Json::Bool(*v),
+			Value::Int8(v) => Json::from(*v),
+			Value::Int16(v) => Json::from(*v),
+			Value::Int32(v) => Json::from(*v),
+			Value::UInt8(v) => Json::from(*v),
+			Value::UInt16(v) => Json::from(*v),
+			Value::UInt32(v) => Json::from(*v),
+			Value::Float32(v) if !v.is_finite() => Json::Null,
+			Value::Float64(v) if !v.is_finite() => Json::Null,
+			Value::Float32(_) | Value::Float64(_) => number(self.to_string()),
+			#[cfg(feature = "decimal")]
+			Value::Decimal(..) => decimal(self.to_string()),
+			Value::Json(v) => v.try_to_serde_json().unwrap_or_else(|_| Json::String(self.to_string())),
+			Value::Tuple(values) | Value::Array(values) => Json::Array(values.iter().map(Value::to_json).collect()),
+			Value::Map(map) => {
+				Json::Object(map.iter().map(|(key, value)| (key.to_string(), value.to_json())).collect())
+			}
+			_ => Json::String(self.to_string()),
+		}
+	}
+}
+
+/// significant decimal digits kept by f64.
+#[cfg(feature = "json")]
+const MAX_EXACT_DIGITS: usize = 15;
+
+/// type of values in array or map.
+fn common_type<'a>(values: impl Iterator<Item = &'a Value>) -> DataType {
+	let mut data_type = None;
+	let mut nullable = false;
+	for value in values {
+		match value {
+			Value::Null => nullable = true,
+			value if data_type.is_none() => data_type = Some(value.data_type()),
+			_ => (),
+		}
+	}
+	match data_type {
+		Some(data_type @ (DataType::Array(_) | DataType::Map(..) | DataType::Tuple(_))) => data_type,
+		Some(data_type) if nullable => DataType::Nullable(Box::new(data_type)),
+		Some(data_type) => data_type,
+		None if nullable => DataType::Nullable(Box::new(DataType::Nothing)),
+		None => DataType::Nothing,
+	}
+}
+
+fn fmt_separated<I, F>(f: &mut Formatter<'_>, iter: I, mut fmt_fn: F) -> std::fmt::Result
+where
+	I: Iterator,
+	F: FnMut(&mut Formatter<'_>, I::Item) -> std::fmt::Result,
+{
+	for (idx, item) in iter.enumerate() {
+		if idx > 0 {
+			f.write_str(",")?;
+		}
+		fmt_fn(f, item)?;
+	}
+	Ok(())
+}
+
+/// format value like ClickHouse text format, e.g. `['a','b']`, `{'k':1}`, `2024-01-31 12:00:00`.
+impl Display for Value {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		self.fmt_text(f, false)
+	}
+}
+
 #[cfg(test)]
 mod tests {
+	use std::collections::HashMap;
+
+	use super::Value;
+	use crate::values::decimal::Decimal;
+	use crate::values::ip::IpV4;
 
 	#[test]
 	fn test() {
 		assert_eq!(1, 1);
 	}
+
+	#[test]
+	fn test_display() {
+		assert_eq!("it's", Value::String(b"it's".to_vec()).to_string());
+		assert_eq!("nan", Value::Float64(f64::NAN).to_string());
+		assert_eq!("-12.50", Value::Decimal(Decimal::I32((-1250i32).to_le_bytes()), 2).to_string());
+		assert_eq!("2024-01-31 12:00:00.5", Value::DateTime64(17067024005, 1, None).to_string());
+		assert_eq!(
+			"['it\\'s',NULL,'127.0.0.1']",
+			Value::Array(vec![Value::String(b"it's".to_vec()), Value::Null, Value::Ipv4(IpV4::new([1, 0, 0, 127]))])
+				.to_string()
+		);
+		assert_eq!(
+			"(1,'a',{'k':'2024-01-31'})",
+			Value::Tuple(vec![
+				Value::UInt8(1),
+				Value::Enum8("a".to_owned(), 1),
+				Value::Map(HashMap::from([(Value::String(b"k".to_vec()), Value::Date(19753))])),
+			])
+			.to_string()
+		);
+		assert_eq!("\u{fffd}", Value::String(vec![0xff]).to_string());
+	}
+
+	#[test]
+	fn test_to_json() {
+		let value = Value::Tuple(vec![
+			Value::UInt32(1),
+			Value::Int64(-2),
+			Value::Float32(0.1),
+			Value::Float64(f64::INFINITY),
+			Value::Decimal(Decimal::I64(12345i64.to_le_bytes()), 3),
+			Value::DateTime(1706702400, Some("Asia/Shanghai".parse().unwrap())),
+			Value::Map(HashMap::from([(Value::UInt8(1), Value::String(b"v".to_vec()))])),
+		]);
+		assert_eq!(
+			r#"[1,"-2",0.1,null,12.345,"2024-01-31 20:00:00",{"1":"v"}]"#,
+			serde_json::to_string(&value.to_json()).unwrap()
+		);
+
+		let decimal = Value::Decimal(Decimal::I128(12345678901234567891i128.to_le_bytes()), 2);
+		let expected = match cfg!(feature = "arbitrary_precision") {
+			true => "123456789012345678.91",
+			false => r#""123456789012345678.91""#,
+		};
+		assert_eq!(expected, serde_json::to_string(&decimal.to_json()).unwrap());
+		let decimal = Value::Decimal(Decimal::I128((-123456789012345i128).to_le_bytes()), 2);
+		assert_eq!("-1234567890123.45", serde_json::to_string(&decimal.to_json()).unwrap());
+	}
 }
@@ -0,0 +1,148 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use rickhouse_common::metadata::DataType;
+use syn::ext::IdentExt;
+use syn::parse_macro_input;
+use syn::spanned::Spanned;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Fields;
+use syn::LitStr;
+use syn::Path;
+
+/// Derive `RowSchema` for struct with named fields.
+///
+/// Field attributes:
+/// - `#[row(rename = "name")]`: column name, field name is used by default.
+/// - `#[row(skip)]`: field is not a column.
+/// - `#[row(default)]`: column may be absent, field takes its default value.
+/// - `#[row(type = "LowCardinality(String)")]`: column type, inferred from field type by default.
+///   It's checked at compile time.
+/// - `#[row(flatten)]`: columns of the field are inlined, field type must implement `RowSchema`.
+///
+/// Container attributes:
+/// - `#[row(crate = "path")]`: path of the crate exporting `metadata` module, `::rickhouse` by
+///   default, e.g. `rickhouse_common` for users of it without `rickhouse`.
+///
+/// Row attributes only describe columns, pair them with serde attributes like `#[serde(skip)]` to
+/// keep (de)serialization consistent.
+#[proc_macro_derive(Row, attributes(row))]
+pub fn derive_row(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	expand(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+	rename: Option<String>,
+	skip: bool,
+	default: bool,
+	data_type: Option<LitStr>,
+	flatten: bool,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+	let crate_path = parse_crate_path(&input)?;
+	let fields = match &input.data {
+		Data::Struct(data) => match &data.fields {
+			Fields::Named(fields) => &fields.named,
+			fields => return Err(syn::Error::new(fields.span(), "Row only supports structs with named fields")),
+		},
+		_ => return Err(syn::Error::new(input.span(), "Row only supports structs")),
+	};
+
+	let mut pushes = Vec::new();
+	for field in fields {
+		let attrs = parse_field_attrs(field)?;
+		if attrs.skip {
+			continue;
+		}
+
+		let ty = &field.ty;
+		if attrs.flatten {
+			pushes.push(quote! {
+				columns.extend(<#ty as #crate_path::metadata::RowSchema>::columns());
+			});
+			continue;
+		}
+
+		let name = attrs.rename.unwrap_or_else(|| field.ident.as_ref().unwrap().unraw().to_string());
+		let data_type = match attrs.data_type {
+			Some(lit) => {
+				if let Err(e) = lit.value().parse::<DataType>() {
+					let msg = format!("invalid type {:?} of column `{}`: {}", lit.value(), name, e);
+					return Err(syn::Error::new(lit.span(), msg));
+				}
+				quote! { #lit.parse::<#crate_path::metadata::DataType>().expect("type is checked by derive") }
+			}
+			None => quote! { <#ty as #crate_path::metadata::ColumnType>::data_type() },
+		};
+		let default = attrs.default;
+		pushes.push(quote! {
+			columns.push(#crate_path::metadata::SchemaColumn {
+				name: #name.to_owned(),
+				data_type: #data_type,
+				default: #default,
+			});
+		});
+	}
+
+	let name = &input.ident;
+	let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+	Ok(quote! {
+		impl #impl_generics #crate_path::metadata::RowSchema for #name #ty_generics #where_clause {
+			fn columns() -> ::std::vec::Vec<#crate_path::metadata::SchemaColumn> {
+				let mut columns = ::std::vec::Vec::new();
+				#(#pushes)*
+				columns
+			}
+		}
+	})
+}
+
+fn parse_crate_path(input: &DeriveInput) -> syn::Result<Path> {
+	let mut path = syn::parse_quote!(::rickhouse);
+	for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("row")) {
+		attr.parse_nested_meta(|meta| {
+			if meta.path.is_ident("crate") {
+				path = meta.value()?.parse::<LitStr>()?.parse()?;
+				Ok(())
+			} else {
+				Err(meta.error("unsupported row attribute"))
+			}
+		})?;
+	}
+	Ok(path)
+}
+
+fn parse_field_attrs(field: &syn::Field) -> syn::Result<FieldAttrs> {
+	let mut attrs = FieldAttrs::default();
+	for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("row")) {
+		attr.parse_nested_meta(|meta| {
+			if meta.path.is_ident("rename") {
+				attrs.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+			} else if meta.path.is_ident("skip") {
+				attrs.skip = true;
+			} else if meta.path.is_ident("default") {
+				attrs.default = true;
+			} else if meta.path.is_ident("type") {
+				let lit = meta.value()?.parse::<LitStr>()?;
+				if lit.value().trim().is_empty() {
+					return Err(syn::Error::new(lit.span(), "type can't be empty"));
+				}
+				attrs.data_type = Some(lit);
+			} else if meta.path.is_ident("flatten") {
+				attrs.flatten = true;
+			} else {
+				return Err(meta.error("unsupported row attribute"));
+			}
+			Ok(())
+		})?;
+	}
+
+	if attrs.flatten && (attrs.rename.is_some() || attrs.data_type.is_some()) {
+		return Err(syn::Error::new(field.span(), "flatten can't be used with rename or type"));
+	}
+	Ok(attrs)
+}
@@ -0,0 +1,9 @@
+#[derive(rickhouse_derive::Row)]
+#[row(crate = "rickhouse_common")]
+struct Event {
+	id: u64,
+	#[row(type = "LowCardinality(Strnig)")]
+	kind: String,
+}
+
+fn main() {}